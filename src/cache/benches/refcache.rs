@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use scope_backend_cache::async_list::refcache::CacheReferences;
+use scope_chat::async_list::AsyncListIndex;
+
+const SIZES: [u64; 3] = [1_000, 10_000, 50_000];
+// the cached part of the list starts this far down, leaving room to page up into
+const PAGE: u64 = 1_000;
+
+/// How `CacheReferences` used to find things: by scanning every segment, item by item.
+#[derive(Default)]
+struct LinearReferences {
+  segments: HashMap<u64, Vec<u64>>,
+}
+
+impl LinearReferences {
+  fn find(&self, item: u64) -> Option<(u64, usize)> {
+    self.segments.iter().find_map(|(id, segment)| segment.iter().position(|haystack| *haystack == item).map(|index| (*id, index)))
+  }
+
+  fn get(&self, index: AsyncListIndex<u64>) -> Option<u64> {
+    match index {
+      AsyncListIndex::After(item) => self.find(item).and_then(|(id, index)| self.segments[&id].get(index + 1).copied()),
+      AsyncListIndex::Before(item) => self.find(item).and_then(|(id, index)| self.segments[&id].get(index.checked_sub(1)?).copied()),
+      _ => unimplemented!(),
+    }
+  }
+
+  fn insert_before(&mut self, anchor: u64, item: u64) {
+    let (id, index) = self.find(anchor).unwrap();
+
+    self.segments.get_mut(&id).unwrap().insert(index, item);
+  }
+}
+
+// a channel cached from the bottom up, plus some detached messages (e.g. from jumping to them) to search past
+fn indexed(size: u64) -> CacheReferences<u64> {
+  let mut references = CacheReferences::new();
+
+  for item in PAGE..PAGE + size {
+    references.append_bottom(item);
+  }
+
+  for item in 0..10 {
+    references.insert_detached(PAGE + size * 2 + item * 10);
+  }
+
+  references
+}
+
+fn linear(size: u64) -> LinearReferences {
+  let mut references = LinearReferences::default();
+
+  references.segments.insert(0, (PAGE..PAGE + size).collect());
+
+  for item in 0..10 {
+    references.segments.insert(item + 1, vec![PAGE + size * 2 + item * 10]);
+  }
+
+  references
+}
+
+fn lookup(c: &mut Criterion) {
+  let mut group = c.benchmark_group("get_before");
+
+  for size in SIZES {
+    // near the bottom, where the newest messages are looked at most, and the furthest a scan from the top has to go
+    let index = AsyncListIndex::Before(PAGE + size - 1);
+
+    let references = indexed(size);
+    group.bench_with_input(BenchmarkId::new("indexed", size), &size, |b, _| {
+      b.iter(|| references.get(black_box(index)))
+    });
+
+    let references = linear(size);
+    group.bench_with_input(BenchmarkId::new("linear", size), &size, |b, _| {
+      b.iter(|| references.get(black_box(index)))
+    });
+  }
+
+  group.finish();
+}
+
+// paging up past the top of what is cached, one item at a time, like scrolling back through a channel
+fn page_up(c: &mut Criterion) {
+  let mut group = c.benchmark_group("page_up");
+
+  for size in SIZES {
+    group.bench_with_input(BenchmarkId::new("indexed", size), &size, |b, size| {
+      b.iter_batched(
+        || indexed(*size),
+        |mut references| {
+          for item in (0..PAGE).rev() {
+            references.insert(AsyncListIndex::Before(item + 1), item, false, false);
+          }
+
+          references
+        },
+        criterion::BatchSize::LargeInput,
+      )
+    });
+
+    group.bench_with_input(BenchmarkId::new("linear", size), &size, |b, size| {
+      b.iter_batched(
+        || linear(*size),
+        |mut references| {
+          for item in (0..PAGE).rev() {
+            references.insert_before(item + 1, item);
+          }
+
+          references
+        },
+        criterion::BatchSize::LargeInput,
+      )
+    });
+  }
+
+  group.finish();
+}
+
+criterion_group!(benches, lookup, page_up);
+criterion_main!(benches);
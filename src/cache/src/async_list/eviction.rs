@@ -0,0 +1,48 @@
+/// How many items message caches keep before dropping the ones furthest from what is being looked at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvictionPolicy {
+  /// Items each list keeps, `None` for no limit
+  pub max_items_per_list: Option<usize>,
+  /// Items kept across every list, taken from the least recently used lists first. `None` for no limit
+  pub max_items: Option<usize>,
+}
+
+impl Default for EvictionPolicy {
+  fn default() -> Self {
+    EvictionPolicy {
+      max_items_per_list: Some(1_000),
+      max_items: Some(10_000),
+    }
+  }
+}
+
+impl EvictionPolicy {
+  pub fn unbounded() -> Self {
+    EvictionPolicy {
+      max_items_per_list: None,
+      max_items: None,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+  /// Lookups the cache could answer
+  pub hits: u64,
+  /// Lookups that had to go to the backend
+  pub misses: u64,
+  /// Items dropped to stay within an `EvictionPolicy`
+  pub evictions: u64,
+}
+
+impl std::ops::Add for CacheMetrics {
+  type Output = CacheMetrics;
+
+  fn add(self, other: CacheMetrics) -> CacheMetrics {
+    CacheMetrics {
+      hits: self.hits + other.hits,
+      misses: self.misses + other.misses,
+      evictions: self.evictions + other.evictions,
+    }
+  }
+}
@@ -1,16 +1,33 @@
+pub mod eviction;
 pub mod refcache;
 pub mod refcacheslice;
 pub mod tests;
 
 use std::collections::HashMap;
 
+use eviction::CacheMetrics;
 use refcache::CacheReferences;
-use refcacheslice::Exists;
-use scope_chat::async_list::{AsyncListIndex, AsyncListItem, AsyncListResult};
+use refcacheslice::{CacheReferencesSlice, Exists};
+use scope_chat::async_list::{AsyncListGap, AsyncListIndex, AsyncListItem, AsyncListResult};
+
+/// A dense run of items, top first, as it is written to and read back from disk.
+#[derive(Clone, Debug)]
+pub struct CacheSegment<I> {
+  pub is_top: bool,
+  pub is_bottom: bool,
+  pub items: Vec<I>,
+}
 
 pub struct AsyncListCache<I: AsyncListItem> {
   cache_refs: CacheReferences<I::Identifier>,
   cache_map: HashMap<I::Identifier, I>,
+
+  // when each item was last looked at, by `clock`, and which item that was most recently
+  last_used: HashMap<I::Identifier, u64>,
+  clock: u64,
+  hot: Option<I::Identifier>,
+
+  metrics: CacheMetrics,
 }
 
 impl<I: AsyncListItem> Default for AsyncListCache<I> {
@@ -24,13 +41,78 @@ impl<I: AsyncListItem> AsyncListCache<I> {
     Self {
       cache_refs: CacheReferences::new(),
       cache_map: HashMap::new(),
+
+      last_used: HashMap::new(),
+      clock: 0,
+      hot: None,
+
+      metrics: CacheMetrics::default(),
     }
   }
 
+  /// A cache for items whose identifiers sort the same way as the list does, top first. Knowing that, it can place
+  /// detached items and tell where the gaps between segments are.
+  pub fn ordered() -> Self
+  where
+    I::Identifier: Ord,
+  {
+    Self::new().into_ordered()
+  }
+
+  /// Switches this cache to identifier order, see `ordered`.
+  pub fn into_ordered(mut self) -> Self
+  where
+    I::Identifier: Ord,
+  {
+    self.cache_refs = self.cache_refs.into_ordered();
+    self
+  }
+
+  /// Rebuilds a cache from segments that don't overlap or touch each other. Empty segments are skipped.
+  pub fn from_segments(segments: Vec<CacheSegment<I>>) -> Self {
+    let mut cache = Self::new();
+
+    for segment in segments {
+      if segment.items.is_empty() {
+        continue;
+      }
+
+      let item_references = segment.items.iter().map(|item| item.get_list_identifier()).collect();
+
+      for item in segment.items {
+        cache.last_used.insert(item.get_list_identifier(), 0);
+        cache.cache_map.insert(item.get_list_identifier(), item);
+      }
+
+      cache.cache_refs.insert_segment(CacheReferencesSlice::new(item_references, segment.is_top, segment.is_bottom));
+    }
+
+    cache
+  }
+
+  pub fn segments(&self) -> Vec<CacheSegment<I>> {
+    self
+      .cache_refs
+      .segments()
+      .map(|segment| CacheSegment {
+        is_top: segment.is_bounded_at_top,
+        is_bottom: segment.is_bounded_at_bottom,
+        items: segment.iter().map(|identifier| self.cache_map.get(identifier).unwrap().clone()).collect(),
+      })
+      .collect()
+  }
+
+  /// Stops treating the bottom item as the bottom of the list, e.g. because the list may have grown since it was
+  /// cached. Returns that item's identifier.
+  pub fn unbound_bottom(&mut self) -> Option<I::Identifier> {
+    self.cache_refs.unbound_bottom()
+  }
+
   pub fn append_bottom(&mut self, value: I) {
     let identifier = value.get_list_identifier();
 
     self.cache_refs.append_bottom(identifier.clone());
+    self.last_used.insert(identifier.clone(), self.clock);
     self.cache_map.insert(identifier, value);
   }
 
@@ -38,19 +120,100 @@ impl<I: AsyncListItem> AsyncListCache<I> {
     let identifier = value.get_list_identifier();
 
     self.cache_map.insert(identifier.clone(), value);
+    self.last_used.insert(identifier.clone(), self.clock);
     self.cache_refs.insert(index, identifier.clone(), is_top, is_bottom);
   }
 
+  /// Inserts a page of items, top first, next to `anchor`. See `CacheReferences::insert_range`.
+  pub fn insert_range(&mut self, anchor: AsyncListIndex<I::Identifier>, items: Vec<I>, is_top: bool, is_bottom: bool) {
+    let identifiers = items.iter().map(|item| item.get_list_identifier()).collect();
+
+    for item in items {
+      self.last_used.insert(item.get_list_identifier(), self.clock);
+      self.cache_map.insert(item.get_list_identifier(), item);
+    }
+
+    self.cache_refs.insert_range(anchor, identifiers, is_top, is_bottom);
+  }
+
   /// you mut **KNOW** that the item you are inserting is not:
   ///  - directly next to (Before or After) **any** item in the list
   ///  - the first or last item in the list
+  ///
+  /// unless the cache is ordered, in which case an item that falls inside a segment is put in its place there.
   pub fn insert_detached(&mut self, value: I) {
     let identifier = value.get_list_identifier();
 
     self.cache_map.insert(identifier.clone(), value);
+    self.last_used.insert(identifier.clone(), self.clock);
     self.cache_refs.insert_detached(identifier);
   }
 
+  /// Replaces the stored value of an item that is already in the cache, keeping its position.
+  /// Returns the previous value, or `None` (without inserting anything) if the item isn't cached.
+  pub fn update(&mut self, value: I) -> Option<I> {
+    let identifier = value.get_list_identifier();
+
+    let existing = self.cache_map.get_mut(&identifier)?;
+
+    Some(std::mem::replace(existing, value))
+  }
+
+  /// Removes an item that no longer exists in the list, returning it if it was cached.
+  pub fn remove(&mut self, identifier: &I::Identifier) -> Option<I> {
+    if self.hot.as_ref() == Some(identifier) {
+      self.hot = None;
+    }
+
+    self.cache_refs.remove(identifier);
+    self.last_used.remove(identifier);
+    self.cache_map.remove(identifier)
+  }
+
+  pub fn len(&self) -> usize {
+    self.cache_map.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.cache_map.is_empty()
+  }
+
+  /// Marks an item as the one being looked at, which keeps it and its neighbours around the longest.
+  /// Items returned by `get` are marked automatically.
+  pub fn touch(&mut self, identifier: &I::Identifier) {
+    if !self.cache_map.contains_key(identifier) {
+      return;
+    }
+
+    self.clock += 1;
+    self.last_used.insert(identifier.clone(), self.clock);
+    self.hot = Some(identifier.clone());
+  }
+
+  /// Drops the least recently used items until at most `max_items` are left, keeping the bounds of what's left
+  /// accurate. Returns how many were dropped.
+  pub fn evict(&mut self, max_items: usize) -> usize {
+    let last_used = &self.last_used;
+    let evicted = self.cache_refs.evict(max_items, self.hot.as_ref(), |identifier| last_used.get(identifier).copied().unwrap_or(0));
+
+    for identifier in &evicted {
+      if self.hot.as_ref() == Some(identifier) {
+        self.hot = None;
+      }
+
+      self.last_used.remove(identifier);
+      self.cache_map.remove(identifier);
+    }
+
+    self.metrics.evictions += evicted.len() as u64;
+
+    evicted.len()
+  }
+
+  pub fn metrics(&self) -> CacheMetrics {
+    self.metrics
+  }
+
   pub fn bounded_at_top_by(&self) -> Option<I::Identifier> {
     self.cache_refs.top_bound()
   }
@@ -59,10 +222,18 @@ impl<I: AsyncListItem> AsyncListCache<I> {
     self.cache_refs.bottom_bound()
   }
 
-  pub fn get(&self, index: AsyncListIndex<I::Identifier>) -> Exists<AsyncListResult<I>> {
+  pub fn get(&mut self, index: AsyncListIndex<I::Identifier>) -> Exists<AsyncListResult<I>> {
     let cache_result = self.cache_refs.get(index.clone());
 
+    if let Exists::Unknown = cache_result {
+      self.metrics.misses += 1;
+    } else {
+      self.metrics.hits += 1;
+    }
+
     if let Exists::Yes(cache_result) = cache_result {
+      self.touch(&cache_result);
+
       let content = self.cache_map.get(&cache_result).unwrap().clone();
       let is_top = self.cache_refs.top_bound().map(|v| v == content.get_list_identifier()).unwrap_or(false);
       let is_bottom = self.cache_refs.bottom_bound().map(|v| v == content.get_list_identifier()).unwrap_or(false);
@@ -77,6 +248,21 @@ impl<I: AsyncListItem> AsyncListCache<I> {
     Exists::Unknown
   }
 
+  /// Whether there are items between `a` and `b` that haven't been loaded. See `CacheReferences::has_gap_between`.
+  pub fn has_gap_between(&self, a: &I::Identifier, b: &I::Identifier) -> Option<bool> {
+    self.cache_refs.has_gap_between(a, b)
+  }
+
+  /// The gap at `index`, if the item next to the one it's relative to hasn't been loaded, but others further along
+  /// have. Only ordered caches know about gaps.
+  pub fn gap(&self, index: AsyncListIndex<I::Identifier>) -> Option<AsyncListGap<I::Identifier>> {
+    match index {
+      AsyncListIndex::After(above) => self.cache_refs.next_segment_below(&above).map(|below| AsyncListGap { above, below }),
+      AsyncListIndex::Before(below) => self.cache_refs.next_segment_above(&below).map(|above| AsyncListGap { above, below }),
+      _ => None,
+    }
+  }
+
   pub fn find(&self, identifier: &I::Identifier) -> Option<I> {
     self.cache_map.get(identifier).cloned()
   }
@@ -1,48 +1,124 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{
+  cmp::Ordering,
+  collections::{BTreeMap, HashMap},
+  fmt::Debug,
+  hash::Hash,
+  ops::Bound,
+};
 
 use scope_chat::async_list::AsyncListIndex;
 
-use super::refcacheslice::{self, CacheReferencesSlice, Exists};
+use super::refcacheslice::{CacheReferencesSlice, Exists};
 
-pub struct CacheReferences<I: Clone + Eq + PartialEq> {
+pub struct CacheReferences<I: Clone + Eq + Hash> {
   // dense segments are unordered (spooky!) slices of content we do! know about.
   // the u64 in the hashmap represents a kind of "segment identifier"
   dense_segments: HashMap<u64, CacheReferencesSlice<I>>,
 
+  // which segment each item is in, so nothing has to search through the segments
+  segment_index: HashMap<I, u64>,
+
   top_bounded_identifier: Option<u64>,
   bottom_bounded_identifier: Option<u64>,
+
+  // for lists whose identifiers sort the same way as the list does (top first), which places segments relative to
+  // each other. segments are dense, so two whose ranges overlap are really one
+  order: Option<fn(&I, &I) -> Ordering>,
+
+  // the segments by their first item, when the references are ordered, so finding where an item falls among them
+  // doesn't mean looking at every one
+  segment_starts: BTreeMap<SegmentStart<I>, u64>,
+}
+
+// a segment's first item, sorted by the references' order
+struct SegmentStart<I> {
+  item: I,
+  order: fn(&I, &I) -> Ordering,
+}
+
+impl<I> SegmentStart<I> {
+  fn new(item: I, order: fn(&I, &I) -> Ordering) -> Self {
+    SegmentStart { item, order }
+  }
+}
+
+impl<I> PartialEq for SegmentStart<I> {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other).is_eq()
+  }
+}
+
+impl<I> Eq for SegmentStart<I> {}
+
+impl<I> PartialOrd for SegmentStart<I> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
 }
 
-impl<I: Clone + Eq + PartialEq> Default for CacheReferences<I> {
+impl<I> Ord for SegmentStart<I> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (self.order)(&self.item, &other.item)
+  }
+}
+
+impl<I: Clone + Eq + Hash> Default for CacheReferences<I> {
   fn default() -> Self {
     Self::new()
   }
 }
 
-impl<I: Clone + Eq + PartialEq> CacheReferences<I> {
+impl<I: Clone + Eq + Hash> CacheReferences<I> {
   pub fn new() -> Self {
     Self {
       dense_segments: HashMap::new(),
+      segment_index: HashMap::new(),
       top_bounded_identifier: None,
       bottom_bounded_identifier: None,
+      order: None,
+      segment_starts: BTreeMap::new(),
     }
   }
 
-  pub fn append_bottom(&mut self, identifier: I) {
-    let mut id = None;
+  /// References for a list whose identifiers sort the same way as the list does, top first.
+  pub fn ordered() -> Self
+  where
+    I: Ord,
+  {
+    Self::new().into_ordered()
+  }
 
-    for (segment_id, segment) in self.dense_segments.iter() {
-      if let Exists::Yes(_) = segment.get(AsyncListIndex::RelativeToBottom(0)) {
-        if id.is_some() {
-          panic!("There should only be one bottom bound segment");
-        }
+  /// Starts keeping these references in identifier order, joining up any segments that turn out to overlap.
+  pub fn into_ordered(mut self) -> Self
+  where
+    I: Ord,
+  {
+    self.order = Some(I::cmp);
+
+    for (id, segment) in &self.dense_segments {
+      self.segment_starts.insert(SegmentStart::new(segment.first().clone(), I::cmp), *id);
+    }
+
+    let ids = self.dense_segments.keys().copied().collect::<Vec<_>>();
 
-        id = Some(*segment_id)
+    for id in ids {
+      if self.dense_segments.contains_key(&id) {
+        self.absorb_overlaps(id);
       }
     }
 
-    if let Some(id) = id {
+    self
+  }
+
+  pub fn is_ordered(&self) -> bool {
+    self.order.is_some()
+  }
+
+  pub fn append_bottom(&mut self, identifier: I) {
+    if let Some(id) = self.bottom_bounded_identifier {
+      self.segment_index.insert(identifier.clone(), id);
       self.dense_segments.get_mut(&id).unwrap().append_bottom(identifier);
+      self.absorb_overlaps(id);
     } else {
       self.insert(AsyncListIndex::RelativeToBottom(0), identifier, false, true);
     }
@@ -54,7 +130,7 @@ impl<I: Clone + Eq + PartialEq> CacheReferences<I> {
 
     assert!(top_bound.is_bounded_at_top);
 
-    Some(top_bound.item_references.first().unwrap().clone())
+    Some(top_bound.first().clone())
   }
 
   pub fn bottom_bound(&self) -> Option<I> {
@@ -63,136 +139,669 @@ impl<I: Clone + Eq + PartialEq> CacheReferences<I> {
 
     assert!(bottom_bound.is_bounded_at_bottom);
 
-    Some(bottom_bound.item_references.last().unwrap().clone())
+    Some(bottom_bound.last().clone())
   }
 
-  pub fn get(&self, index: AsyncListIndex<I>) -> Exists<I> {
-    for segment in self.dense_segments.values() {
-      let result = segment.get(index.clone());
+  pub fn segments(&self) -> impl Iterator<Item = &CacheReferencesSlice<I>> {
+    self.dense_segments.values()
+  }
 
-      if let Exists::Yes(value) = result {
-        return Exists::Yes(value);
-      } else if let Exists::No = result {
-        return Exists::No;
-      }
+  /// Adds a segment that doesn't overlap or touch any existing one.
+  pub fn insert_segment(&mut self, segment: CacheReferencesSlice<I>) {
+    let id = rand::random();
+
+    if segment.is_bounded_at_top {
+      self.top_bounded_identifier = Some(id);
+    }
+
+    if segment.is_bounded_at_bottom {
+      self.bottom_bounded_identifier = Some(id);
+    }
+
+    for item in segment.iter() {
+      self.segment_index.insert(item.clone(), id);
     }
 
-    Exists::Unknown
+    self.add_segment(id, segment);
+  }
+
+  /// Forgets which item is at the bottom of the list, keeping the item itself, and returns it.
+  pub fn unbound_bottom(&mut self) -> Option<I> {
+    let id = self.bottom_bounded_identifier.take()?;
+    let segment = self.dense_segments.get_mut(&id).unwrap();
+
+    segment.is_bounded_at_bottom = false;
+
+    Some(segment.last().clone())
+  }
+
+  pub fn get(&self, index: AsyncListIndex<I>) -> Exists<I> {
+    match self.segment_for(&index) {
+      Some(id) => self.dense_segments[&id].get(index),
+      None => Exists::Unknown,
+    }
   }
 
   /// you mut **KNOW** that the item you are inserting is not:
   ///  - directly next to (Before or After) **any** item in the list
   ///  - the first or last item in the list
+  ///
+  /// unless the references are ordered, in which case an item that falls inside a segment is put in its place there.
   pub fn insert_detached(&mut self, item: I) {
-    self.dense_segments.insert(
-      rand::random(),
-      CacheReferencesSlice {
-        is_bounded_at_top: false,
-        is_bounded_at_bottom: false,
+    if let Some(order) = self.order {
+      if self.segment_index.contains_key(&item) {
+        return;
+      }
+
+      if let Some(id) = self.segment_containing(&item) {
+        self.segment_index.insert(item.clone(), id);
+        self.edit_segment(id, |segment| segment.insert_sorted(item, order));
+
+        return;
+      }
+    }
+
+    self.insert_segment(CacheReferencesSlice::new(vec![item], false, false));
+  }
 
-        item_references: vec![item],
-      },
-    );
+  /// Whether there are items between `a` and `b` that aren't known: `Some(false)` if they're in the same segment and
+  /// `Some(true)` if they aren't. Uncached items can only be placed if the references are ordered, so otherwise
+  /// asking about them gives `None`.
+  pub fn has_gap_between(&self, a: &I, b: &I) -> Option<bool> {
+    match (self.segment_containing(a), self.segment_containing(b)) {
+      (Some(a), Some(b)) => Some(a != b),
+      _ if self.order.is_some() => Some(true),
+      _ => None,
+    }
+  }
+
+  /// The top of the next segment down, if `item` is the bottom of its segment and that isn't the bottom of the list.
+  /// Only ordered references know where their segments are relative to each other, so others return `None`.
+  pub fn next_segment_below(&self, item: &I) -> Option<I> {
+    let order = self.order?;
+    let segment = &self.dense_segments[&self.segment_of(item)?];
+
+    if segment.last() != item || segment.is_bounded_at_bottom {
+      return None;
+    }
+
+    let below = self.segment_starts.range((Bound::Excluded(SegmentStart::new(item.clone(), order)), Bound::Unbounded)).next();
+
+    below.map(|(start, _)| start.item.clone())
+  }
+
+  /// The bottom of the next segment up, if `item` is the top of its segment and that isn't the top of the list.
+  /// Only ordered references know where their segments are relative to each other, so others return `None`.
+  pub fn next_segment_above(&self, item: &I) -> Option<I> {
+    let order = self.order?;
+    let segment = &self.dense_segments[&self.segment_of(item)?];
+
+    if segment.first() != item || segment.is_bounded_at_top {
+      return None;
+    }
+
+    let above = self.segment_starts.range(..SegmentStart::new(item.clone(), order)).next_back();
+
+    above.map(|(_, id)| self.dense_segments[id].last().clone())
+  }
+
+  /// Removes an item that no longer exists in the list.
+  ///
+  /// The items on either side of a removed item become neighbours, so its segment shrinks rather than splits.
+  /// Returns `false` if the item wasn't in any segment.
+  pub fn remove(&mut self, item: &I) -> bool {
+    let Some(segment_id) = self.segment_index.remove(item) else {
+      return false;
+    };
+
+    if self.dense_segments[&segment_id].len() > 1 {
+      self.edit_segment(segment_id, |segment| {
+        let index = segment.find_index_of(item).unwrap();
+
+        segment.remove_at(index);
+      });
+
+      return true;
+    }
+
+    // segments must not be empty, so the whole segment goes, and with it whatever we knew about its bounds. The
+    // nearest segment left isn't the new end, as whatever is between it and the removed item was never loaded
+    self.take_segment(segment_id);
+
+    if self.top_bounded_identifier == Some(segment_id) {
+      self.top_bounded_identifier = None;
+    }
+
+    if self.bottom_bounded_identifier == Some(segment_id) {
+      self.bottom_bounded_identifier = None;
+    }
+
+    true
   }
 
   pub fn insert(&mut self, index: AsyncListIndex<I>, item: I, is_top: bool, is_bottom: bool) {
+    self.place(index, item.clone(), is_top, is_bottom);
+
+    if let Some(id) = self.segment_of(&item) {
+      self.absorb_overlaps(id);
+    }
+  }
+
+  fn place(&mut self, index: AsyncListIndex<I>, item: I, is_top: bool, is_bottom: bool) {
     // insert routine is really complex:
     // an insert can "join" together 2 segments
     // an insert can append to a segment
     // or an insert can construct a new segment
 
-    let mut segments = vec![];
+    // an item we already know about only tells us who its neighbour is, which can join the two segments
+    if let Some(existing) = self.segment_of(&item) {
+      self.join_existing(index, &item, existing, is_top, is_bottom);
+
+      return;
+    }
+
+    // items are only in one segment, so only one segment can have a place for the new one
+    let candidate = self.segment_for(&index).filter(|id| self.dense_segments[id].can_insert(index.clone()).is_some());
 
-    for (i, segment) in self.dense_segments.iter() {
-      if let Some(position) = segment.can_insert(index.clone()) {
-        segments.push((position, *i));
+    if let Some(id) = candidate {
+      self.segment_index.insert(item.clone(), id);
+      self.edit_segment(id, |segment| segment.insert(index, item, is_bottom, is_top));
+
+      if is_top {
+        self.top_bounded_identifier = Some(id)
       }
+      if is_bottom {
+        self.bottom_bounded_identifier = Some(id)
+      }
+    } else {
+      // nothing before the top, or after the bottom, so these can only start a bounded segment
+      let is_top = is_top || matches!(index, AsyncListIndex::RelativeToTop(0));
+      let is_bottom = is_bottom || matches!(index, AsyncListIndex::RelativeToBottom(0));
+
+      self.insert_segment(CacheReferencesSlice::new(vec![item], is_top, is_bottom));
     }
+  }
 
-    if segments.is_empty() {
-      let id = rand::random();
+  /// Inserts a run of items, top first, next to `anchor`: below it for `After`, above it for `Before`, and for the
+  /// relative indices ending up that many items away from that end of the list, like `insert`.
+  ///
+  /// The run is added to the anchor's segment in one pass, and any segment it runs into is joined up with it.
+  /// `is_top` and `is_bottom` mean the run starts at the top, or ends at the bottom, of the list. An empty run that
+  /// does means the anchor is that end instead.
+  pub fn insert_range(&mut self, anchor: AsyncListIndex<I>, items: Vec<I>, is_top: bool, is_bottom: bool) {
+    let (anchor, implied_top, implied_bottom) = self.resolve_anchor(anchor);
+
+    let (Some(first), Some(last)) = (items.first().cloned(), items.last().cloned()) else {
+      match anchor {
+        Some(AsyncListIndex::After(item)) if is_bottom || implied_bottom => self.bound_bottom(&item),
+        Some(AsyncListIndex::Before(item)) if is_top || implied_top => self.bound_top(&item),
+        _ => {}
+      }
 
-      self.dense_segments.insert(
-        id,
-        CacheReferencesSlice {
-          is_bounded_at_top: is_top,
-          is_bounded_at_bottom: is_bottom,
+      return;
+    };
+    let downwards = !matches!(anchor, Some(AsyncListIndex::Before(_)));
 
-          item_references: vec![item],
-        },
-      );
+    // the segment being added to, and the item the next one goes next to
+    let mut current = anchor.and_then(|anchor| match anchor {
+      AsyncListIndex::After(item) | AsyncListIndex::Before(item) => self.segment_of(&item).map(|id| (id, item)),
+      _ => None,
+    });
 
-      if is_bottom {
-        self.bottom_bounded_identifier = Some(id);
+    let mut items = items;
+
+    // outwards from the anchor
+    if !downwards {
+      items.reverse();
+    }
+
+    for item in items {
+      current = Some(self.extend_run(current, item, downwards));
+    }
+
+    if is_top || implied_top {
+      self.bound_top(&first);
+    }
+
+    if is_bottom || implied_bottom {
+      self.bound_bottom(&last);
+    }
+
+    for item in [first, last] {
+      if let Some(id) = self.segment_of(&item) {
+        self.absorb_overlaps(id);
       }
+    }
+  }
 
-      if is_top {
-        self.top_bounded_identifier = Some(id);
+  /// Where relative indices put a run, as the item it goes next to (if there is one), and whether the run then is
+  /// the top or the bottom of the list.
+  fn resolve_anchor(&self, anchor: AsyncListIndex<I>) -> (Option<AsyncListIndex<I>>, bool, bool) {
+    match anchor {
+      AsyncListIndex::RelativeToTop(count) => {
+        let anchor = self.top_bounded_identifier.and_then(|id| {
+          let segment = &self.dense_segments[&id];
+
+          match count {
+            0 => Some(AsyncListIndex::Before(segment.first().clone())),
+            count => segment.iter().nth(count - 1).map(|item| AsyncListIndex::After(item.clone())),
+          }
+        });
+
+        (anchor, count == 0, false)
       }
-    } else if segments.len() == 1 {
-      self.dense_segments.get_mut(&segments[0].1).unwrap().insert(index.clone(), item, is_bottom, is_top);
+      AsyncListIndex::RelativeToBottom(count) => {
+        let anchor = self.bottom_bounded_identifier.and_then(|id| {
+          let segment = &self.dense_segments[&id];
 
-      if is_top {
-        self.top_bounded_identifier = Some(segments[0].1)
+          match count {
+            0 => Some(AsyncListIndex::After(segment.last().clone())),
+            count => segment.iter().nth_back(count - 1).map(|item| AsyncListIndex::Before(item.clone())),
+          }
+        });
+
+        (anchor, false, count == 0)
       }
-      if is_bottom {
-        self.bottom_bounded_identifier = Some(segments[0].1)
+      anchor => (Some(anchor), false, false),
+    }
+  }
+
+  /// Adds `item` to a run, next to the previous item, returning where it ended up.
+  fn extend_run(&mut self, current: Option<(u64, I)>, item: I, downwards: bool) -> (u64, I) {
+    if let Some(existing) = self.segment_of(&item) {
+      // the run reached an item we already know about, so the two segments are neighbours
+      if let Some((id, previous)) = current {
+        if id != existing && self.is_end_of(id, &previous, downwards) && self.is_end_of(existing, &item, !downwards) {
+          let merged = if downwards {
+            self.merge(id, None, existing)
+          } else {
+            self.merge(existing, None, id)
+          };
+
+          return (merged, item);
+        }
+
+        // even when the other segment's end has gone missing, e.g. because it was deleted since it was cached
+        if let Some(order) = self.order.filter(|_| id != existing) {
+          return (self.merge_sorted(id, existing, order), item);
+        }
+      }
+
+      return (existing, item);
+    }
+
+    let id = match current {
+      Some((id, previous)) => {
+        let index = if downwards {
+          AsyncListIndex::After(previous)
+        } else {
+          AsyncListIndex::Before(previous)
+        };
+
+        self.edit_segment(id, |segment| segment.insert(index, item.clone(), false, false));
+
+        id
+      }
+      None => {
+        let id = rand::random();
+
+        self.add_segment(id, CacheReferencesSlice::new(vec![item.clone()], false, false));
+
+        id
       }
-    } else if segments.len() == 2 {
-      assert!(!is_top);
-      assert!(!is_bottom);
+    };
 
-      let (li, ri) = match (segments[0], segments[1]) {
-        ((refcacheslice::Position::After, lp), (refcacheslice::Position::Before, rp)) => (lp, rp),
-        ((refcacheslice::Position::Before, rp), (refcacheslice::Position::After, lp)) => (lp, rp),
+    self.segment_index.insert(item.clone(), id);
+
+    (id, item)
+  }
+
+  /// The segment `item` is in or, if the references are ordered, the one whose range it falls in.
+  fn segment_containing(&self, item: &I) -> Option<u64> {
+    if let Some(id) = self.segment_of(item) {
+      return Some(id);
+    }
+
+    let order = self.order?;
+    let (_, id) = self.segment_starts.range(..SegmentStart::new(item.clone(), order)).next_back()?;
+
+    order(item, self.dense_segments[id].last()).is_lt().then_some(*id)
+  }
 
-        _ => panic!("How are there two candidates that aren't (Before, After) or (After, Before)?"),
+  /// Joins every segment whose range overlaps `id`'s into it, if the references are ordered. Returns where `id`'s
+  /// items ended up.
+  fn absorb_overlaps(&mut self, mut id: u64) -> u64 {
+    let Some(order) = self.order else {
+      return id;
+    };
+
+    loop {
+      let segment = &self.dense_segments[&id];
+      let end = SegmentStart::new(segment.last().clone(), order);
+
+      // the other segment starting last before this one ends is the only one that can overlap it, as the others don't
+      // overlap each other
+      let overlapping = self
+        .segment_starts
+        .range(..=end)
+        .rev()
+        .map(|(_, other)| *other)
+        .find(|other| *other != id)
+        .filter(|other| order(segment.first(), self.dense_segments[other].last()).is_le());
+
+      let Some(other) = overlapping else {
+        return id;
       };
 
-      let (left, right) = if li < ri {
-        let right = self.dense_segments.remove(&ri).unwrap();
-        let left = self.dense_segments.remove(&li).unwrap();
+      id = self.merge_sorted(id, other, order);
+    }
+  }
 
-        (left, right)
-      } else {
-        let left = self.dense_segments.remove(&li).unwrap();
-        let right = self.dense_segments.remove(&ri).unwrap();
+  /// Replaces two segments with one holding both of their items in order.
+  fn merge_sorted(&mut self, a: u64, b: u64, order: fn(&I, &I) -> Ordering) -> u64 {
+    let a = self.take_segment(a);
+    let b = self.take_segment(b);
+
+    let is_bounded_at_top = if order(a.first(), b.first()).is_le() {
+      a.is_bounded_at_top
+    } else {
+      b.is_bounded_at_top
+    };
+    let is_bounded_at_bottom = if order(a.last(), b.last()).is_ge() {
+      a.is_bounded_at_bottom
+    } else {
+      b.is_bounded_at_bottom
+    };
+
+    let mut items = a.into_items().into_iter().chain(b.into_items()).collect::<Vec<_>>();
+
+    items.sort_by(order);
+    items.dedup();
+
+    let id = rand::random();
+
+    for item in &items {
+      self.segment_index.insert(item.clone(), id);
+    }
+
+    self.add_segment(id, CacheReferencesSlice::new(items, is_bounded_at_top, is_bounded_at_bottom));
+
+    // the old segments' bounds went with them
+    if self.top_bounded_identifier.is_some_and(|top| !self.dense_segments.contains_key(&top)) {
+      self.top_bounded_identifier = None;
+    }
 
-        (left, right)
+    if self.bottom_bounded_identifier.is_some_and(|bottom| !self.dense_segments.contains_key(&bottom)) {
+      self.bottom_bounded_identifier = None;
+    }
+
+    if is_bounded_at_top {
+      self.top_bounded_identifier = Some(id);
+    }
+
+    if is_bounded_at_bottom {
+      self.bottom_bounded_identifier = Some(id);
+    }
+
+    id
+  }
+
+  fn is_end_of(&self, id: u64, item: &I, bottom: bool) -> bool {
+    let segment = &self.dense_segments[&id];
+
+    if bottom {
+      segment.last() == item
+    } else {
+      segment.first() == item
+    }
+  }
+
+  /// Marks `item` as the top of the list, if nothing is known to be above it.
+  fn bound_top(&mut self, item: &I) {
+    let Some(id) = self.segment_of(item) else {
+      return;
+    };
+
+    let segment = self.dense_segments.get_mut(&id).unwrap();
+
+    if segment.first() == item {
+      segment.is_bounded_at_top = true;
+      self.top_bounded_identifier = Some(id);
+    }
+  }
+
+  /// Marks `item` as the bottom of the list, if nothing is known to be below it.
+  fn bound_bottom(&mut self, item: &I) {
+    let Some(id) = self.segment_of(item) else {
+      return;
+    };
+
+    let segment = self.dense_segments.get_mut(&id).unwrap();
+
+    if segment.last() == item {
+      segment.is_bounded_at_bottom = true;
+      self.bottom_bounded_identifier = Some(id);
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.segment_index.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.dense_segments.is_empty()
+  }
+
+  /// Drops items until at most `max` are left, returning them.
+  ///
+  /// Whole segments go first, least recently used (by `recency`) first, and never the one `hot` is in. If that isn't
+  /// enough, `hot`'s segment is trimmed from whichever end is further from it. Trimming an end means it's no longer
+  /// known to be the top or bottom of the list.
+  pub fn evict(&mut self, max: usize, hot: Option<&I>, recency: impl Fn(&I) -> u64) -> Vec<I> {
+    let mut evicted = vec![];
+    let mut len = self.len();
+
+    while len > max {
+      let excess = len - max;
+      let hot_segment = hot.and_then(|hot| self.segment_of(hot));
+
+      let victim =
+        self.dense_segments.iter().filter(|(id, _)| Some(**id) != hot_segment).min_by_key(|(_, segment)| segment.iter().map(&recency).max()).map(
+          |(id, segment)| {
+            // the end used longer ago goes first
+            let from_top = recency(segment.first()) <= recency(segment.last());
+
+            (*id, from_top)
+          },
+        );
+
+      let (id, from_top) = match victim {
+        Some(victim) => victim,
+        None => {
+          let id = hot_segment.unwrap();
+          let segment = &self.dense_segments[&id];
+          let index = segment.find_index_of(hot.unwrap()).unwrap();
+
+          (id, index >= segment.len() - 1 - index)
+        }
       };
 
-      let mut merged = left.item_references;
+      let trimmed = self.trim(id, excess, from_top);
 
-      merged.push(item);
+      len -= trimmed.len();
+      evicted.extend(trimmed);
+    }
 
-      merged.extend(right.item_references);
+    evicted
+  }
 
-      let id = rand::random();
+  fn trim(&mut self, id: u64, count: usize, from_top: bool) -> Vec<I> {
+    let trimmed = if count >= self.dense_segments[&id].len() {
+      let segment = self.take_segment(id);
 
-      self.dense_segments.insert(
-        id,
-        CacheReferencesSlice {
-          is_bounded_at_top: left.is_bounded_at_top,
-          is_bounded_at_bottom: right.is_bounded_at_bottom,
+      if self.top_bounded_identifier == Some(id) {
+        self.top_bounded_identifier = None;
+      }
 
-          item_references: merged,
-        },
-      );
+      if self.bottom_bounded_identifier == Some(id) {
+        self.bottom_bounded_identifier = None;
+      }
 
-      if left.is_bounded_at_top {
-        self.top_bounded_identifier = Some(id);
+      segment.into_items().into()
+    } else if from_top {
+      if self.top_bounded_identifier == Some(id) {
+        self.top_bounded_identifier = None;
       }
 
-      if right.is_bounded_at_bottom {
-        self.bottom_bounded_identifier = Some(id);
+      self.edit_segment(id, |segment| {
+        segment.is_bounded_at_top = false;
+        segment.drain_top(count)
+      })
+    } else {
+      if self.bottom_bounded_identifier == Some(id) {
+        self.bottom_bounded_identifier = None;
       }
+
+      let segment = self.dense_segments.get_mut(&id).unwrap();
+
+      segment.is_bounded_at_bottom = false;
+      segment.drain_bottom(count)
+    };
+
+    for item in &trimmed {
+      self.segment_index.remove(item);
+    }
+
+    trimmed
+  }
+
+  fn add_segment(&mut self, id: u64, segment: CacheReferencesSlice<I>) {
+    if let Some(order) = self.order {
+      self.segment_starts.insert(SegmentStart::new(segment.first().clone(), order), id);
+    }
+
+    self.dense_segments.insert(id, segment);
+  }
+
+  fn take_segment(&mut self, id: u64) -> CacheReferencesSlice<I> {
+    let segment = self.dense_segments.remove(&id).unwrap();
+
+    if let Some(order) = self.order {
+      self.segment_starts.remove(&SegmentStart::new(segment.first().clone(), order));
+    }
+
+    segment
+  }
+
+  /// Changes a segment in a way that might change which item is first in it.
+  fn edit_segment<R>(&mut self, id: u64, edit: impl FnOnce(&mut CacheReferencesSlice<I>) -> R) -> R {
+    let segment = self.dense_segments.get_mut(&id).unwrap();
+
+    let Some(order) = self.order else {
+      return edit(segment);
+    };
+
+    self.segment_starts.remove(&SegmentStart::new(segment.first().clone(), order));
+
+    let result = edit(segment);
+
+    self.segment_starts.insert(SegmentStart::new(segment.first().clone(), order), id);
+
+    result
+  }
+
+  fn segment_of(&self, item: &I) -> Option<u64> {
+    self.segment_index.get(item).copied()
+  }
+
+  /// The only segment that could know about `index`.
+  fn segment_for(&self, index: &AsyncListIndex<I>) -> Option<u64> {
+    match index {
+      AsyncListIndex::After(item) | AsyncListIndex::Before(item) => self.segment_of(item),
+      AsyncListIndex::RelativeToTop(_) => self.top_bounded_identifier,
+      AsyncListIndex::RelativeToBottom(_) => self.bottom_bounded_identifier,
+    }
+  }
+
+  fn join_existing(&mut self, index: AsyncListIndex<I>, item: &I, existing: u64, is_top: bool, is_bottom: bool) {
+    let segment = self.dense_segments.get(&existing).unwrap();
+    let item_is_first = segment.first() == item;
+    let item_is_last = segment.last() == item;
+
+    let neighbour = match index {
+      AsyncListIndex::After(anchor) if item_is_first => {
+        self.segment_of(&anchor).filter(|id| *id != existing && *self.dense_segments[id].last() == anchor).map(|id| (id, existing))
+      }
+      AsyncListIndex::Before(anchor) if item_is_last => {
+        self.segment_of(&anchor).filter(|id| *id != existing && *self.dense_segments[id].first() == anchor).map(|id| (existing, id))
+      }
+      _ => None,
+    };
+
+    if let Some((top, bottom)) = neighbour {
+      self.merge(top, None, bottom);
+    }
+
+    if is_top {
+      self.bound_top(item);
+    }
+
+    if is_bottom {
+      self.bound_bottom(item);
+    }
+  }
+
+  /// Replaces two segments with one, `top` followed by `middle` (if any) followed by `bottom`.
+  ///
+  /// The smaller segment's items are moved into the larger one, which keeps its identifier.
+  fn merge(&mut self, top: u64, middle: Option<I>, bottom: u64) -> u64 {
+    let top_is_larger = self.dense_segments[&top].len() >= self.dense_segments[&bottom].len();
+
+    let (id, moved) = if top_is_larger { (top, bottom) } else { (bottom, top) };
+    let moved = self.take_segment(moved);
+    let segment = &self.dense_segments[&id];
+
+    let is_bounded_at_top = if top_is_larger {
+      segment.is_bounded_at_top
     } else {
-      panic!("Impossible state")
+      moved.is_bounded_at_top
+    };
+    let is_bounded_at_bottom = if top_is_larger {
+      moved.is_bounded_at_bottom
+    } else {
+      segment.is_bounded_at_bottom
+    };
+
+    let moved = moved.into_items();
+
+    for item in moved.iter().chain(middle.iter()) {
+      self.segment_index.insert(item.clone(), id);
+    }
+
+    self.edit_segment(id, |segment| {
+      if top_is_larger {
+        segment.extend_bottom(middle.into_iter().chain(moved));
+      } else {
+        segment.extend_top(moved.into_iter().chain(middle).rev());
+      }
+
+      segment.is_bounded_at_top = is_bounded_at_top;
+      segment.is_bounded_at_bottom = is_bounded_at_bottom;
+    });
+
+    if is_bounded_at_top {
+      self.top_bounded_identifier = Some(id);
     }
+
+    if is_bounded_at_bottom {
+      self.bottom_bounded_identifier = Some(id);
+    }
+
+    id
   }
 }
 
-impl<I: Clone + Eq + PartialEq + Debug> Debug for CacheReferences<I> {
+impl<I: Clone + Eq + Hash + Debug> Debug for CacheReferences<I> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.debug_struct("CacheReferences")
       .field("top_bounded_segment", &self.top_bounded_identifier)
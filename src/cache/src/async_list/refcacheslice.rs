@@ -1,14 +1,22 @@
-use std::fmt::Debug;
+use std::{
+  collections::{HashMap, VecDeque},
+  fmt::Debug,
+  hash::Hash,
+};
 
 use scope_chat::async_list::AsyncListIndex;
 
-pub struct CacheReferencesSlice<I: Clone + Eq + PartialEq> {
+pub struct CacheReferencesSlice<I: Clone + Eq + Hash> {
   pub is_bounded_at_top: bool,
   pub is_bounded_at_bottom: bool,
 
-  // the vec's 0th item is the top, and it's last item is the bottom
-  // the vec MUST NOT be empty.
-  pub(super) item_references: Vec<I>,
+  // the deque's 0th item is the top, and it's last item is the bottom
+  // the deque MUST NOT be empty.
+  item_references: VecDeque<I>,
+
+  // where each item is, as an offset from `first_position`, so items can be added to the top without renumbering
+  positions: HashMap<I, i64>,
+  first_position: i64,
 }
 
 pub enum Exists<T> {
@@ -17,15 +25,46 @@ pub enum Exists<T> {
   Unknown,
 }
 
-impl<I: Clone + Eq + PartialEq> CacheReferencesSlice<I> {
-  fn find_index_of(&self, item: I) -> Option<usize> {
-    for (haystack, index) in self.item_references.iter().zip(0..) {
-      if *haystack == item {
-        return Some(index);
-      }
+impl<I: Clone + Eq + Hash> CacheReferencesSlice<I> {
+  pub fn new(items: Vec<I>, is_bounded_at_top: bool, is_bounded_at_bottom: bool) -> Self {
+    assert!(!items.is_empty());
+
+    let positions = items.iter().cloned().zip(0..).collect();
+
+    Self {
+      is_bounded_at_top,
+      is_bounded_at_bottom,
+
+      item_references: items.into(),
+
+      positions,
+      first_position: 0,
     }
+  }
+
+  pub fn len(&self) -> usize {
+    self.item_references.len()
+  }
 
-    None
+  pub fn is_empty(&self) -> bool {
+    self.item_references.is_empty()
+  }
+
+  pub fn first(&self) -> &I {
+    self.item_references.front().unwrap()
+  }
+
+  pub fn last(&self) -> &I {
+    self.item_references.back().unwrap()
+  }
+
+  /// The items from top to bottom.
+  pub fn iter(&self) -> impl DoubleEndedIterator<Item = &I> + ExactSizeIterator {
+    self.item_references.iter()
+  }
+
+  pub(super) fn find_index_of(&self, item: &I) -> Option<usize> {
+    self.positions.get(item).map(|position| (position - self.first_position) as usize)
   }
 
   fn get_index(&self, index: AsyncListIndex<I>) -> Option<isize> {
@@ -34,9 +73,9 @@ impl<I: Clone + Eq + PartialEq> CacheReferencesSlice<I> {
 
       AsyncListIndex::RelativeToTop(count) if self.is_bounded_at_top => Some(count as isize),
 
-      AsyncListIndex::After(item) => Some((self.find_index_of(item)? as isize) + 1),
+      AsyncListIndex::After(item) => Some((self.find_index_of(&item)? as isize) + 1),
 
-      AsyncListIndex::Before(item) => Some((self.find_index_of(item)? as isize) - 1),
+      AsyncListIndex::Before(item) => Some((self.find_index_of(&item)? as isize) - 1),
 
       _ => None,
     }
@@ -45,7 +84,7 @@ impl<I: Clone + Eq + PartialEq> CacheReferencesSlice<I> {
   pub fn append_bottom(&mut self, index: I) {
     assert!(self.is_bounded_at_bottom);
 
-    self.item_references.push(index);
+    self.push_bottom(index);
   }
 
   pub fn get(&self, index: AsyncListIndex<I>) -> Exists<I> {
@@ -76,16 +115,36 @@ impl<I: Clone + Eq + PartialEq> CacheReferencesSlice<I> {
 
   pub fn can_insert(&self, index: AsyncListIndex<I>) -> Option<Position> {
     match index {
-      AsyncListIndex::After(item) => self.find_index_of(item).map(|idx| {
+      AsyncListIndex::After(item) => self.find_index_of(&item).map(|idx| {
         if idx == (self.item_references.len() - 1) {
           Position::After
         } else {
           Position::Inside
         }
       }),
-      AsyncListIndex::Before(item) => self.find_index_of(item).map(|idx| if idx == 0 { Position::Before } else { Position::Inside }),
+      AsyncListIndex::Before(item) => self.find_index_of(&item).map(|idx| if idx == 0 { Position::Before } else { Position::Inside }),
+
+      // relative indices only mean something to the segment bounded at that end, and only if they don't leave a gap
+      AsyncListIndex::RelativeToTop(count) if self.is_bounded_at_top => {
+        Self::relative_position(count, self.item_references.len(), Position::Before, Position::After)
+      }
+      AsyncListIndex::RelativeToBottom(count) if self.is_bounded_at_bottom => {
+        Self::relative_position(count, self.item_references.len(), Position::After, Position::Before)
+      }
 
-      _ => panic!("TODO: Figure out what well-defined behaviour for what should occur for inserting relative to top or bottom"),
+      _ => None,
+    }
+  }
+
+  fn relative_position(count: usize, len: usize, at_bound: Position, at_far_end: Position) -> Option<Position> {
+    if count == 0 {
+      Some(at_bound)
+    } else if count == len {
+      Some(at_far_end)
+    } else if count < len {
+      Some(Position::Inside)
+    } else {
+      None
     }
   }
 
@@ -97,24 +156,122 @@ impl<I: Clone + Eq + PartialEq> CacheReferencesSlice<I> {
       self.is_bounded_at_top = true
     }
 
-    match index {
-      AsyncListIndex::After(item) => {
-        let i = self.find_index_of(item).unwrap();
+    let i = match index {
+      AsyncListIndex::After(item) => self.find_index_of(&item).unwrap() + 1,
+      AsyncListIndex::Before(item) => self.find_index_of(&item).unwrap(),
 
-        self.item_references.insert(i + 1, value);
-      }
-      AsyncListIndex::Before(item) => {
-        let i = self.find_index_of(item).unwrap();
+      // the inserted item ends up `count` items away from that end
+      AsyncListIndex::RelativeToTop(count) => count,
+      AsyncListIndex::RelativeToBottom(count) => self.item_references.len() - count,
+    };
 
-        self.item_references.insert(i, value);
-      }
+    self.insert_at(i, value);
+  }
+
+  /// Inserts an item where it sorts, for segments kept in identifier order.
+  pub(super) fn insert_sorted(&mut self, item: I, order: fn(&I, &I) -> std::cmp::Ordering) {
+    let index = self.item_references.partition_point(|other| order(other, &item).is_lt());
+
+    self.insert_at(index, item);
+  }
+
+  /// Adds items above the top, the first of them ending up directly above the current top.
+  pub(super) fn extend_top(&mut self, items: impl IntoIterator<Item = I>) {
+    for item in items {
+      self.push_top(item);
+    }
+  }
+
+  pub(super) fn extend_bottom(&mut self, items: impl IntoIterator<Item = I>) {
+    for item in items {
+      self.push_bottom(item);
+    }
+  }
+
+  pub(super) fn push_top(&mut self, item: I) {
+    self.first_position -= 1;
+    self.positions.insert(item.clone(), self.first_position);
+    self.item_references.push_front(item);
+  }
+
+  pub(super) fn push_bottom(&mut self, item: I) {
+    self.positions.insert(item.clone(), self.first_position + self.item_references.len() as i64);
+    self.item_references.push_back(item);
+  }
+
+  // only inserting at either end is cheap, anything below the new item has to be renumbered
+  fn insert_at(&mut self, index: usize, item: I) {
+    if index == 0 {
+      return self.push_top(item);
+    }
+
+    if index == self.item_references.len() {
+      return self.push_bottom(item);
+    }
+
+    self.item_references.insert(index, item);
+    self.renumber_from(index);
+  }
+
+  /// Removes the item at `index`, which must leave at least one item behind.
+  pub(super) fn remove_at(&mut self, index: usize) -> I {
+    assert!(self.item_references.len() > 1);
+
+    let item = self.item_references.remove(index).unwrap();
+
+    self.positions.remove(&item);
+
+    if index == 0 {
+      self.first_position += 1;
+    } else {
+      self.renumber_from(index);
+    }
+
+    item
+  }
+
+  /// Removes `count` items from the top, which must leave at least one item behind.
+  pub(super) fn drain_top(&mut self, count: usize) -> Vec<I> {
+    assert!(count < self.item_references.len());
+
+    let drained: Vec<I> = self.item_references.drain(..count).collect();
+
+    for item in &drained {
+      self.positions.remove(item);
+    }
+
+    self.first_position += count as i64;
+
+    drained
+  }
+
+  /// Removes `count` items from the bottom, which must leave at least one item behind.
+  pub(super) fn drain_bottom(&mut self, count: usize) -> Vec<I> {
+    let len = self.item_references.len();
+
+    assert!(count < len);
+
+    let drained: Vec<I> = self.item_references.drain(len - count..).collect();
+
+    for item in &drained {
+      self.positions.remove(item);
+    }
+
+    drained
+  }
+
+  pub(super) fn into_items(self) -> VecDeque<I> {
+    self.item_references
+  }
 
-      _ => panic!("TODO: Figure out what well-defined behaviour for what should occur for inserting relative to top or bottom"),
+  fn renumber_from(&mut self, index: usize) {
+    for (item, position) in self.item_references.iter().skip(index).zip(self.first_position + index as i64..) {
+      self.positions.insert(item.clone(), position);
     }
   }
 }
 
-impl<I: Clone + Eq + PartialEq + Debug> Debug for CacheReferencesSlice<I> {
+impl<I: Clone + Eq + Hash + Debug> Debug for CacheReferencesSlice<I> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.debug_struct("CacheReferences")
       .field("is_bounded_at_top", &self.is_bounded_at_top)
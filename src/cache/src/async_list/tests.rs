@@ -1,7 +1,7 @@
 use std::fmt::Debug;
 
 #[allow(unused_imports)]
-use scope_chat::async_list::{AsyncListIndex, AsyncListItem, AsyncListResult};
+use scope_chat::async_list::{AsyncListGap, AsyncListIndex, AsyncListItem, AsyncListResult};
 
 #[allow(unused_imports)]
 use crate::async_list::{refcacheslice::Exists, AsyncListCache};
@@ -18,6 +18,18 @@ impl AsyncListItem for ListItem {
   }
 }
 
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct VersionedListItem(i64, u32);
+
+impl AsyncListItem for VersionedListItem {
+  type Identifier = i64;
+
+  fn get_list_identifier(&self) -> Self::Identifier {
+    self.0
+  }
+}
+
 #[allow(dead_code)]
 fn assert_query_exists<I: PartialEq + Eq + Debug>(result: Exists<AsyncListResult<I>>, item: I, is_top_in: bool, is_bottom_in: bool) {
   if let Exists::Yes(AsyncListResult { content, is_top, is_bottom }) = result {
@@ -239,3 +251,518 @@ pub fn cache_can_merge() {
   assert_query_exists(cache.get(AsyncListIndex::After(0)), ListItem(1), false, false);
   assert_query_exists(cache.get(AsyncListIndex::Before(1)), ListItem(0), false, false);
 }
+
+#[test]
+pub fn cache_can_remove_from_middle_of_segment() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  for i in 0..5 {
+    cache.append_bottom(ListItem(i));
+  }
+
+  assert_eq!(cache.remove(&2), Some(ListItem(2)));
+
+  assert_eq!(cache.find(&2), None);
+  assert_eq!(cache.bounded_at_bottom_by(), Some(4));
+  assert_query_exists(cache.get(AsyncListIndex::After(1)), ListItem(3), false, false);
+  assert_query_exists(cache.get(AsyncListIndex::Before(3)), ListItem(1), false, false);
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(1)), ListItem(3), false, false);
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(2)), ListItem(1), false, false);
+  assert!(matches!(cache.get(AsyncListIndex::After(2)), Exists::Unknown));
+}
+
+#[test]
+pub fn cache_can_remove_bottom_bound() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  for i in 0..3 {
+    cache.append_bottom(ListItem(i));
+  }
+
+  assert_eq!(cache.remove(&2), Some(ListItem(2)));
+
+  assert_eq!(cache.bounded_at_bottom_by(), Some(1));
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(0)), ListItem(1), false, true);
+  assert!(matches!(cache.get(AsyncListIndex::After(1)), Exists::No));
+
+  cache.append_bottom(ListItem(3));
+
+  assert_eq!(cache.bounded_at_bottom_by(), Some(3));
+  assert_query_exists(cache.get(AsyncListIndex::After(1)), ListItem(3), false, true);
+}
+
+#[test]
+pub fn cache_can_remove_top_bound() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.insert_detached(ListItem(0));
+  cache.insert(AsyncListIndex::After(0), ListItem(1), false, false);
+  cache.insert(AsyncListIndex::Before(0), ListItem(-1), true, false);
+
+  assert_eq!(cache.bounded_at_top_by(), Some(-1));
+
+  assert_eq!(cache.remove(&-1), Some(ListItem(-1)));
+
+  assert_eq!(cache.bounded_at_top_by(), Some(0));
+  assert_query_exists(cache.get(AsyncListIndex::Before(1)), ListItem(0), true, false);
+  assert!(matches!(cache.get(AsyncListIndex::Before(0)), Exists::No));
+}
+
+#[test]
+pub fn cache_can_remove_last_item_of_segment() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.append_bottom(ListItem(0));
+  cache.insert_detached(ListItem(10));
+
+  assert_eq!(cache.remove(&10), Some(ListItem(10)));
+  assert_eq!(cache.find(&10), None);
+  assert!(matches!(cache.get(AsyncListIndex::After(10)), Exists::Unknown));
+
+  assert_eq!(cache.remove(&0), Some(ListItem(0)));
+  assert_eq!(cache.find(&0), None);
+  assert_eq!(cache.bounded_at_bottom_by(), None);
+  assert!(matches!(cache.get(AsyncListIndex::RelativeToBottom(0)), Exists::Unknown));
+
+  assert_eq!(cache.remove(&0), None);
+}
+
+#[test]
+pub fn ordered_cache_fetches_past_the_last_segment_when_an_end_segment_is_removed() {
+  let mut cache = AsyncListCache::<ListItem>::ordered();
+
+  cache.insert(AsyncListIndex::RelativeToTop(0), ListItem(-5), true, false);
+  cache.insert_range(AsyncListIndex::After(-1), vec![ListItem(0), ListItem(1), ListItem(2)], false, false);
+  cache.insert(AsyncListIndex::RelativeToBottom(0), ListItem(10), false, true);
+
+  assert_eq!(cache.bounded_at_top_by(), Some(-5));
+  assert_eq!(cache.bounded_at_bottom_by(), Some(10));
+
+  assert_eq!(cache.remove(&10), Some(ListItem(10)));
+  assert_eq!(cache.remove(&-5), Some(ListItem(-5)));
+
+  // 3 to 9 were never loaded, so the segment that's left isn't either end of the list
+  assert_eq!(cache.bounded_at_bottom_by(), None);
+  assert_eq!(cache.bounded_at_top_by(), None);
+  assert!(matches!(cache.get(AsyncListIndex::RelativeToBottom(0)), Exists::Unknown));
+  assert!(matches!(cache.get(AsyncListIndex::RelativeToTop(0)), Exists::Unknown));
+  assert!(matches!(cache.get(AsyncListIndex::After(2)), Exists::Unknown));
+  assert!(matches!(cache.get(AsyncListIndex::Before(0)), Exists::Unknown));
+
+  // so they're fetched, and end up between what was kept
+  cache.insert_range(AsyncListIndex::After(2), (3..10).map(ListItem).collect(), false, true);
+
+  assert_eq!(cache.bounded_at_bottom_by(), Some(9));
+  assert_query_exists(cache.get(AsyncListIndex::After(2)), ListItem(3), false, false);
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(0)), ListItem(9), false, true);
+}
+
+#[test]
+pub fn cache_range_with_nothing_past_the_anchor_bounds_it() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.insert_range(AsyncListIndex::RelativeToTop(0), (0..3).map(ListItem).collect(), true, false);
+
+  assert!(matches!(cache.get(AsyncListIndex::After(2)), Exists::Unknown));
+
+  // the last page was empty, so 2 is the bottom
+  cache.insert_range(AsyncListIndex::After(2), vec![], false, true);
+
+  assert_eq!(cache.bounded_at_bottom_by(), Some(2));
+  assert!(matches!(cache.get(AsyncListIndex::After(2)), Exists::No));
+
+  // and new items can be added below it
+  cache.append_bottom(ListItem(3));
+
+  assert_query_exists(cache.get(AsyncListIndex::After(2)), ListItem(3), false, true);
+
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.insert_range(AsyncListIndex::RelativeToBottom(0), (5..8).map(ListItem).collect(), false, true);
+  cache.insert_range(AsyncListIndex::Before(5), vec![], true, false);
+
+  assert_eq!(cache.bounded_at_top_by(), Some(5));
+  assert!(matches!(cache.get(AsyncListIndex::Before(5)), Exists::No));
+}
+
+#[test]
+pub fn cache_can_update_in_place() {
+  let mut cache = AsyncListCache::<VersionedListItem>::new();
+
+  for i in 0..3 {
+    cache.append_bottom(VersionedListItem(i, 0));
+  }
+
+  assert_eq!(cache.update(VersionedListItem(1, 1)), Some(VersionedListItem(1, 0)));
+
+  assert_eq!(cache.find(&1), Some(VersionedListItem(1, 1)));
+  assert_query_exists(cache.get(AsyncListIndex::After(0)), VersionedListItem(1, 1), false, false);
+  assert_query_exists(cache.get(AsyncListIndex::Before(2)), VersionedListItem(1, 1), false, false);
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(0)), VersionedListItem(2, 0), false, true);
+
+  assert_eq!(cache.update(VersionedListItem(5, 1)), None);
+  assert_eq!(cache.find(&5), None);
+}
+
+#[test]
+pub fn cache_can_insert_relative_to_top() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.insert(AsyncListIndex::RelativeToTop(0), ListItem(0), true, false);
+
+  assert_eq!(cache.bounded_at_top_by(), Some(0));
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToTop(0)), ListItem(0), true, false);
+
+  cache.insert(AsyncListIndex::After(0), ListItem(2), false, false);
+  cache.insert(AsyncListIndex::RelativeToTop(1), ListItem(1), false, false);
+
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToTop(1)), ListItem(1), false, false);
+  assert_query_exists(cache.get(AsyncListIndex::After(1)), ListItem(2), false, false);
+  assert!(matches!(cache.get(AsyncListIndex::After(2)), Exists::Unknown));
+
+  cache.insert(AsyncListIndex::RelativeToTop(0), ListItem(-1), false, false);
+
+  assert_eq!(cache.bounded_at_top_by(), Some(-1));
+  assert!(matches!(cache.get(AsyncListIndex::Before(-1)), Exists::No));
+}
+
+#[test]
+pub fn cache_can_insert_relative_to_bottom() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.append_bottom(ListItem(0));
+  cache.append_bottom(ListItem(2));
+
+  cache.insert(AsyncListIndex::RelativeToBottom(1), ListItem(1), false, false);
+  cache.insert(AsyncListIndex::RelativeToBottom(0), ListItem(3), false, false);
+
+  assert_query_exists(cache.get(AsyncListIndex::Before(2)), ListItem(1), false, false);
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(0)), ListItem(3), false, true);
+
+  // an unbounded segment can't take a relative insert, so this can't land in it
+  cache.insert_detached(ListItem(100));
+  cache.append_bottom(ListItem(4));
+
+  assert_query_exists(cache.get(AsyncListIndex::After(3)), ListItem(4), false, true);
+  assert!(matches!(cache.get(AsyncListIndex::After(100)), Exists::Unknown));
+}
+
+#[test]
+pub fn cache_joins_segments_when_paging_meets_known_items() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  for i in 3..6 {
+    cache.append_bottom(ListItem(i));
+  }
+
+  cache.insert(AsyncListIndex::RelativeToTop(0), ListItem(0), true, false);
+  cache.insert(AsyncListIndex::After(0), ListItem(1), false, false);
+  cache.insert(AsyncListIndex::After(1), ListItem(2), false, false);
+
+  // 3 is already the top of the bottom segment, so this joins the two instead of adding it again
+  cache.insert(AsyncListIndex::After(2), ListItem(3), false, false);
+
+  let mut seen = vec![];
+  let mut index = AsyncListIndex::RelativeToTop(0);
+
+  while let Exists::Yes(result) = cache.get(index) {
+    seen.push(result.content.0);
+    index = AsyncListIndex::After(result.content.0);
+  }
+
+  assert_eq!(seen, vec![0, 1, 2, 3, 4, 5]);
+  assert_eq!(cache.bounded_at_top_by(), Some(0));
+  assert_eq!(cache.bounded_at_bottom_by(), Some(5));
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(5)), ListItem(0), true, false);
+}
+
+#[test]
+pub fn cache_marks_known_items_as_bounds() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.append_bottom(ListItem(1));
+  cache.insert(AsyncListIndex::Before(1), ListItem(0), false, false);
+
+  cache.insert(AsyncListIndex::Before(1), ListItem(0), true, false);
+
+  assert_eq!(cache.bounded_at_top_by(), Some(0));
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToTop(0)), ListItem(0), true, false);
+}
+
+#[test]
+pub fn cache_can_unbound_bottom() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.append_bottom(ListItem(0));
+  cache.append_bottom(ListItem(1));
+
+  assert_eq!(cache.unbound_bottom(), Some(1));
+  assert_eq!(cache.bounded_at_bottom_by(), None);
+  assert!(matches!(cache.get(AsyncListIndex::After(1)), Exists::Unknown));
+
+  // the next item after the old bottom lands in the same segment
+  cache.insert(AsyncListIndex::After(1), ListItem(2), false, true);
+
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(2)), ListItem(0), false, false);
+  assert_eq!(cache.unbound_bottom(), Some(2));
+  assert_eq!(cache.unbound_bottom(), None);
+}
+
+#[test]
+pub fn cache_keeps_order_when_a_small_segment_joins_a_larger_one() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  // the bottom segment is larger, so the top one's items move into it
+  cache.insert_detached(ListItem(0));
+  cache.insert(AsyncListIndex::After(0), ListItem(1), false, false);
+
+  for i in 3..10 {
+    cache.append_bottom(ListItem(i));
+  }
+
+  cache.insert(AsyncListIndex::Before(3), ListItem(2), false, false);
+  cache.insert(AsyncListIndex::After(1), ListItem(2), false, false);
+
+  // and positions stay right after removing from either end and the middle
+  cache.remove(&0);
+  cache.remove(&5);
+  cache.remove(&9);
+
+  let mut seen = vec![];
+  let mut index = AsyncListIndex::RelativeToBottom(0);
+
+  while let Exists::Yes(result) = cache.get(index) {
+    seen.push(result.content.0);
+    index = AsyncListIndex::Before(result.content.0);
+  }
+
+  assert_eq!(seen, vec![8, 7, 6, 4, 3, 2, 1]);
+  assert!(matches!(cache.get(AsyncListIndex::Before(1)), Exists::Unknown));
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(4)), ListItem(3), false, false);
+}
+
+#[allow(dead_code)]
+fn walk_down<I: AsyncListItem<Identifier = i64> + Clone>(cache: &mut AsyncListCache<I>) -> Vec<i64> {
+  let mut seen = vec![];
+  let mut index = AsyncListIndex::RelativeToTop(0);
+
+  while let Exists::Yes(result) = cache.get(index) {
+    seen.push(result.content.get_list_identifier());
+    index = AsyncListIndex::After(result.content.get_list_identifier());
+  }
+
+  seen
+}
+
+#[test]
+pub fn cache_range_joins_two_segments_paging_down() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.insert_range(AsyncListIndex::RelativeToTop(0), (0..3).map(ListItem).collect(), false, false);
+
+  for i in 10..15 {
+    cache.append_bottom(ListItem(i));
+  }
+
+  // the page overlaps the bottom segment, which it joins up with at 10
+  cache.insert_range(AsyncListIndex::After(2), (3..12).map(ListItem).collect(), false, false);
+
+  assert_eq!(walk_down(&mut cache), (0..15).collect::<Vec<_>>());
+  assert_eq!(cache.segments().len(), 1);
+  assert_eq!(cache.len(), 15);
+  assert_query_exists(cache.get(AsyncListIndex::RelativeToBottom(14)), ListItem(0), true, false);
+}
+
+#[test]
+pub fn cache_range_joins_two_segments_paging_up() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.insert_range(AsyncListIndex::RelativeToBottom(0), (10..15).map(ListItem).collect(), false, false);
+  cache.insert_detached(ListItem(2));
+  cache.insert(AsyncListIndex::Before(2), ListItem(1), false, false);
+
+  // a page of messages before 10 reaches back to 2
+  cache.insert_range(AsyncListIndex::Before(10), (2..10).map(ListItem).collect(), false, false);
+
+  assert_eq!(cache.segments().len(), 1);
+  assert_query_exists(cache.get(AsyncListIndex::After(2)), ListItem(3), false, false);
+
+  // reaching the top of the list marks the first item of the page
+  cache.insert_range(AsyncListIndex::Before(1), vec![ListItem(0)], true, false);
+
+  assert_eq!(walk_down(&mut cache), (0..15).collect::<Vec<_>>());
+  assert_eq!(cache.bounded_at_top_by(), Some(0));
+  assert_eq!(cache.bounded_at_bottom_by(), Some(14));
+}
+
+#[test]
+pub fn ordered_cache_places_detached_items() {
+  let mut cache = AsyncListCache::<ListItem>::ordered();
+
+  for i in [10, 20, 30] {
+    cache.append_bottom(ListItem(i));
+  }
+
+  cache.insert_detached(ListItem(25));
+  cache.insert_detached(ListItem(50));
+
+  assert_query_exists(cache.get(AsyncListIndex::After(20)), ListItem(25), false, false);
+  assert_query_exists(cache.get(AsyncListIndex::Before(30)), ListItem(25), false, false);
+  assert!(matches!(cache.get(AsyncListIndex::After(50)), Exists::Unknown));
+
+  assert_eq!(cache.has_gap_between(&10, &30), Some(false));
+  assert_eq!(cache.has_gap_between(&30, &50), Some(true));
+  // 40 hasn't been loaded, but it can only be between the two segments
+  assert_eq!(cache.has_gap_between(&40, &50), Some(true));
+}
+
+#[test]
+pub fn ordered_cache_joins_overlapping_segments() {
+  let mut cache = AsyncListCache::<ListItem>::ordered();
+
+  cache.insert_range(AsyncListIndex::RelativeToTop(0), (0..5).map(ListItem).collect(), true, false);
+  cache.insert_detached(ListItem(10));
+  cache.insert(AsyncListIndex::After(10), ListItem(11), false, true);
+
+  // 10 was deleted since it was cached, so this page skips over it without ever reaching it
+  cache.insert_range(AsyncListIndex::After(4), vec![ListItem(5), ListItem(9), ListItem(11)], false, false);
+
+  assert_eq!(walk_down(&mut cache), vec![0, 1, 2, 3, 4, 5, 9, 10, 11]);
+  assert_eq!(cache.segments().len(), 1);
+  assert_eq!(cache.bounded_at_bottom_by(), Some(11));
+  assert_eq!(cache.has_gap_between(&0, &11), Some(false));
+
+  // without an order, the cache can't place what it hasn't seen
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.append_bottom(ListItem(0));
+
+  assert_eq!(cache.has_gap_between(&0, &1), None);
+}
+
+#[test]
+pub fn ordered_cache_reports_gaps_between_segments() {
+  let mut cache = AsyncListCache::<ListItem>::ordered();
+
+  cache.insert_range(AsyncListIndex::RelativeToBottom(0), (20..25).map(ListItem).collect(), false, false);
+  cache.insert_range(AsyncListIndex::After(3), (4..8).map(ListItem).collect(), false, false);
+  cache.insert_detached(ListItem(12));
+
+  assert_eq!(cache.gap(AsyncListIndex::After(7)), Some(AsyncListGap { above: 7, below: 12 }));
+  assert_eq!(cache.gap(AsyncListIndex::Before(20)), Some(AsyncListGap { above: 12, below: 20 }));
+  assert_eq!(cache.gap(AsyncListIndex::Before(12)), Some(AsyncListGap { above: 7, below: 12 }));
+
+  // inside a segment, at the bottom of the list, or with nothing known further along, there's no gap
+  assert_eq!(cache.gap(AsyncListIndex::After(5)), None);
+  assert_eq!(cache.gap(AsyncListIndex::After(24)), None);
+  assert_eq!(cache.gap(AsyncListIndex::Before(4)), None);
+
+  // filling the gap joins the segments
+  cache.insert_range(AsyncListIndex::After(7), (8..21).map(ListItem).collect(), false, false);
+
+  assert_eq!(cache.gap(AsyncListIndex::After(7)), None);
+  assert_eq!(cache.gap(AsyncListIndex::Before(20)), None);
+  assert_eq!(cache.segments().len(), 1);
+}
+
+#[test]
+pub fn ordered_cache_places_items_after_a_segment_gains_a_new_top() {
+  let mut cache = AsyncListCache::<ListItem>::ordered();
+
+  cache.insert_range(AsyncListIndex::RelativeToBottom(0), (30..33).map(ListItem).collect(), false, false);
+  cache.insert_detached(ListItem(10));
+  cache.insert(AsyncListIndex::After(10), ListItem(11), false, false);
+
+  // the segment now starts at 25, so 27 falls inside it
+  cache.insert(AsyncListIndex::Before(30), ListItem(25), false, false);
+  cache.insert_detached(ListItem(27));
+
+  assert_query_exists(cache.get(AsyncListIndex::After(25)), ListItem(27), false, false);
+  assert_eq!(cache.has_gap_between(&25, &32), Some(false));
+  assert_eq!(cache.gap(AsyncListIndex::Before(25)), Some(AsyncListGap { above: 11, below: 25 }));
+
+  // and once 25 is gone, it starts at 27 again
+  cache.remove(&25);
+
+  assert_eq!(cache.gap(AsyncListIndex::Before(27)), Some(AsyncListGap { above: 11, below: 27 }));
+  assert_eq!(cache.gap(AsyncListIndex::After(11)), Some(AsyncListGap { above: 11, below: 27 }));
+  assert_eq!(cache.has_gap_between(&26, &31), Some(true));
+}
+
+#[test]
+pub fn cache_evicts_least_recently_used_segment_first() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  for i in 0..3 {
+    cache.append_bottom(ListItem(i));
+  }
+
+  cache.insert_detached(ListItem(10));
+  cache.insert(AsyncListIndex::After(10), ListItem(11), false, false);
+
+  cache.insert_detached(ListItem(20));
+  cache.insert(AsyncListIndex::After(20), ListItem(21), false, false);
+
+  // the segment at 10 is looked at before the one at 20, and the bottom last of all
+  cache.touch(&10);
+  cache.touch(&21);
+  cache.get(AsyncListIndex::RelativeToBottom(0));
+
+  assert_eq!(cache.evict(5), 2);
+
+  assert_eq!(cache.len(), 5);
+  assert_eq!(cache.find(&10), None);
+  assert_eq!(cache.find(&11), None);
+  assert_eq!(cache.find(&20), Some(ListItem(20)));
+  assert_eq!(cache.bounded_at_bottom_by(), Some(2));
+  assert_eq!(cache.metrics().evictions, 2);
+}
+
+#[test]
+pub fn cache_trims_far_end_of_looked_at_segment() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  for i in 0..10 {
+    cache.append_bottom(ListItem(i));
+  }
+
+  cache.insert(AsyncListIndex::Before(0), ListItem(-1), true, false);
+  cache.touch(&7);
+
+  assert_eq!(cache.evict(6), 5);
+
+  // the top was furthest away, so it went, and with it what we knew about the top of the list
+  assert_eq!(cache.bounded_at_top_by(), None);
+  assert_eq!(cache.bounded_at_bottom_by(), Some(9));
+  assert!(matches!(cache.get(AsyncListIndex::Before(4)), Exists::Unknown));
+  assert_query_exists(cache.get(AsyncListIndex::After(4)), ListItem(5), false, false);
+
+  cache.touch(&4);
+
+  assert_eq!(cache.evict(3), 3);
+
+  assert_eq!(cache.bounded_at_bottom_by(), None);
+  assert_query_exists(cache.get(AsyncListIndex::After(5)), ListItem(6), false, false);
+  assert!(matches!(cache.get(AsyncListIndex::After(6)), Exists::Unknown));
+
+  assert_eq!(cache.evict(0), 3);
+  assert!(cache.is_empty());
+}
+
+#[test]
+pub fn cache_counts_hits_and_misses() {
+  let mut cache = AsyncListCache::<ListItem>::new();
+
+  cache.append_bottom(ListItem(0));
+
+  cache.get(AsyncListIndex::RelativeToBottom(0));
+  cache.get(AsyncListIndex::After(0));
+  cache.get(AsyncListIndex::Before(0));
+  cache.get(AsyncListIndex::After(5));
+
+  let metrics = cache.metrics();
+
+  assert_eq!(metrics.hits, 2);
+  assert_eq!(metrics.misses, 2);
+  assert_eq!(metrics.evictions, 0);
+}
@@ -1 +1,2 @@
 pub mod async_list;
+pub mod persistent;
@@ -0,0 +1,201 @@
+pub mod tests;
+
+use std::{path::Path, sync::Mutex};
+
+use rusqlite::{params, Connection};
+use scope_chat::{
+  async_list::AsyncListItem,
+  error::{ChatError, ChatErrorKind},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::async_list::{AsyncListCache, CacheSegment};
+
+/// An item that can be written to a `PersistentStore`.
+pub trait PersistentItem: AsyncListItem {
+  /// What is written to disk
+  type Stored: Serialize + DeserializeOwned;
+  /// Whatever is needed to turn a stored item back into a live one
+  type Context;
+
+  /// `None` for items that shouldn't outlive the session, like messages that haven't been sent yet.
+  fn to_stored(&self) -> Option<Self::Stored>;
+  fn from_stored(stored: Self::Stored, context: &Self::Context) -> Option<Self>;
+}
+
+/// Keeps `AsyncListCache`s on disk between launches, as their dense segments and bounds.
+///
+/// Each list is stored under a key of the caller's choosing, and is replaced as a whole when saved.
+pub struct PersistentStore {
+  connection: Mutex<Connection>,
+}
+
+/// A list's segments as they're written to disk, see `PersistentStore::snapshot`.
+pub struct Snapshot(Vec<CacheSegment<String>>);
+
+const SCHEMA: &str = "
+  CREATE TABLE IF NOT EXISTS segments (
+    list TEXT NOT NULL,
+    segment INTEGER NOT NULL,
+    is_top INTEGER NOT NULL,
+    is_bottom INTEGER NOT NULL,
+    PRIMARY KEY (list, segment)
+  );
+
+  CREATE TABLE IF NOT EXISTS items (
+    list TEXT NOT NULL,
+    segment INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (list, segment, position)
+  );
+";
+
+fn storage_error(error: impl std::fmt::Display) -> ChatError {
+  ChatError::new(ChatErrorKind::Storage, error.to_string())
+}
+
+impl PersistentStore {
+  pub fn open(path: impl AsRef<Path>) -> Result<Self, ChatError> {
+    if let Some(parent) = path.as_ref().parent() {
+      std::fs::create_dir_all(parent).map_err(storage_error)?;
+    }
+
+    Self::from_connection(Connection::open(path).map_err(storage_error)?)
+  }
+
+  /// A store that only lasts as long as it does.
+  pub fn in_memory() -> Result<Self, ChatError> {
+    Self::from_connection(Connection::open_in_memory().map_err(storage_error)?)
+  }
+
+  fn from_connection(connection: Connection) -> Result<Self, ChatError> {
+    connection.execute_batch(SCHEMA).map_err(storage_error)?;
+
+    Ok(PersistentStore {
+      connection: Mutex::new(connection),
+    })
+  }
+
+  pub fn save<I: PersistentItem>(&self, list: &str, cache: &AsyncListCache<I>) -> Result<(), ChatError> {
+    self.save_snapshot(list, Self::snapshot(cache)?)
+  }
+
+  /// What `save` would write for `cache`, taken without touching the disk, so it can be written later on another
+  /// thread with `save_snapshot`.
+  pub fn snapshot<I: PersistentItem>(cache: &AsyncListCache<I>) -> Result<Snapshot, ChatError> {
+    let segments = cache.segments().into_iter().flat_map(|segment| split_segment(segment, |item| item.to_stored()));
+
+    segments
+      .map(|segment| {
+        Ok(CacheSegment {
+          is_top: segment.is_top,
+          is_bottom: segment.is_bottom,
+          items: segment.items.iter().map(serde_json::to_string).collect::<Result<_, _>>().map_err(storage_error)?,
+        })
+      })
+      .collect::<Result<_, _>>()
+      .map(Snapshot)
+  }
+
+  pub fn save_snapshot(&self, list: &str, snapshot: Snapshot) -> Result<(), ChatError> {
+    let mut connection = self.connection.lock().unwrap();
+    let transaction = connection.transaction().map_err(storage_error)?;
+
+    transaction.execute("DELETE FROM segments WHERE list = ?1", params![list]).map_err(storage_error)?;
+    transaction.execute("DELETE FROM items WHERE list = ?1", params![list]).map_err(storage_error)?;
+
+    for (segment, index) in snapshot.0.iter().zip(0..) {
+      transaction
+        .execute(
+          "INSERT INTO segments (list, segment, is_top, is_bottom) VALUES (?1, ?2, ?3, ?4)",
+          params![list, index, segment.is_top, segment.is_bottom],
+        )
+        .map_err(storage_error)?;
+
+      for (value, position) in segment.items.iter().zip(0..) {
+        transaction
+          .execute(
+            "INSERT INTO items (list, segment, position, value) VALUES (?1, ?2, ?3, ?4)",
+            params![list, index, position, value],
+          )
+          .map_err(storage_error)?;
+      }
+    }
+
+    transaction.commit().map_err(storage_error)
+  }
+
+  /// An empty cache if nothing was saved under `list`.
+  pub fn load<I: PersistentItem>(&self, list: &str, context: &I::Context) -> Result<AsyncListCache<I>, ChatError> {
+    let connection = self.connection.lock().unwrap();
+
+    let mut segment_query =
+      connection.prepare("SELECT segment, is_top, is_bottom FROM segments WHERE list = ?1 ORDER BY segment").map_err(storage_error)?;
+    let mut item_query = connection.prepare("SELECT value FROM items WHERE list = ?1 AND segment = ?2 ORDER BY position").map_err(storage_error)?;
+
+    let stored_segments = segment_query
+      .query_map(params![list], |row| Ok((row.get::<_, i64>(0)?, row.get(1)?, row.get(2)?)))
+      .map_err(storage_error)?
+      .collect::<Result<Vec<(i64, bool, bool)>, _>>()
+      .map_err(storage_error)?;
+
+    let mut segments = vec![];
+
+    for (segment, is_top, is_bottom) in stored_segments {
+      let values = item_query
+        .query_map(params![list, segment], |row| row.get::<_, String>(0))
+        .map_err(storage_error)?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(storage_error)?;
+
+      let stored = CacheSegment {
+        is_top,
+        is_bottom,
+        items: values,
+      };
+
+      // an item that can't be read back (e.g. because its format changed) leaves a gap, like an unstorable one
+      segments.extend(split_segment(stored, |value| {
+        serde_json::from_str(value).ok().and_then(|stored| I::from_stored(stored, context))
+      }));
+    }
+
+    Ok(AsyncListCache::from_segments(segments))
+  }
+
+  pub fn remove(&self, list: &str) -> Result<(), ChatError> {
+    let connection = self.connection.lock().unwrap();
+
+    connection.execute("DELETE FROM segments WHERE list = ?1", params![list]).map_err(storage_error)?;
+    connection.execute("DELETE FROM items WHERE list = ?1", params![list]).map_err(storage_error)?;
+
+    Ok(())
+  }
+}
+
+/// Converts every item in a segment, splitting it wherever an item can't be converted, since what's on either
+/// side of it is no longer known to be adjacent.
+fn split_segment<T, U>(segment: CacheSegment<T>, mut convert: impl FnMut(&T) -> Option<U>) -> Vec<CacheSegment<U>> {
+  let mut segments = vec![CacheSegment {
+    is_top: segment.is_top,
+    is_bottom: false,
+    items: vec![],
+  }];
+
+  for item in &segment.items {
+    match convert(item) {
+      Some(converted) => segments.last_mut().unwrap().items.push(converted),
+      None => segments.push(CacheSegment {
+        is_top: false,
+        is_bottom: false,
+        items: vec![],
+      }),
+    }
+  }
+
+  segments.last_mut().unwrap().is_bottom = segment.is_bottom;
+  segments.retain(|segment| !segment.items.is_empty());
+
+  segments
+}
@@ -0,0 +1,120 @@
+#[allow(unused_imports)]
+use scope_chat::async_list::{AsyncListIndex, AsyncListItem, AsyncListResult};
+
+#[allow(unused_imports)]
+use crate::{
+  async_list::{refcacheslice::Exists, AsyncListCache},
+  persistent::{PersistentItem, PersistentStore},
+};
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct StoredItem(i64);
+
+impl AsyncListItem for StoredItem {
+  type Identifier = i64;
+
+  fn get_list_identifier(&self) -> Self::Identifier {
+    self.0
+  }
+}
+
+impl PersistentItem for StoredItem {
+  type Stored = i64;
+  type Context = ();
+
+  // negative items stand in for ones that aren't worth keeping, like unsent messages
+  fn to_stored(&self) -> Option<i64> {
+    (self.0 >= 0).then_some(self.0)
+  }
+
+  fn from_stored(stored: i64, _: &()) -> Option<Self> {
+    Some(StoredItem(stored))
+  }
+}
+
+#[allow(dead_code)]
+fn assert_item(result: Exists<AsyncListResult<StoredItem>>, item: i64, is_top_in: bool, is_bottom_in: bool) {
+  if let Exists::Yes(AsyncListResult { content, is_top, is_bottom }) = result {
+    assert_eq!(content, StoredItem(item));
+    assert_eq!(is_top, is_top_in);
+    assert_eq!(is_bottom, is_bottom_in);
+  } else {
+    panic!("Expected eq yes")
+  }
+}
+
+#[test]
+pub fn store_round_trips_segments_and_bounds() {
+  let store = PersistentStore::in_memory().unwrap();
+  let mut cache = AsyncListCache::new();
+
+  for i in 10..13 {
+    cache.append_bottom(StoredItem(i));
+  }
+
+  cache.insert(AsyncListIndex::RelativeToTop(0), StoredItem(0), true, false);
+  cache.insert(AsyncListIndex::After(0), StoredItem(1), false, false);
+  cache.insert_detached(StoredItem(5));
+
+  store.save("list", &cache).unwrap();
+
+  let mut loaded = store.load::<StoredItem>("list", &()).unwrap();
+
+  assert_eq!(loaded.bounded_at_top_by(), Some(0));
+  assert_eq!(loaded.bounded_at_bottom_by(), Some(12));
+  assert_item(loaded.get(AsyncListIndex::After(0)), 1, false, false);
+  assert_item(loaded.get(AsyncListIndex::Before(11)), 10, false, false);
+  assert_eq!(loaded.find(&5), Some(StoredItem(5)));
+  assert!(matches!(loaded.get(AsyncListIndex::After(1)), Exists::Unknown));
+  assert!(matches!(loaded.get(AsyncListIndex::After(5)), Exists::Unknown));
+}
+
+#[test]
+pub fn store_splits_segments_around_unstored_items() {
+  let store = PersistentStore::in_memory().unwrap();
+  let mut cache = AsyncListCache::new();
+
+  cache.append_bottom(StoredItem(0));
+  cache.append_bottom(StoredItem(-1));
+  cache.append_bottom(StoredItem(2));
+
+  store.save("list", &cache).unwrap();
+
+  let mut loaded = store.load::<StoredItem>("list", &()).unwrap();
+
+  assert_eq!(loaded.find(&-1), None);
+  assert_eq!(loaded.bounded_at_bottom_by(), Some(2));
+  // what was on either side of the unstored item can't be known to be adjacent
+  assert!(matches!(loaded.get(AsyncListIndex::After(0)), Exists::Unknown));
+  assert!(matches!(loaded.get(AsyncListIndex::Before(2)), Exists::Unknown));
+}
+
+#[test]
+pub fn store_keeps_lists_apart() {
+  let store = PersistentStore::in_memory().unwrap();
+  let mut first = AsyncListCache::new();
+  let mut second = AsyncListCache::new();
+
+  first.append_bottom(StoredItem(1));
+  second.append_bottom(StoredItem(2));
+
+  store.save("first", &first).unwrap();
+  store.save("second", &second).unwrap();
+
+  // saving again replaces what was there
+  first.append_bottom(StoredItem(3));
+  store.save("first", &first).unwrap();
+
+  let first = store.load::<StoredItem>("first", &()).unwrap();
+  let second = store.load::<StoredItem>("second", &()).unwrap();
+
+  assert_eq!(first.bounded_at_bottom_by(), Some(3));
+  assert_eq!(second.bounded_at_bottom_by(), Some(2));
+  assert_eq!(second.find(&1), None);
+
+  store.remove("second").unwrap();
+
+  assert_eq!(store.load::<StoredItem>("second", &()).unwrap().bounded_at_bottom_by(), None);
+  assert_eq!(store.load::<StoredItem>("missing", &()).unwrap().find(&1), None);
+}
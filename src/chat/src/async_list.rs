@@ -1,38 +1,58 @@
 use std::{fmt::Debug, future::Future, hash::Hash, sync::Arc};
 
+use crate::error::ChatError;
+
 pub trait AsyncList {
   type Content: AsyncListItem;
 
-  fn bounded_at_top_by(&self) -> impl Future<Output = Option<<Self::Content as AsyncListItem>::Identifier>>;
+  fn bounded_at_top_by(&self) -> impl Future<Output = Result<Option<<Self::Content as AsyncListItem>::Identifier>, ChatError>>;
   fn get(
     &self,
     index: AsyncListIndex<<Self::Content as AsyncListItem>::Identifier>,
-  ) -> impl Future<Output = Option<AsyncListResult<Self::Content>>> + Send;
-  fn find(&self, identifier: &<Self::Content as AsyncListItem>::Identifier) -> impl Future<Output = Option<Self::Content>>;
-  fn bounded_at_bottom_by(&self) -> impl Future<Output = Option<<Self::Content as AsyncListItem>::Identifier>>;
+  ) -> impl Future<Output = Result<Option<AsyncListResult<Self::Content>>, ChatError>> + Send;
+  fn find(&self, identifier: &<Self::Content as AsyncListItem>::Identifier) -> impl Future<Output = Result<Option<Self::Content>, ChatError>> + Send;
+  fn bounded_at_bottom_by(&self) -> impl Future<Output = Result<Option<<Self::Content as AsyncListItem>::Identifier>, ChatError>>;
+
+  /// Like `get`, except that where the next item hasn't been loaded but items further along have, this returns the gap
+  /// between them instead of loading it. Lists that don't keep track of gaps never return one.
+  fn get_entry(
+    &self,
+    index: AsyncListIndex<<Self::Content as AsyncListItem>::Identifier>,
+  ) -> impl Future<Output = Result<Option<AsyncListResult<AsyncListEntry<Self::Content>>>, ChatError>> + Send {
+    let item = self.get(index);
+
+    async move { Ok(item.await?.map(|result| result.map(AsyncListEntry::Item))) }
+  }
 }
 
 impl<L: AsyncList> AsyncList for Arc<L> {
   type Content = L::Content;
 
-  fn bounded_at_bottom_by(&self) -> impl Future<Output = Option<<Self::Content as AsyncListItem>::Identifier>> {
+  fn bounded_at_bottom_by(&self) -> impl Future<Output = Result<Option<<Self::Content as AsyncListItem>::Identifier>, ChatError>> {
     (**self).bounded_at_bottom_by()
   }
 
-  fn bounded_at_top_by(&self) -> impl Future<Output = Option<<Self::Content as AsyncListItem>::Identifier>> {
+  fn bounded_at_top_by(&self) -> impl Future<Output = Result<Option<<Self::Content as AsyncListItem>::Identifier>, ChatError>> {
     (**self).bounded_at_top_by()
   }
 
-  fn find(&self, identifier: &<Self::Content as AsyncListItem>::Identifier) -> impl Future<Output = Option<Self::Content>> {
+  fn find(&self, identifier: &<Self::Content as AsyncListItem>::Identifier) -> impl Future<Output = Result<Option<Self::Content>, ChatError>> + Send {
     (**self).find(identifier)
   }
 
   fn get(
     &self,
     index: AsyncListIndex<<Self::Content as AsyncListItem>::Identifier>,
-  ) -> impl Future<Output = Option<AsyncListResult<Self::Content>>> + Send {
+  ) -> impl Future<Output = Result<Option<AsyncListResult<Self::Content>>, ChatError>> + Send {
     (**self).get(index)
   }
+
+  fn get_entry(
+    &self,
+    index: AsyncListIndex<<Self::Content as AsyncListItem>::Identifier>,
+  ) -> impl Future<Output = Result<Option<AsyncListResult<AsyncListEntry<Self::Content>>>, ChatError>> + Send {
+    (**self).get_entry(index)
+  }
 }
 
 pub trait AsyncListItem: Clone {
@@ -74,6 +94,32 @@ pub struct AsyncListResult<T> {
   pub is_bottom: bool,
 }
 
+impl<T> AsyncListResult<T> {
+  pub fn map<U>(self, f: impl FnOnce(T) -> U) -> AsyncListResult<U> {
+    AsyncListResult {
+      content: f(self.content),
+      is_top: self.is_top,
+      is_bottom: self.is_bottom,
+    }
+  }
+}
+
+/// Items of a list that haven't been loaded, between two that have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsyncListGap<I> {
+  /// The loaded item directly above the gap
+  pub above: I,
+  /// The loaded item directly below the gap
+  pub below: I,
+}
+
+/// What `AsyncList::get_entry` found at an index.
+#[derive(Clone, Debug)]
+pub enum AsyncListEntry<T: AsyncListItem> {
+  Item(T),
+  Gap(AsyncListGap<T::Identifier>),
+}
+
 impl<I: Clone + Debug> Debug for AsyncListResult<I> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.debug_struct("AsyncListResult").field("content", &self.content).field("is_top", &self.is_top).field("is_bottom", &self.is_bottom).finish()
@@ -1,18 +1,44 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{fmt::Debug, future::Future, sync::Arc};
 
+use chrono::{DateTime, Utc};
 use tokio::sync::broadcast;
 
-use crate::{async_list::AsyncList, message::Message};
+use crate::{
+  async_list::{AsyncList, AsyncListItem},
+  error::ChatError,
+  message::Message,
+};
 
 pub trait Channel: AsyncList<Content = Self::Message> + Send + Sync + Clone {
   type Message: Message<Identifier = Self::Identifier>;
   type Identifier: Sized + Copy + Clone + Debug + Eq + PartialEq;
 
-  fn get_receiver(&self) -> broadcast::Receiver<Self::Message>;
+  fn get_receiver(&self) -> broadcast::Receiver<ChannelEvent<Self::Message>>;
 
-  fn send_message(&self, content: String, nonce: String) -> Self::Message;
+  /// Creates the placeholder shown while a message is being sent. The nonce is what ties it to
+  /// the message the backend eventually reports.
+  fn pending_message(&self, content: String, nonce: String) -> Self::Message;
+
+  /// Sends a message created by `pending_message`. Sending it again after a failure reuses its
+  /// nonce, so the backend can still match it up with the placeholder.
+  fn send_message(&self, message: &Self::Message) -> impl Future<Output = Result<(), ChatError>> + Send;
 
   fn get_identifier(&self) -> Self::Identifier;
+
+  /// An identifier that goes right before the first message sent at or after `time`, whether or not a message has it,
+  /// for loading the list from `AsyncListIndex::After` it. Lists that don't order their identifiers by time can't do this.
+  fn identifier_at(&self, _time: DateTime<Utc>) -> Option<<Self::Message as AsyncListItem>::Identifier> {
+    None
+  }
+}
+
+#[derive(Clone)]
+pub enum ChannelEvent<M: Message> {
+  Created(M),
+  /// The message's identifier is unchanged, but its contents may not be
+  Updated(M),
+  Deleted(<M as Message>::Identifier),
+  BulkDeleted(Vec<<M as Message>::Identifier>),
 }
 
 impl<C: Channel> Channel for Arc<C> {
@@ -23,11 +49,19 @@ impl<C: Channel> Channel for Arc<C> {
     (**self).get_identifier()
   }
 
-  fn get_receiver(&self) -> broadcast::Receiver<Self::Message> {
+  fn get_receiver(&self) -> broadcast::Receiver<ChannelEvent<Self::Message>> {
     (**self).get_receiver()
   }
 
-  fn send_message(&self, content: String, nonce: String) -> Self::Message {
-    (**self).send_message(content, nonce)
+  fn pending_message(&self, content: String, nonce: String) -> Self::Message {
+    (**self).pending_message(content, nonce)
+  }
+
+  fn send_message(&self, message: &Self::Message) -> impl Future<Output = Result<(), ChatError>> + Send {
+    (**self).send_message(message)
+  }
+
+  fn identifier_at(&self, time: DateTime<Utc>) -> Option<<Self::Message as AsyncListItem>::Identifier> {
+    (**self).identifier_at(time)
   }
 }
@@ -1,6 +1,10 @@
 use std::{fmt::Debug, future::Future};
 
-use crate::channel::Channel;
+use crate::{
+  channel::Channel,
+  error::ChatError,
+  guild::{ChannelListing, Guild},
+};
 
 pub trait ClientConstructor<C: Client> {
   type ConstructorArguments;
@@ -12,6 +16,12 @@ pub trait ClientConstructor<C: Client> {
 pub trait Client {
   type Identifier: Sized + Copy + Clone + Debug + Eq + PartialEq;
   type Channel: Channel;
+  type Guild: Guild<ChannelIdentifier = Self::Identifier>;
 
-  fn channel(identifier: Self::Identifier) -> impl Future<Output = Option<Self::Channel>>;
+  fn channel(&self, identifier: Self::Identifier) -> impl Future<Output = Result<Self::Channel, ChatError>>;
+
+  fn guilds(&self) -> impl Future<Output = Vec<Self::Guild>>;
+  fn guild(&self, identifier: <Self::Guild as Guild>::Identifier) -> impl Future<Output = Option<Self::Guild>>;
+
+  fn direct_message_channels(&self) -> impl Future<Output = Result<Vec<ChannelListing<Self::Identifier>>, ChatError>>;
 }
@@ -0,0 +1,291 @@
+use chrono::{DateTime, Local, Utc};
+
+/// What a message says, with its formatting, as blocks from top to bottom.
+///
+/// Backends parse their own markup into this, so the same formatting renders, and can be searched, the same way
+/// whichever backend a message came from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RichContent {
+  pub blocks: Vec<Block>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Block {
+  Paragraph(Vec<Inline>),
+  /// `level` 1 is the largest
+  Heading {
+    level: u8,
+    content: Vec<Inline>,
+  },
+  /// Small, dimmed text, like a footnote
+  Subtext(Vec<Inline>),
+  CodeBlock {
+    language: Option<String>,
+    code: String,
+  },
+  Quote(Vec<Block>),
+  List(List),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct List {
+  /// The number of the first item, or `None` for a bulleted list
+  pub start: Option<u64>,
+  pub items: Vec<Vec<Block>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Inline {
+  Text(Span),
+  Code(String),
+  Link {
+    url: String,
+    content: Vec<Inline>,
+  },
+  Mention(Mention),
+  Emoji(Emoji),
+  Timestamp {
+    time: DateTime<Utc>,
+    format: TimestampFormat,
+  },
+  /// Hidden until it's clicked, whatever is in it
+  Spoiler(Vec<Inline>),
+  LineBreak,
+}
+
+/// A run of text in a single style. Nested formatting is flattened into the styles of each run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+  pub text: String,
+  pub style: TextStyle,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TextStyle {
+  pub bold: bool,
+  pub italic: bool,
+  pub underline: bool,
+  pub strikethrough: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mention {
+  pub kind: MentionKind,
+  /// The backend's identifier for what is mentioned, as text
+  pub id: String,
+  /// What to show for it, if the backend knows, e.g. the user's display name
+  pub name: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MentionKind {
+  User,
+  Channel,
+  Role,
+  /// Everyone who can see the channel
+  Everyone,
+  /// Everyone who can see the channel and is online
+  Here,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Emoji {
+  Unicode(String),
+  /// An image, e.g. one uploaded to a guild
+  Custom {
+    name: String,
+    url: String,
+    animated: bool,
+  },
+}
+
+/// How a timestamp is shown. It's always shown in the reader's time zone.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TimestampFormat {
+  /// 16:20
+  ShortTime,
+  /// 16:20:30
+  LongTime,
+  /// 20/04/2021
+  ShortDate,
+  /// 20 April 2021
+  LongDate,
+  /// 20 April 2021 16:20
+  #[default]
+  ShortDateTime,
+  /// Tuesday, 20 April 2021 16:20
+  LongDateTime,
+  /// 2 months ago
+  Relative,
+}
+
+impl TimestampFormat {
+  pub fn format(&self, time: DateTime<Utc>) -> String {
+    let local = time.with_timezone(&Local);
+
+    match self {
+      TimestampFormat::ShortTime => local.format("%H:%M").to_string(),
+      TimestampFormat::LongTime => local.format("%H:%M:%S").to_string(),
+      TimestampFormat::ShortDate => local.format("%d/%m/%Y").to_string(),
+      TimestampFormat::LongDate => local.format("%-d %B %Y").to_string(),
+      TimestampFormat::ShortDateTime => local.format("%-d %B %Y %H:%M").to_string(),
+      TimestampFormat::LongDateTime => local.format("%A, %-d %B %Y %H:%M").to_string(),
+      TimestampFormat::Relative => relative(time, Utc::now()),
+    }
+  }
+}
+
+fn relative(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+  let seconds = now.signed_duration_since(time).num_seconds();
+
+  let (amount, unit) = match seconds.abs() {
+    s if s < 60 => (s, "second"),
+    s if s < 60 * 60 => (s / 60, "minute"),
+    s if s < 60 * 60 * 24 => (s / (60 * 60), "hour"),
+    s if s < 60 * 60 * 24 * 30 => (s / (60 * 60 * 24), "day"),
+    s if s < 60 * 60 * 24 * 365 => (s / (60 * 60 * 24 * 30), "month"),
+    s => (s / (60 * 60 * 24 * 365), "year"),
+  };
+
+  let plural = if amount == 1 { "" } else { "s" };
+
+  if seconds < 0 {
+    format!("in {} {}{}", amount, unit, plural)
+  } else {
+    format!("{} {}{} ago", amount, unit, plural)
+  }
+}
+
+impl RichContent {
+  /// Unformatted text, kept as it is. Each line is broken where it was.
+  pub fn plain(text: &str) -> Self {
+    if text.is_empty() {
+      return RichContent::default();
+    }
+
+    let mut inlines = vec![];
+
+    for (i, line) in text.split('\n').enumerate() {
+      if i > 0 {
+        inlines.push(Inline::LineBreak);
+      }
+
+      if !line.is_empty() {
+        inlines.push(Inline::text(line, TextStyle::default()));
+      }
+    }
+
+    RichContent {
+      blocks: vec![Block::Paragraph(inlines)],
+    }
+  }
+
+  /// The text without its formatting, one block per line, e.g. to search it or to show it where formatting can't be.
+  pub fn plain_text(&self) -> String {
+    let mut text = String::new();
+
+    blocks_text(&self.blocks, &mut text);
+
+    text
+  }
+
+  /// Whether the text contains `query`, ignoring case and formatting.
+  pub fn matches(&self, query: &str) -> bool {
+    self.plain_text().to_lowercase().contains(&query.to_lowercase())
+  }
+
+  /// Calls `f` with every mention, wherever it is, e.g. to fill in their names.
+  pub fn for_each_mention_mut(&mut self, mut f: impl FnMut(&mut Mention)) {
+    blocks_mentions(&mut self.blocks, &mut f);
+  }
+}
+
+impl Mention {
+  /// What to show for it, e.g. `@name`, or `#id` when the backend doesn't know the channel's name.
+  pub fn label(&self) -> String {
+    let prefix = match self.kind {
+      MentionKind::User | MentionKind::Role | MentionKind::Everyone | MentionKind::Here => '@',
+      MentionKind::Channel => '#',
+    };
+
+    match (&self.name, self.kind) {
+      (Some(name), _) => format!("{}{}", prefix, name),
+      (None, MentionKind::Everyone) => "@everyone".to_owned(),
+      (None, MentionKind::Here) => "@here".to_owned(),
+      (None, _) => format!("{}{}", prefix, self.id),
+    }
+  }
+}
+
+impl Inline {
+  pub fn text(text: impl Into<String>, style: TextStyle) -> Self {
+    Inline::Text(Span { text: text.into(), style })
+  }
+}
+
+fn blocks_mentions(blocks: &mut [Block], f: &mut impl FnMut(&mut Mention)) {
+  for block in blocks {
+    match block {
+      Block::Paragraph(content) | Block::Heading { content, .. } | Block::Subtext(content) => inlines_mentions(content, f),
+      Block::CodeBlock { .. } => {}
+      Block::Quote(blocks) => blocks_mentions(blocks, f),
+      Block::List(list) => {
+        for item in &mut list.items {
+          blocks_mentions(item, f);
+        }
+      }
+    }
+  }
+}
+
+fn inlines_mentions(inlines: &mut [Inline], f: &mut impl FnMut(&mut Mention)) {
+  for inline in inlines {
+    match inline {
+      Inline::Mention(mention) => f(mention),
+      Inline::Link { content, .. } | Inline::Spoiler(content) => inlines_mentions(content, f),
+      _ => {}
+    }
+  }
+}
+
+fn blocks_text(blocks: &[Block], text: &mut String) {
+  for (i, block) in blocks.iter().enumerate() {
+    if i > 0 {
+      text.push('\n');
+    }
+
+    match block {
+      Block::Paragraph(content) | Block::Heading { content, .. } | Block::Subtext(content) => inlines_text(content, text),
+      Block::CodeBlock { code, .. } => text.push_str(code),
+      Block::Quote(blocks) => blocks_text(blocks, text),
+      Block::List(list) => {
+        for (i, item) in list.items.iter().enumerate() {
+          if i > 0 {
+            text.push('\n');
+          }
+
+          blocks_text(item, text);
+        }
+      }
+    }
+  }
+}
+
+fn inlines_text(inlines: &[Inline], text: &mut String) {
+  for inline in inlines {
+    match inline {
+      Inline::Text(span) => text.push_str(&span.text),
+      Inline::Code(code) => text.push_str(code),
+      Inline::Link { content, .. } | Inline::Spoiler(content) => inlines_text(content, text),
+      Inline::Mention(mention) => text.push_str(&mention.label()),
+      Inline::Emoji(Emoji::Unicode(emoji)) => text.push_str(emoji),
+      Inline::Emoji(Emoji::Custom { name, .. }) => {
+        text.push(':');
+        text.push_str(name);
+        text.push(':');
+      }
+      Inline::Timestamp { time, format } => text.push_str(&format.format(*time)),
+      Inline::LineBreak => text.push('\n'),
+    }
+  }
+}
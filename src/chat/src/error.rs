@@ -0,0 +1,57 @@
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChatErrorKind {
+  /// The backend rejected the client's credentials
+  Unauthorized,
+  /// The client isn't allowed to do this, e.g. read a channel it can't see
+  Forbidden,
+  NotFound,
+  RateLimited,
+  /// The backend couldn't be reached
+  Network,
+  /// The backend was reached, but failed to handle the request
+  Unavailable,
+  /// Something stored locally, like the message cache, couldn't be read or written
+  Storage,
+  Other,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChatError {
+  kind: ChatErrorKind,
+  message: String,
+}
+
+impl ChatError {
+  pub fn new(kind: ChatErrorKind, message: impl Into<String>) -> Self {
+    ChatError {
+      kind,
+      message: message.into(),
+    }
+  }
+
+  pub fn kind(&self) -> ChatErrorKind {
+    self.kind
+  }
+
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+
+  /// Whether making the same request again might succeed.
+  pub fn is_retryable(&self) -> bool {
+    matches!(
+      self.kind,
+      ChatErrorKind::RateLimited | ChatErrorKind::Network | ChatErrorKind::Unavailable
+    )
+  }
+}
+
+impl Display for ChatError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{:?}: {}", self.kind, self.message)
+  }
+}
+
+impl std::error::Error for ChatError {}
@@ -0,0 +1,51 @@
+use std::fmt::Debug;
+
+use gpui::{IntoElement, SharedString};
+
+use crate::message::IconRenderConfig;
+
+pub trait Guild: Clone + Send + Sync {
+  type Identifier: Sized + Copy + Clone + Debug + Eq + PartialEq;
+  type ChannelIdentifier: Sized + Copy + Clone + Debug + Eq + PartialEq;
+  type Icon: IntoElement + Clone;
+
+  fn get_identifier(&self) -> Self::Identifier;
+  fn get_name(&self) -> SharedString;
+  fn get_icon(&self, config: IconRenderConfig) -> Self::Icon;
+
+  /// Categories in display order. Channels that don't belong to a category are grouped
+  /// into a leading category with no identifier.
+  fn get_categories(&self) -> Vec<ChannelCategory<Self::ChannelIdentifier>>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelKind {
+  Text,
+  Announcement,
+  Voice,
+  Forum,
+  DirectMessage,
+  Other,
+}
+
+impl ChannelKind {
+  /// Whether a channel of this kind can be opened as a message list.
+  pub fn is_text_based(&self) -> bool {
+    matches!(self, ChannelKind::Text | ChannelKind::Announcement | ChannelKind::DirectMessage)
+  }
+}
+
+/// A lightweight description of a channel, used for navigation without loading the channel itself.
+#[derive(Clone, Debug)]
+pub struct ChannelListing<I: Clone> {
+  pub identifier: I,
+  pub name: SharedString,
+  pub kind: ChannelKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChannelCategory<I: Clone> {
+  pub identifier: Option<I>,
+  pub name: Option<SharedString>,
+  pub channels: Vec<ChannelListing<I>>,
+}
@@ -1,4 +1,7 @@
 pub mod async_list;
 pub mod channel;
 pub mod client;
+pub mod content;
+pub mod error;
+pub mod guild;
 pub mod message;
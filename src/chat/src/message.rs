@@ -3,19 +3,35 @@ use std::fmt::Debug;
 use chrono::{DateTime, Utc};
 use gpui::{IntoElement, Render, View, WindowContext};
 
-use crate::async_list::AsyncListItem;
+use crate::{async_list::AsyncListItem, content::RichContent, error::ChatError};
 
 pub trait Message: Clone + AsyncListItem + Send {
-  type Identifier: Sized + Copy + Clone + Debug + Eq + PartialEq;
+  type Identifier: Sized + Copy + Clone + Debug + Eq + PartialEq + Send;
   type Author: MessageAuthor<Identifier = <Self as Message>::Identifier>;
   type Content: Render;
 
   fn get_author(&self) -> Self::Author;
   fn get_content(&self, cx: &mut WindowContext) -> View<Self::Content>;
+  /// What the message says, with its formatting, the same way for every backend.
+  fn get_rich_content(&self) -> RichContent;
   fn get_identifier(&self) -> Option<<Self as Message>::Identifier>;
   fn get_nonce(&self) -> impl PartialEq;
   fn should_group(&self, previous: &Self) -> bool;
   fn get_timestamp(&self) -> Option<DateTime<Utc>>;
+
+  /// Messages received from the backend are always `Sent`.
+  fn get_send_status(&self) -> SendStatus;
+  /// A copy of this message in the given state, e.g. to mark a pending message as failed.
+  fn with_send_status(&self, status: SendStatus) -> Self;
+}
+
+#[derive(Clone, Debug)]
+pub enum SendStatus {
+  /// The message is on its way to the backend
+  Pending,
+  /// The backend accepted the message, but hasn't reported it back yet
+  Sent,
+  Failed(ChatError),
 }
 
 #[derive(Debug, Clone, Copy)]
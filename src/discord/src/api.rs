@@ -0,0 +1,181 @@
+use std::{
+  sync::RwLock,
+  time::{Instant, SystemTime},
+};
+
+use scope_chat::error::{ChatError, ChatErrorKind};
+use serenity::{
+  all::{Channel, ChannelId, ChunkGuildFilter, CreateMessage, GuildId, Member, Message, MessageId, MessagePagination, Nonce, PrivateChannel, UserId},
+  async_trait,
+};
+
+use crate::{client::SerenityClient, error::chat_error, scheduler::RateLimit};
+
+/// The Discord REST endpoints the client depends on.
+///
+/// `DiscordClient` only talks to Discord through this trait, so it can be pointed at something other than
+/// the real API, e.g. the fixture-backed `mock::MockDiscord`.
+#[async_trait]
+pub trait DiscordApi: Send + Sync {
+  async fn get_channel(&self, channel_id: ChannelId) -> Result<Channel, ChatError>;
+
+  /// Messages come back newest first, whichever direction `target` pages in.
+  async fn get_messages(&self, channel_id: ChannelId, target: Option<MessagePagination>, limit: u8) -> Result<Vec<Message>, ChatError>;
+  async fn get_message(&self, channel_id: ChannelId, message_id: MessageId) -> Result<Option<Message>, ChatError>;
+  async fn get_member(&self, guild_id: GuildId, user_id: UserId) -> Result<Option<Member>, ChatError>;
+
+  async fn send_message(&self, channel_id: ChannelId, content: String, nonce: String) -> Result<(), ChatError>;
+
+  async fn get_dm_channels(&self) -> Result<Vec<PrivateChannel>, ChatError>;
+
+  /// Asks the gateway for these members of a guild, all at once. This isn't a REST request: the answer comes back as
+  /// `GUILD_MEMBERS_CHUNK` events, which are handed to `DiscordClient::members_chunk`.
+  async fn request_guild_members(&self, guild_id: GuildId, user_ids: Vec<UserId>) -> Result<(), ChatError>;
+
+  /// The rate limits the API is waiting out before it makes requests again.
+  async fn rate_limits(&self) -> Vec<RateLimit> {
+    vec![]
+  }
+}
+
+#[async_trait]
+impl DiscordApi for SerenityClient {
+  async fn get_channel(&self, channel_id: ChannelId) -> Result<Channel, ChatError> {
+    channel_id.to_channel(self).await.map_err(chat_error)
+  }
+
+  async fn get_messages(&self, channel_id: ChannelId, target: Option<MessagePagination>, limit: u8) -> Result<Vec<Message>, ChatError> {
+    self.http.get_messages(channel_id, target, Some(limit)).await.map_err(chat_error)
+  }
+
+  async fn get_message(&self, channel_id: ChannelId, message_id: MessageId) -> Result<Option<Message>, ChatError> {
+    match self.http.get_message(channel_id, message_id).await.map_err(chat_error) {
+      Ok(message) => Ok(Some(message)),
+      Err(e) if e.kind() == ChatErrorKind::NotFound => Ok(None),
+      Err(e) => Err(e),
+    }
+  }
+
+  async fn get_member(&self, guild_id: GuildId, user_id: UserId) -> Result<Option<Member>, ChatError> {
+    match guild_id.member(self, user_id).await.map_err(chat_error) {
+      Ok(member) => Ok(Some(member)),
+      Err(e) if e.kind() == ChatErrorKind::NotFound => Ok(None),
+      Err(e) => Err(e),
+    }
+  }
+
+  async fn send_message(&self, channel_id: ChannelId, content: String, nonce: String) -> Result<(), ChatError> {
+    channel_id
+      .send_message(
+        self.http.clone(),
+        CreateMessage::new().content(content).enforce_nonce(true).nonce(Nonce::String(nonce)),
+      )
+      .await
+      .map_err(chat_error)?;
+
+    Ok(())
+  }
+
+  async fn get_dm_channels(&self) -> Result<Vec<PrivateChannel>, ChatError> {
+    self.http.get_user_dm_channels().await.map_err(chat_error)
+  }
+
+  async fn request_guild_members(&self, guild_id: GuildId, user_ids: Vec<UserId>) -> Result<(), ChatError> {
+    // a user account only ever has the one shard
+    let runners = self.shard_manager.runners.lock().await;
+    let Some(runner) = runners.values().next() else {
+      return Err(ChatError::new(ChatErrorKind::Network, "Not connected to the gateway"));
+    };
+
+    // Discord takes at most 100 users per request
+    for user_ids in user_ids.chunks(100) {
+      runner.runner_tx.chunk_guild(guild_id, None, false, ChunkGuildFilter::UserIds(user_ids.to_vec()), None);
+    }
+
+    Ok(())
+  }
+
+  async fn rate_limits(&self) -> Vec<RateLimit> {
+    let Some(ratelimiter) = &self.http.ratelimiter else {
+      return vec![];
+    };
+
+    let routes = ratelimiter.routes();
+    let routes = routes.read().await;
+    let mut rate_limits = vec![];
+
+    for (bucket, ratelimit) in routes.iter() {
+      let ratelimit = ratelimit.lock().await;
+
+      if ratelimit.remaining() > 0 {
+        continue;
+      }
+
+      if let Some(reset_in) = ratelimit.reset().and_then(|reset| reset.duration_since(SystemTime::now()).ok()) {
+        rate_limits.push(RateLimit {
+          bucket: format!("{:?}", bucket),
+          retry_at: Instant::now() + reset_in,
+        });
+      }
+    }
+
+    rate_limits
+  }
+}
+
+/// Whichever gateway connection is current. A connection that has failed for good is replaced by a new one, with its
+/// own cache and shards, so requests go through this rather than holding on to one.
+#[derive(Default)]
+pub(crate) struct CurrentConnection(RwLock<Option<SerenityClient>>);
+
+impl CurrentConnection {
+  pub(crate) fn get(&self) -> Option<SerenityClient> {
+    self.0.read().unwrap().clone()
+  }
+
+  pub(crate) fn replace(&self, client: SerenityClient) {
+    *self.0.write().unwrap() = Some(client);
+  }
+
+  fn connected(&self) -> Result<SerenityClient, ChatError> {
+    self.get().ok_or_else(|| ChatError::new(ChatErrorKind::Network, "Not connected to the gateway"))
+  }
+}
+
+#[async_trait]
+impl DiscordApi for CurrentConnection {
+  async fn get_channel(&self, channel_id: ChannelId) -> Result<Channel, ChatError> {
+    self.connected()?.get_channel(channel_id).await
+  }
+
+  async fn get_messages(&self, channel_id: ChannelId, target: Option<MessagePagination>, limit: u8) -> Result<Vec<Message>, ChatError> {
+    self.connected()?.get_messages(channel_id, target, limit).await
+  }
+
+  async fn get_message(&self, channel_id: ChannelId, message_id: MessageId) -> Result<Option<Message>, ChatError> {
+    self.connected()?.get_message(channel_id, message_id).await
+  }
+
+  async fn get_member(&self, guild_id: GuildId, user_id: UserId) -> Result<Option<Member>, ChatError> {
+    self.connected()?.get_member(guild_id, user_id).await
+  }
+
+  async fn send_message(&self, channel_id: ChannelId, content: String, nonce: String) -> Result<(), ChatError> {
+    self.connected()?.send_message(channel_id, content, nonce).await
+  }
+
+  async fn get_dm_channels(&self) -> Result<Vec<PrivateChannel>, ChatError> {
+    self.connected()?.get_dm_channels().await
+  }
+
+  async fn request_guild_members(&self, guild_id: GuildId, user_ids: Vec<UserId>) -> Result<(), ChatError> {
+    self.connected()?.request_guild_members(guild_id, user_ids).await
+  }
+
+  async fn rate_limits(&self) -> Vec<RateLimit> {
+    match self.get() {
+      Some(client) => client.rate_limits().await,
+      None => vec![],
+    }
+  }
+}
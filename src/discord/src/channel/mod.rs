@@ -1,44 +1,529 @@
-use std::sync::{Arc, OnceLock};
+pub mod tests;
+
+use std::{
+  collections::HashMap,
+  future::Future,
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, OnceLock,
+  },
+  time::Duration,
+};
 
-use chrono::Utc;
-use scope_backend_cache::async_list::{refcacheslice::Exists, AsyncListCache};
+use chrono::{DateTime, Utc};
+use scope_backend_cache::{
+  async_list::{eviction::CacheMetrics, refcacheslice::Exists, AsyncListCache},
+  persistent::PersistentStore,
+};
 use scope_chat::{
-  async_list::{AsyncList, AsyncListIndex, AsyncListItem, AsyncListResult},
-  channel::Channel,
+  async_list::{AsyncList, AsyncListEntry, AsyncListIndex, AsyncListItem, AsyncListResult},
+  channel::{Channel, ChannelEvent},
+  error::{ChatError, ChatErrorKind},
+  message::SendStatus,
 };
-use serenity::all::{ChannelId, GetMessages, MessageId};
+use serenity::all::{ChannelId, GuildId, Member, MessageId, MessagePagination, UserId};
 use tokio::sync::{broadcast, Mutex, Semaphore};
 
 use crate::{
   client::DiscordClient,
   message::{DiscordMessage, DiscordMessageData},
+  scheduler::Priority,
   snowflake::Snowflake,
 };
 
 pub struct DiscordChannel {
   channel: Arc<serenity::model::channel::Channel>,
 
-  receiver: broadcast::Receiver<DiscordMessage>,
+  receiver: broadcast::Receiver<ChannelEvent<DiscordMessage>>,
   client: Arc<DiscordClient>,
   cache: Arc<Mutex<AsyncListCache<DiscordMessage>>>,
   blocker: Semaphore,
+
+  save_scheduled: Arc<AtomicBool>,
+  // by the client's clock, so channels can be ranked against each other when evicting
+  last_used: Arc<AtomicU64>,
+  // messages loaded before their author's member was, by author
+  awaiting_members: Arc<std::sync::Mutex<HashMap<UserId, Vec<Snowflake>>>>,
 }
 
+// only this many batches are fetched to catch up with messages sent since the cache was saved, beyond that the
+// channel is loaded from the bottom as usual
+const MAX_CATCH_UP_BATCHES: usize = 4;
+// changes are saved in bulk, since the whole channel is written each time
+const SAVE_DELAY: Duration = Duration::from_secs(5);
+// how many events a view can fall behind on, e.g. during a burst of edits, before it has to reload the channel
+const EVENT_CAPACITY: usize = 256;
+
 impl DiscordChannel {
-  pub(crate) async fn new(client: Arc<DiscordClient>, channel_id: ChannelId) -> Self {
-    let (sender, receiver) = broadcast::channel(10);
+  pub(crate) async fn new(client: Arc<DiscordClient>, channel_id: ChannelId) -> Result<Self, ChatError> {
+    let channel = Arc::new(client.get_channel(channel_id).await?);
+
+    // messages are ordered by their snowflakes, which lets the cache place messages it finds and see gaps
+    let cache = match client.store().cloned() {
+      Some(store) => {
+        let key = client.store_key(channel_id);
+        let context = (client.clone(), channel.clone());
+
+        // like saving, the disk is read off the runtime's threads
+        let loaded = tokio::task::spawn_blocking(move || store.load::<DiscordMessage>(&key, &context))
+          .await
+          .map_err(|e| ChatError::new(ChatErrorKind::Storage, e.to_string()))
+          .and_then(|loaded| loaded);
+
+        loaded.map(AsyncListCache::into_ordered).unwrap_or_else(|e| {
+          log::error!("Failed to load cached messages: {}", e);
+          AsyncListCache::ordered()
+        })
+      }
+      None => AsyncListCache::ordered(),
+    };
 
-    client.add_channel_message_sender(channel_id, sender).await;
+    let (sender, receiver) = broadcast::channel(EVENT_CAPACITY);
 
-    let channel = Arc::new(channel_id.to_channel(client.discord()).await.unwrap());
+    client.add_channel_message_sender(channel_id, sender).await;
 
-    DiscordChannel {
+    Ok(DiscordChannel {
       channel,
       receiver,
       client,
-      cache: Arc::new(Mutex::new(AsyncListCache::new())),
+      cache: Arc::new(Mutex::new(cache)),
       blocker: Semaphore::new(1),
+      save_scheduled: Arc::new(AtomicBool::new(false)),
+      last_used: Arc::new(AtomicU64::new(0)),
+      awaiting_members: Arc::new(std::sync::Mutex::new(HashMap::new())),
+    })
+  }
+
+  pub async fn cache_metrics(&self) -> CacheMetrics {
+    self.cache.lock().await.metrics()
+  }
+
+  pub(crate) async fn cached_len(&self) -> usize {
+    self.cache.lock().await.len()
+  }
+
+  /// The newest message, if the cache knows which it is, without fetching it.
+  pub(crate) async fn cached_bottom(&self) -> Option<Snowflake> {
+    self.cache.lock().await.bounded_at_bottom_by()
+  }
+
+  pub(crate) fn last_used(&self) -> u64 {
+    self.last_used.load(Ordering::SeqCst)
+  }
+
+  /// Drops cached messages until at most `max_items` are left, returning how many were dropped.
+  pub(crate) async fn evict(&self, max_items: usize) -> usize {
+    self.cache.lock().await.evict(max_items)
+  }
+
+  /// Writes the cached messages to the client's store, if it has one.
+  pub async fn save(&self) -> Result<(), ChatError> {
+    let Some(store) = self.client.store().cloned() else {
+      return Ok(());
+    };
+
+    // only the snapshot is taken while the cache is locked, the disk is written to off the runtime's threads
+    let snapshot = PersistentStore::snapshot(&*self.cache.lock().await)?;
+    let key = self.client.store_key(self.channel.id());
+
+    tokio::task::spawn_blocking(move || store.save_snapshot(&key, snapshot))
+      .await
+      .map_err(|e| ChatError::new(ChatErrorKind::Storage, e.to_string()))?
+  }
+
+  fn schedule_save(&self) {
+    if self.client.store().is_none() || self.save_scheduled.swap(true, Ordering::SeqCst) {
+      return;
+    }
+
+    let channel = self.clone();
+
+    tokio::spawn(async move {
+      tokio::time::sleep(SAVE_DELAY).await;
+
+      channel.save_scheduled.store(false, Ordering::SeqCst);
+
+      if let Err(e) = channel.save().await {
+        log::error!("Failed to save cached messages: {}", e);
+      }
+    });
+  }
+
+  /// Fetches the messages sent after `from`, the bottom of the list when it was last known. Returns them oldest first,
+  /// or `None` if there were too many to catch up with. Nobody is waiting on them, so pages the user is loading go first.
+  async fn catch_up(&self, lock: &mut AsyncListCache<DiscordMessage>, from: Snowflake) -> Result<Option<Vec<DiscordMessage>>, ChatError> {
+    let Some(mut newest) = lock.find(&from) else {
+      return Ok(None);
+    };
+
+    let mut sent = vec![];
+
+    for _ in 0..MAX_CATCH_UP_BATCHES {
+      // NEWEST first
+      let v = self
+        .client
+        .get_messages(
+          self.channel.id(),
+          Some(MessagePagination::After(MessageId::new(newest.get_list_identifier().0))),
+          DISCORD_MESSAGE_BATCH_SIZE,
+          Priority::Prefetch,
+        )
+        .await?;
+
+      if v.is_empty() {
+        // nothing was sent, so the old bottom still is the bottom
+        lock.insert(AsyncListIndex::RelativeToBottom(0), newest.clone(), false, true);
+
+        return Ok(Some(sent));
+      }
+
+      let reached_bottom = v.len() < DISCORD_MESSAGE_BATCH_SIZE as usize;
+      let page = self.load_page(v).await;
+      let anchor = AsyncListIndex::After(newest.get_list_identifier());
+
+      newest = page.last().unwrap().clone();
+      sent.extend(page.iter().cloned());
+      lock.insert_range(anchor, page, false, reached_bottom);
+
+      if reached_bottom {
+        return Ok(Some(sent));
+      }
     }
+
+    Ok(None)
+  }
+
+  /// Fetches the messages sent since the bottom of the list was fetched, whose events were missed while the gateway
+  /// was disconnected. Returns them oldest first.
+  pub(crate) async fn backfill(&self) -> Result<Vec<DiscordMessage>, ChatError> {
+    let permit = self.blocker.acquire().await;
+    let mut lock = self.cache.lock().await;
+
+    // if the bottom hasn't been fetched yet, it will be up to date when it is
+    let Some(from) = lock.unbound_bottom() else {
+      return Ok(vec![]);
+    };
+
+    // if this fails the bottom stays unbound, and is fetched again the next time it's loaded
+    let sent = self.catch_up(&mut lock, from).await?.unwrap_or_else(|| {
+      log::warn!("Too many messages were missed in {} to fetch them all", self.channel.id());
+      vec![]
+    });
+
+    drop(permit);
+    drop(lock);
+
+    self.schedule_save();
+
+    Ok(sent)
+  }
+
+  pub(crate) fn channel_id(&self) -> ChannelId {
+    self.channel.id()
+  }
+
+  pub(crate) fn guild_id(&self) -> Option<GuildId> {
+    match &*self.channel {
+      serenity::model::channel::Channel::Guild(channel) => Some(channel.guild_id),
+      _ => None,
+    }
+  }
+
+  /// Wraps a message sent in this channel, with its author's member if that's known.
+  pub(crate) fn load_message(&self, message: serenity::model::channel::Message) -> DiscordMessage {
+    let member = self.guild_id().and_then(|guild_id| {
+      let members = self.client.members();
+
+      members.insert_from_message(guild_id, &message).or_else(|| members.get(guild_id, message.author.id))
+    });
+
+    DiscordMessage::from_serenity(self.client.clone(), Arc::new(message), self.channel.clone(), member)
+  }
+
+  /// Loads a page of messages, which Discord returns newest first, in list order (oldest first).
+  async fn load_page(&self, messages: Vec<serenity::model::channel::Message>) -> Vec<DiscordMessage> {
+    let page = messages.into_iter().rev().map(|message| self.load_message(message)).collect::<Vec<_>>();
+
+    self.request_members(&page).await;
+
+    page
+  }
+
+  /// Asks for the members that sent these messages and aren't known yet, in one request. The messages are updated
+  /// once they arrive, see `members_loaded`.
+  pub(crate) async fn request_members(&self, messages: &[DiscordMessage]) {
+    let Some(guild_id) = self.guild_id() else {
+      return;
+    };
+
+    let members = self.client.members();
+
+    let authors = {
+      let mut awaiting = self.awaiting_members.lock().unwrap();
+      let mut authors = vec![];
+
+      for message in messages {
+        let DiscordMessageData::Received(message, None) = &message.data else {
+          continue;
+        };
+
+        // webhooks aren't members, and neither are users Discord has already said aren't
+        if message.webhook_id.is_some() || members.contains(guild_id, message.author.id) {
+          continue;
+        }
+
+        awaiting.entry(message.author.id).or_default().push(message.id.into());
+        authors.push(message.author.id);
+      }
+
+      authors
+    };
+
+    let missing = members.claim_missing(guild_id, authors);
+
+    if missing.is_empty() {
+      return;
+    }
+
+    if let Err(e) = self.client.api().request_guild_members(guild_id, missing.clone()).await {
+      log::error!("Failed to request members: {}", e);
+
+      members.release(guild_id, &missing);
+    }
+  }
+
+  /// The cached messages that were waiting on these members, now with them.
+  pub(crate) async fn members_loaded(&self, members: &[Arc<Member>], not_found: &[UserId]) -> Vec<DiscordMessage> {
+    let waiting = {
+      let mut awaiting = self.awaiting_members.lock().unwrap();
+
+      for user_id in not_found {
+        awaiting.remove(user_id);
+      }
+
+      members.iter().filter_map(|member| Some((member.clone(), awaiting.remove(&member.user.id)?))).collect::<Vec<_>>()
+    };
+
+    let lock = self.cache.lock().await;
+    let mut updated = vec![];
+
+    for (member, identifiers) in waiting {
+      for identifier in identifiers {
+        if let Some(DiscordMessage {
+          data: DiscordMessageData::Received(message, None),
+          ..
+        }) = lock.find(&identifier)
+        {
+          updated.push(DiscordMessage::from_serenity(
+            self.client.clone(),
+            message,
+            self.channel.clone(),
+            Some(member.clone()),
+          ));
+        }
+      }
+    }
+
+    updated
+  }
+
+  /// Fetches the page at the top of the channel into the cache, and returns the oldest message.
+  async fn fetch_top(&self, lock: &mut AsyncListCache<DiscordMessage>) -> Result<Option<AsyncListResult<DiscordMessage>>, ChatError> {
+    // NEWEST first, but starting from the oldest message in the channel
+    let v = self.client.get_messages(self.channel.id(), Some(OLDEST_MESSAGE), DISCORD_MESSAGE_BATCH_SIZE, Priority::Load).await?;
+
+    // a short batch means there was nothing further to fetch in that direction
+    let reached_bottom = v.len() < DISCORD_MESSAGE_BATCH_SIZE as usize;
+    let is_bottom = reached_bottom && v.len() == 1;
+
+    let page = self.load_page(v).await;
+    let result = page.first().cloned();
+
+    lock.insert_range(AsyncListIndex::RelativeToTop(0), page, true, reached_bottom);
+
+    Ok(result.map(|content| AsyncListResult {
+      content,
+      is_top: true,
+      is_bottom,
+    }))
+  }
+
+  /// Fetches the page below `message` into the cache, and returns the message right below it.
+  async fn fetch_after(
+    &self,
+    lock: &mut AsyncListCache<DiscordMessage>,
+    message: Snowflake,
+  ) -> Result<Option<AsyncListResult<DiscordMessage>>, ChatError> {
+    // NEWEST first
+    let v = self
+      .client
+      .get_messages(
+        self.channel.id(),
+        Some(MessagePagination::After(MessageId::new(message.0))),
+        DISCORD_MESSAGE_BATCH_SIZE,
+        Priority::Load,
+      )
+      .await?;
+
+    let reached_bottom = v.len() < DISCORD_MESSAGE_BATCH_SIZE as usize;
+    let is_bottom = reached_bottom && v.len() == 1;
+
+    let page = self.load_page(v).await;
+    let result = page.first().cloned();
+
+    lock.insert_range(AsyncListIndex::After(message), page, false, reached_bottom);
+
+    Ok(result.map(|content| AsyncListResult {
+      content,
+      is_top: false,
+      is_bottom,
+    }))
+  }
+
+  /// Fetches the page at the bottom of the channel into the cache, and returns the newest message.
+  async fn fetch_bottom(&self, lock: &mut AsyncListCache<DiscordMessage>) -> Result<Option<AsyncListResult<DiscordMessage>>, ChatError> {
+    // NEWEST first
+    let v = self.client.get_messages(self.channel.id(), None, DISCORD_MESSAGE_BATCH_SIZE, Priority::Load).await?;
+
+    let reached_top = v.len() < DISCORD_MESSAGE_BATCH_SIZE as usize;
+    let is_top = reached_top && v.len() == 1;
+
+    let page = self.load_page(v).await;
+    let result = page.last().cloned();
+
+    lock.insert_range(AsyncListIndex::RelativeToBottom(0), page, reached_top, true);
+
+    Ok(result.map(|content| AsyncListResult {
+      content,
+      is_top,
+      is_bottom: true,
+    }))
+  }
+
+  /// Fetches the page above `message` into the cache, and returns the message right above it.
+  async fn fetch_before(
+    &self,
+    lock: &mut AsyncListCache<DiscordMessage>,
+    message: Snowflake,
+  ) -> Result<Option<AsyncListResult<DiscordMessage>>, ChatError> {
+    // NEWEST first
+    let v = self
+      .client
+      .get_messages(
+        self.channel.id(),
+        Some(MessagePagination::Before(MessageId::new(message.0))),
+        DISCORD_MESSAGE_BATCH_SIZE,
+        Priority::Load,
+      )
+      .await?;
+    log::debug!("Discord gave us {:?} messages (out of {:?})", v.len(), DISCORD_MESSAGE_BATCH_SIZE);
+
+    let reached_top = v.len() < DISCORD_MESSAGE_BATCH_SIZE as usize;
+    let is_top = reached_top && v.len() == 1;
+
+    let page = self.load_page(v).await;
+    let result = page.last().cloned();
+
+    lock.insert_range(AsyncListIndex::Before(message), page, reached_top, false);
+
+    Ok(result.map(|content| AsyncListResult {
+      content,
+      is_top,
+      is_bottom: false,
+    }))
+  }
+
+  /// Pages in from the top of the channel, or the bottom, until the message `count` messages from that end is cached,
+  /// and returns it. Whatever is already cached on the way isn't fetched again.
+  async fn fetch_from_end(
+    &self,
+    lock: &mut AsyncListCache<DiscordMessage>,
+    count: usize,
+    from_top: bool,
+  ) -> Result<Option<AsyncListResult<DiscordMessage>>, ChatError> {
+    let end = if from_top {
+      AsyncListIndex::RelativeToTop(0)
+    } else {
+      AsyncListIndex::RelativeToBottom(0)
+    };
+
+    let mut current = match lock.get(end) {
+      Exists::Yes(end) => end,
+      Exists::No => return Ok(None),
+      Exists::Unknown => {
+        let fetched = if from_top {
+          self.fetch_top(lock).await?
+        } else {
+          self.fetch_bottom(lock).await?
+        };
+
+        match fetched {
+          Some(end) => end,
+          None => return Ok(None),
+        }
+      }
+    };
+
+    for _ in 0..count {
+      let message = current.content.get_list_identifier();
+      let next = if from_top {
+        AsyncListIndex::After(message)
+      } else {
+        AsyncListIndex::Before(message)
+      };
+
+      current = match lock.get(next) {
+        Exists::Yes(next) => next,
+        Exists::No => return Ok(None),
+        Exists::Unknown => {
+          let fetched = if from_top {
+            self.fetch_after(lock, message).await?
+          } else {
+            self.fetch_before(lock, message).await?
+          };
+
+          match fetched {
+            Some(next) => next,
+            None => return Ok(None),
+          }
+        }
+      };
+    }
+
+    Ok(Some(current))
+  }
+
+  pub(crate) async fn cached(&self, identifier: Snowflake) -> Option<DiscordMessage> {
+    self.cache.lock().await.find(&identifier)
+  }
+
+  /// Keeps the message cache in step with gateway events, before they are broadcast to listeners.
+  pub(crate) async fn apply_event(&self, event: &ChannelEvent<DiscordMessage>) {
+    let mut lock = self.cache.lock().await;
+
+    match event {
+      ChannelEvent::Created(message) => {
+        // until the bottom of the channel has been fetched, the cache can't know where this message belongs
+        if lock.bounded_at_bottom_by().is_some() && lock.find(&message.get_list_identifier()).is_none() {
+          lock.append_bottom(message.clone());
+        }
+      }
+      ChannelEvent::Updated(message) => {
+        lock.update(message.clone());
+      }
+      ChannelEvent::Deleted(identifier) => {
+        lock.remove(identifier);
+      }
+      ChannelEvent::BulkDeleted(identifiers) => {
+        for identifier in identifiers {
+          lock.remove(identifier);
+        }
+      }
+    }
+
+    drop(lock);
+
+    self.schedule_save();
   }
 }
 
@@ -46,20 +531,11 @@ impl Channel for DiscordChannel {
   type Message = DiscordMessage;
   type Identifier = Snowflake;
 
-  fn get_receiver(&self) -> broadcast::Receiver<Self::Message> {
+  fn get_receiver(&self) -> broadcast::Receiver<ChannelEvent<Self::Message>> {
     self.receiver.resubscribe()
   }
 
-  fn send_message(&self, content: String, nonce: String) -> DiscordMessage {
-    let client = self.client.clone();
-    let channel_id = self.channel.id();
-    let sent_content = content.clone();
-    let sent_nonce = nonce.clone();
-
-    tokio::spawn(async move {
-      client.send_message(channel_id, sent_content, sent_nonce).await;
-    });
-
+  fn pending_message(&self, content: String, nonce: String) -> DiscordMessage {
     DiscordMessage {
       channel: self.channel.clone(),
       client: self.client.clone(),
@@ -68,184 +544,145 @@ impl Channel for DiscordChannel {
         content,
         sent_time: Utc::now(),
         list_item_id: Snowflake::random(),
+        status: SendStatus::Pending,
       },
       content: OnceLock::new(),
     }
   }
 
+  fn send_message(&self, message: &DiscordMessage) -> impl Future<Output = Result<(), ChatError>> + Send {
+    let client = self.client.clone();
+    let channel_id = self.channel.id();
+    let pending = match &message.data {
+      DiscordMessageData::Pending { content, nonce, .. } => Some((content.clone(), nonce.clone())),
+      DiscordMessageData::Received(..) => None,
+    };
+
+    async move {
+      let Some((content, nonce)) = pending else {
+        return Err(ChatError::new(ChatErrorKind::Other, "This message has already been sent"));
+      };
+
+      client.send_message(channel_id, content, nonce).await
+    }
+  }
+
   fn get_identifier(&self) -> Self::Identifier {
     self.channel.id().into()
   }
+
+  // Discord only returns messages strictly after the anchor, so this is one less than anything sent at `time` can be,
+  // but never zero, which isn't a valid message ID
+  fn identifier_at(&self, time: DateTime<Utc>) -> Option<Snowflake> {
+    Some(Snowflake(Snowflake::from_timestamp(time).0.saturating_sub(1).max(1)))
+  }
 }
 
 const DISCORD_MESSAGE_BATCH_SIZE: u8 = 50;
+// paging after the smallest possible snowflake starts at the beginning of the channel
+const OLDEST_MESSAGE: MessagePagination = MessagePagination::After(MessageId::new(1));
 
 impl AsyncList for DiscordChannel {
-  async fn bounded_at_bottom_by(&self) -> Option<Snowflake> {
+  async fn bounded_at_bottom_by(&self) -> Result<Option<Snowflake>, ChatError> {
     let lock = self.cache.lock().await;
-    let cache_value = lock.bounded_at_top_by();
+    let cache_value = lock.bounded_at_bottom_by();
 
     if let Some(v) = cache_value {
-      return Some(v);
+      return Ok(Some(v));
     };
 
-    match &*self.channel {
-      serenity::model::channel::Channel::Guild(guild_channel) => guild_channel.messages(self.client.discord(), GetMessages::new().limit(1)).await,
-      serenity::model::channel::Channel::Private(private_channel) => {
-        private_channel.messages(self.client.discord(), GetMessages::new().limit(1)).await
-      }
-      _ => unimplemented!(),
-    }
-    .unwrap()
-    .first()
-    .map(|v| Snowflake(v.id.get()))
+    Ok(self.client.get_messages(self.channel.id(), None, 1, Priority::Prefetch).await?.first().map(|v| Snowflake(v.id.get())))
   }
 
-  async fn bounded_at_top_by(&self) -> Option<Snowflake> {
+  async fn bounded_at_top_by(&self) -> Result<Option<Snowflake>, ChatError> {
     let lock = self.cache.lock().await;
-    let cache_value = lock.bounded_at_bottom_by();
+    let cache_value = lock.bounded_at_top_by();
 
     if let Some(v) = cache_value {
-      return Some(v);
+      return Ok(Some(v));
     };
 
-    panic!("Unsupported")
+    Ok(self.client.get_messages(self.channel.id(), Some(OLDEST_MESSAGE), 1, Priority::Prefetch).await?.first().map(|v| Snowflake(v.id.get())))
   }
 
-  async fn find(&self, identifier: &Snowflake) -> Option<Self::Content> {
+  async fn find(&self, identifier: &Snowflake) -> Result<Option<Self::Content>, ChatError> {
     let lock = self.cache.lock().await;
     let cache_value = lock.find(identifier);
 
     drop(lock);
 
     if let Some(v) = cache_value {
-      return Some(v);
+      return Ok(Some(v));
     }
 
-    let result = self.client.get_specific_message(self.channel.id(), MessageId::new(identifier.0)).await?;
+    let Some(result) = self.client.get_specific_message(self.channel.id(), MessageId::new(identifier.0)).await? else {
+      return Ok(None);
+    };
 
-    Some(DiscordMessage::load_serenity(self.client.clone(), Arc::new(result)).await)
-  }
+    let message = self.load_message(result);
 
-  async fn get(&self, index: AsyncListIndex<Snowflake>) -> Option<AsyncListResult<Self::Content>> {
-    let permit = self.blocker.acquire().await;
+    self.request_members(std::slice::from_ref(&message)).await;
+
+    // cached on its own, so the list can be loaded outwards from it in both directions
     let mut lock = self.cache.lock().await;
-    let cache_value = lock.get(index);
 
-    if let Exists::Yes(v) = cache_value {
-      return Some(v);
-    } else if let Exists::No = cache_value {
-      return None;
+    if lock.find(identifier).is_none() {
+      lock.insert_detached(message.clone());
     }
 
-    let mut result: Option<DiscordMessage> = None;
-    let mut is_top = false;
-    let mut is_bottom = false;
-
-    match index {
-      AsyncListIndex::RelativeToTop(_) => todo!("Unsupported"),
-      AsyncListIndex::RelativeToBottom(index) => {
-        if index != 0 {
-          unimplemented!()
-        }
-
-        let v = self.client.get_messages(self.channel.id(), GetMessages::new().limit(DISCORD_MESSAGE_BATCH_SIZE)).await;
-
-        let is_end = v.len() == DISCORD_MESSAGE_BATCH_SIZE as usize;
-        is_bottom = true;
-        is_top = v.len() == 1;
-
-        let mut iter = v.into_iter();
-
-        let v = iter.next();
-
-        if let Some(v) = v {
-          let msg = DiscordMessage::load_serenity(self.client.clone(), Arc::new(v)).await;
-          let mut id = msg.get_list_identifier();
-          lock.append_bottom(msg.clone());
-          result = Some(msg);
-
-          for message in iter {
-            let msg = DiscordMessage::load_serenity(self.client.clone(), Arc::new(message)).await;
-            let nid = msg.get_list_identifier();
-
-            lock.insert(AsyncListIndex::Before(id), msg, false, is_end);
-
-            id = nid;
-          }
-        };
-      }
-      AsyncListIndex::After(message) => {
-        // NEWEST first
-        let v = self
-          .client
-          .get_messages(
-            self.channel.id(),
-            GetMessages::new().after(MessageId::new(message.0)).limit(DISCORD_MESSAGE_BATCH_SIZE),
-          )
-          .await;
-        let mut current_index: Snowflake = message;
-
-        let is_end = v.len() == DISCORD_MESSAGE_BATCH_SIZE as usize;
-        let len = v.len();
-        is_bottom = is_end && v.len() == 1;
-
-        for (message, index) in v.into_iter().rev().zip(0..) {
-          let id = Snowflake(message.id.get());
-
-          let value = DiscordMessage::load_serenity(self.client.clone(), Arc::new(message)).await;
-
-          if index == 0 {
-            result = Some(value.clone());
-          }
-
-          lock.insert(AsyncListIndex::After(current_index), value, false, is_end && index == (len - 1));
-
-          current_index = id;
-        }
-      }
-      AsyncListIndex::Before(message) => {
-        let v = self
-          .client
-          .get_messages(
-            self.channel.id(),
-            GetMessages::new().before(MessageId::new(message.0)).limit(DISCORD_MESSAGE_BATCH_SIZE),
-          )
-          .await;
-        let mut current_index: Snowflake = message;
+    Ok(Some(message))
+  }
 
-        println!("Discord gave us {:?} messages (out of {:?})", v.len(), DISCORD_MESSAGE_BATCH_SIZE);
+  async fn get_entry(&self, index: AsyncListIndex<Snowflake>) -> Result<Option<AsyncListResult<AsyncListEntry<Self::Content>>>, ChatError> {
+    // a gap is only reported once the messages on both sides are cached, filling it is left to `get`
+    if let Some(gap) = self.cache.lock().await.gap(index) {
+      return Ok(Some(AsyncListResult {
+        content: AsyncListEntry::Gap(gap),
+        is_top: false,
+        is_bottom: false,
+      }));
+    }
 
-        let is_end = v.len() == DISCORD_MESSAGE_BATCH_SIZE as usize;
-        let len = v.len();
-        is_top = is_end && v.len() == 1;
+    Ok(self.get(index).await?.map(|result| result.map(AsyncListEntry::Item)))
+  }
 
-        result = None;
+  async fn get(&self, index: AsyncListIndex<Snowflake>) -> Result<Option<AsyncListResult<Self::Content>>, ChatError> {
+    self.last_used.store(self.client.tick(), Ordering::SeqCst);
 
-        for (message, index) in v.into_iter().zip(0..) {
-          let id = Snowflake(message.id.get());
+    let permit = self.blocker.acquire().await;
+    let mut lock = self.cache.lock().await;
+    let cache_value = lock.get(index);
 
-          let value = DiscordMessage::load_serenity(self.client.clone(), Arc::new(message)).await;
+    if let Exists::Yes(v) = cache_value {
+      return Ok(Some(v));
+    } else if let Exists::No = cache_value {
+      return Ok(None);
+    }
 
-          if index == 0 {
-            result = Some(value.clone());
-          }
+    let result = match index {
+      AsyncListIndex::RelativeToTop(0) => self.fetch_top(&mut lock).await?,
+      AsyncListIndex::RelativeToTop(index) => self.fetch_from_end(&mut lock, index, true).await?,
+      AsyncListIndex::RelativeToBottom(0) => self.fetch_bottom(&mut lock).await?,
+      AsyncListIndex::RelativeToBottom(index) => self.fetch_from_end(&mut lock, index, false).await?,
+      AsyncListIndex::After(message) => self.fetch_after(&mut lock, message).await?,
+      AsyncListIndex::Before(message) => self.fetch_before(&mut lock, message).await?,
+    };
 
-          lock.insert(AsyncListIndex::Before(current_index), value, false, is_end && index == len);
+    if let Some(result) = &result {
+      lock.touch(&result.content.get_list_identifier());
+    }
 
-          current_index = id;
-        }
-      }
-    };
+    if let Some(max_items) = self.client.eviction_policy().max_items_per_list {
+      lock.evict(max_items);
+    }
 
     drop(permit);
     drop(lock);
 
-    result.map(|v| AsyncListResult {
-      content: v,
-      is_top,
-      is_bottom,
-    })
+    self.client.enforce_cache_budget(self.channel.id()).await;
+    self.schedule_save();
+
+    Ok(result)
   }
 
   type Content = DiscordMessage;
@@ -259,6 +696,9 @@ impl Clone for DiscordChannel {
       client: self.client.clone(),
       cache: self.cache.clone(),
       blocker: Semaphore::new(1),
+      save_scheduled: self.save_scheduled.clone(),
+      last_used: self.last_used.clone(),
+      awaiting_members: self.awaiting_members.clone(),
     }
   }
 }
@@ -0,0 +1,624 @@
+use std::{future::Future, sync::Arc};
+
+#[allow(unused_imports)]
+use scope_backend_cache::{async_list::eviction::EvictionPolicy, persistent::PersistentStore};
+#[allow(unused_imports)]
+use scope_chat::{
+  async_list::{AsyncList, AsyncListEntry, AsyncListGap, AsyncListIndex, AsyncListItem, AsyncListResult},
+  channel::{Channel, ChannelEvent},
+  error::{ChatError, ChatErrorKind},
+  message::Message,
+};
+#[allow(unused_imports)]
+use serenity::all::{ChannelId, GuildId, MessageId};
+
+#[allow(unused_imports)]
+use crate::{
+  channel::{DiscordChannel, DISCORD_MESSAGE_BATCH_SIZE},
+  client::DiscordClient,
+  message::{DiscordMessage, DiscordMessageData},
+  mock::MockDiscord,
+  snowflake::Snowflake,
+};
+
+#[allow(dead_code)]
+const CHANNEL_ID: u64 = 100;
+#[allow(dead_code)]
+const FIRST_MESSAGE_ID: u64 = 1000;
+// more than two batches, with a short one at the top
+#[allow(dead_code)]
+const MESSAGE_COUNT: u64 = 120;
+
+#[allow(dead_code)]
+fn block_on<F: Future>(future: F) -> F::Output {
+  tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(future)
+}
+
+#[allow(dead_code)]
+async fn seeded_channel() -> (Arc<MockDiscord>, Arc<DiscordChannel>) {
+  let mock = Arc::new(MockDiscord::new());
+  let channel_id = mock.add_direct_message_channel(CHANNEL_ID, MockDiscord::user(2, "friend"));
+
+  mock.seed_messages(channel_id, FIRST_MESSAGE_ID, MESSAGE_COUNT);
+
+  let client = DiscordClient::mock(mock.clone());
+  let channel = client.channel(Snowflake(CHANNEL_ID)).await.unwrap();
+
+  (mock, channel)
+}
+
+#[allow(dead_code)]
+fn identifiers(ids: impl Iterator<Item = u64>) -> Vec<Snowflake> {
+  ids.map(Snowflake).collect()
+}
+
+#[test]
+pub fn channel_starts_at_newest_message() {
+  block_on(async {
+    let (_, channel) = seeded_channel().await;
+
+    let result = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(result.content.get_identifier(), Some(Snowflake(FIRST_MESSAGE_ID + MESSAGE_COUNT - 1)));
+    assert!(result.is_bottom);
+    assert!(!result.is_top);
+  });
+}
+
+#[test]
+pub fn channel_pages_before_until_top() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+
+    let mut current = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+    let mut seen = vec![current.content.get_list_identifier()];
+
+    while let Some(result) = channel.get(AsyncListIndex::Before(current.content.get_list_identifier())).await.unwrap() {
+      seen.push(result.content.get_list_identifier());
+      current = result;
+    }
+
+    assert_eq!(seen, identifiers((FIRST_MESSAGE_ID..FIRST_MESSAGE_ID + MESSAGE_COUNT).rev()));
+    assert!(current.is_top);
+
+    // the whole channel is cached now, so going back down doesn't touch the API
+    let requests = mock.request_count();
+
+    while let Some(result) = channel.get(AsyncListIndex::After(current.content.get_list_identifier())).await.unwrap() {
+      current = result;
+    }
+
+    assert_eq!(current.content.get_identifier(), Some(Snowflake(FIRST_MESSAGE_ID + MESSAGE_COUNT - 1)));
+    assert!(current.is_bottom);
+    assert_eq!(mock.request_count(), requests);
+  });
+}
+
+#[test]
+pub fn channel_pages_after_until_bottom() {
+  block_on(async {
+    let (_, channel) = seeded_channel().await;
+
+    let mut current = Snowflake(FIRST_MESSAGE_ID);
+    let mut seen = vec![];
+    let mut last = None;
+
+    while let Some(result) = channel.get(AsyncListIndex::After(current)).await.unwrap() {
+      current = result.content.get_list_identifier();
+      seen.push(current);
+      last = Some(result);
+    }
+
+    assert_eq!(seen, identifiers(FIRST_MESSAGE_ID + 1..FIRST_MESSAGE_ID + MESSAGE_COUNT));
+    assert!(last.unwrap().is_bottom);
+  });
+}
+
+#[test]
+pub fn sent_message_matches_its_placeholder() {
+  block_on(async {
+    let (_, channel) = seeded_channel().await;
+
+    channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap();
+
+    let mut receiver = channel.get_receiver();
+    let pending = channel.pending_message("Hello".to_owned(), "test-nonce".to_owned());
+
+    channel.send_message(&pending).await.unwrap();
+
+    let ChannelEvent::Created(message) = receiver.recv().await.unwrap() else {
+      panic!("Expected the sent message to be echoed back");
+    };
+
+    assert!(message.get_nonce() == pending.get_nonce());
+    assert_eq!(message.get_identifier(), Some(Snowflake(FIRST_MESSAGE_ID + MESSAGE_COUNT)));
+
+    let bottom = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(bottom.content.get_identifier(), message.get_identifier());
+  });
+}
+
+#[test]
+pub fn deleted_message_leaves_cache() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+
+    channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap();
+
+    let mut receiver = channel.get_receiver();
+    let newest = FIRST_MESSAGE_ID + MESSAGE_COUNT - 1;
+
+    mock.delete_message(ChannelId::new(CHANNEL_ID), MessageId::new(newest));
+
+    let ChannelEvent::Deleted(identifier) = receiver.recv().await.unwrap() else {
+      panic!("Expected a delete event");
+    };
+
+    assert_eq!(identifier, Snowflake(newest));
+
+    let bottom = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(bottom.content.get_identifier(), Some(Snowflake(newest - 1)));
+  });
+}
+
+#[test]
+pub fn edited_message_is_updated_in_cache() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+
+    channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap();
+
+    let mut receiver = channel.get_receiver();
+    let edited = FIRST_MESSAGE_ID + MESSAGE_COUNT - 2;
+
+    mock.edit_message(ChannelId::new(CHANNEL_ID), MessageId::new(edited), "Edited");
+
+    let ChannelEvent::Updated(message) = receiver.recv().await.unwrap() else {
+      panic!("Expected an update event");
+    };
+
+    assert_eq!(message.get_identifier(), Some(Snowflake(edited)));
+
+    // the edit is applied to what was cached, without fetching the message again
+    let requests = mock.request_count();
+    let bottom = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+    let above = channel.get(AsyncListIndex::Before(bottom.content.get_list_identifier())).await.unwrap().unwrap();
+
+    let DiscordMessageData::Received(message, _) = &above.content.data else {
+      panic!("Expected a received message");
+    };
+
+    assert_eq!(message.id, MessageId::new(edited));
+    assert_eq!(message.content, "Edited");
+    assert_eq!(mock.request_count(), requests);
+  });
+}
+
+#[test]
+pub fn bulk_deleted_messages_leave_cache() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+
+    channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap();
+
+    let mut receiver = channel.get_receiver();
+    let newest = FIRST_MESSAGE_ID + MESSAGE_COUNT - 1;
+
+    mock.delete_messages(ChannelId::new(CHANNEL_ID), vec![MessageId::new(newest), MessageId::new(newest - 2)]);
+
+    let ChannelEvent::BulkDeleted(deleted) = receiver.recv().await.unwrap() else {
+      panic!("Expected a bulk delete event");
+    };
+
+    assert_eq!(deleted, identifiers([newest, newest - 2].into_iter()));
+
+    let requests = mock.request_count();
+    let bottom = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+    let above = channel.get(AsyncListIndex::Before(bottom.content.get_list_identifier())).await.unwrap().unwrap();
+
+    assert_eq!(bottom.content.get_identifier(), Some(Snowflake(newest - 1)));
+    assert_eq!(above.content.get_identifier(), Some(Snowflake(newest - 3)));
+    assert_eq!(mock.request_count(), requests);
+  });
+}
+
+#[test]
+pub fn channel_reports_request_failures() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+
+    mock.fail_requests(Some(ChatError::new(ChatErrorKind::Network, "Connection reset")));
+
+    let error = channel.get(AsyncListIndex::RelativeToBottom(0)).await.err().unwrap();
+
+    assert_eq!(error.kind(), ChatErrorKind::Network);
+
+    mock.fail_requests(None);
+
+    assert!(channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().is_some());
+  });
+}
+
+#[test]
+pub fn channel_pages_from_top_into_loaded_bottom() {
+  block_on(async {
+    let (_, channel) = seeded_channel().await;
+
+    channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap();
+
+    let mut current = channel.get(AsyncListIndex::RelativeToTop(0)).await.unwrap().unwrap();
+
+    assert!(current.is_top);
+    assert_eq!(channel.bounded_at_top_by().await.unwrap(), Some(Snowflake(FIRST_MESSAGE_ID)));
+
+    let mut seen = vec![current.content.get_list_identifier()];
+
+    while let Some(result) = channel.get(AsyncListIndex::After(current.content.get_list_identifier())).await.unwrap() {
+      seen.push(result.content.get_list_identifier());
+      current = result;
+    }
+
+    assert_eq!(seen, identifiers(FIRST_MESSAGE_ID..FIRST_MESSAGE_ID + MESSAGE_COUNT));
+    assert!(current.is_bottom);
+  });
+}
+
+#[test]
+pub fn channel_pages_down_from_top_to_relative_index() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+
+    // further down than one page reaches
+    let index = DISCORD_MESSAGE_BATCH_SIZE as usize + 10;
+    let result = channel.get(AsyncListIndex::RelativeToTop(index)).await.unwrap().unwrap();
+
+    assert_eq!(result.content.get_identifier(), Some(Snowflake(FIRST_MESSAGE_ID + index as u64)));
+    assert!(!result.is_top);
+    assert!(!result.is_bottom);
+
+    // everything above it was cached on the way
+    let requests = mock.request_count();
+    let above = channel.get(AsyncListIndex::RelativeToTop(index - 1)).await.unwrap().unwrap();
+
+    assert_eq!(above.content.get_identifier(), Some(Snowflake(FIRST_MESSAGE_ID + index as u64 - 1)));
+    assert_eq!(mock.request_count(), requests);
+
+    assert!(channel.get(AsyncListIndex::RelativeToTop(MESSAGE_COUNT as usize)).await.unwrap().is_none());
+  });
+}
+
+#[test]
+pub fn channel_pages_up_from_bottom_to_relative_index() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+
+    let index = DISCORD_MESSAGE_BATCH_SIZE as usize + 10;
+    let newest = FIRST_MESSAGE_ID + MESSAGE_COUNT - 1;
+    let result = channel.get(AsyncListIndex::RelativeToBottom(index)).await.unwrap().unwrap();
+
+    assert_eq!(result.content.get_identifier(), Some(Snowflake(newest - index as u64)));
+    assert!(!result.is_top);
+    assert!(!result.is_bottom);
+
+    let requests = mock.request_count();
+    let below = channel.get(AsyncListIndex::RelativeToBottom(index - 1)).await.unwrap().unwrap();
+
+    assert_eq!(below.content.get_identifier(), Some(Snowflake(newest - index as u64 + 1)));
+    assert_eq!(mock.request_count(), requests);
+
+    assert!(channel.get(AsyncListIndex::RelativeToBottom(MESSAGE_COUNT as usize)).await.unwrap().is_none());
+  });
+}
+
+#[test]
+pub fn channel_loads_outwards_from_found_message() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+
+    channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap();
+
+    let anchor = Snowflake(FIRST_MESSAGE_ID + 10);
+    let found = channel.find(&anchor).await.unwrap().unwrap();
+
+    assert_eq!(found.get_identifier(), Some(anchor));
+
+    let mut current = anchor;
+    let mut above = vec![];
+
+    while let Some(result) = channel.get(AsyncListIndex::Before(current)).await.unwrap() {
+      current = result.content.get_list_identifier();
+      above.push(current);
+    }
+
+    assert_eq!(above, identifiers((FIRST_MESSAGE_ID..anchor.0).rev()));
+
+    let mut current = anchor;
+    let mut below = vec![];
+    let mut last = None;
+
+    while let Some(result) = channel.get(AsyncListIndex::After(current)).await.unwrap() {
+      current = result.content.get_list_identifier();
+      below.push(current);
+      last = Some(result);
+    }
+
+    assert_eq!(below, identifiers(anchor.0 + 1..FIRST_MESSAGE_ID + MESSAGE_COUNT));
+    assert!(last.unwrap().is_bottom);
+
+    // everything joined into one segment, so nothing needs fetching again
+    let requests = mock.request_count();
+
+    assert!(channel.get(AsyncListIndex::After(Snowflake(FIRST_MESSAGE_ID))).await.unwrap().is_some());
+    assert_eq!(mock.request_count(), requests);
+  });
+}
+
+#[test]
+pub fn reopened_channel_only_fetches_new_messages() {
+  block_on(async {
+    let store = Arc::new(PersistentStore::in_memory().unwrap());
+    let (mock, channel) = seeded_channel().await;
+
+    channel.client.set_store(store.clone());
+    channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap();
+    channel.save().await.unwrap();
+
+    // sent while the channel was closed
+    let newest = FIRST_MESSAGE_ID + MESSAGE_COUNT + 2;
+
+    mock.seed_messages(ChannelId::new(CHANNEL_ID), FIRST_MESSAGE_ID + MESSAGE_COUNT, 3);
+
+    let client = DiscordClient::mock(mock.clone());
+
+    client.set_store(store);
+
+    let channel = client.channel(Snowflake(CHANNEL_ID)).await.unwrap();
+    let mut receiver = channel.get_receiver();
+
+    // the saved bottom is there straight away, without waiting for Discord
+    let requests = mock.request_count();
+    let saved = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(saved.content.get_identifier(), Some(Snowflake(FIRST_MESSAGE_ID + MESSAGE_COUNT - 1)));
+    assert_eq!(mock.request_count(), requests);
+
+    // and what was sent since arrives after it
+    for id in newest - 2..=newest {
+      let ChannelEvent::Created(message) = receiver.recv().await.unwrap() else {
+        panic!("Expected the new messages to be created");
+      };
+
+      assert_eq!(message.get_identifier(), Some(Snowflake(id)));
+    }
+
+    let mut current = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(current.content.get_identifier(), Some(Snowflake(newest)));
+    assert!(current.is_bottom);
+
+    // the new messages join up with the saved ones, which don't have to be fetched again
+    let requests = mock.request_count();
+    let mut seen = vec![current.content.get_list_identifier()];
+
+    for _ in 0..DISCORD_MESSAGE_BATCH_SIZE + 2 {
+      current = channel.get(AsyncListIndex::Before(current.content.get_list_identifier())).await.unwrap().unwrap();
+      seen.push(current.content.get_list_identifier());
+    }
+
+    assert_eq!(seen, identifiers((newest - DISCORD_MESSAGE_BATCH_SIZE as u64 - 2..=newest).rev()));
+    assert_eq!(mock.request_count(), requests);
+  });
+}
+
+#[test]
+pub fn cache_budget_evicts_from_least_recently_used_channel() {
+  block_on(async {
+    let mock = Arc::new(MockDiscord::new());
+    let first = mock.add_direct_message_channel(CHANNEL_ID, MockDiscord::user(2, "friend"));
+    let second = mock.add_direct_message_channel(CHANNEL_ID + 1, MockDiscord::user(3, "other friend"));
+
+    mock.seed_messages(first, FIRST_MESSAGE_ID, MESSAGE_COUNT);
+    mock.seed_messages(second, FIRST_MESSAGE_ID + MESSAGE_COUNT, MESSAGE_COUNT);
+
+    let client = DiscordClient::mock(mock.clone());
+    let budget = DISCORD_MESSAGE_BATCH_SIZE as usize + 10;
+
+    client.set_eviction_policy(EvictionPolicy {
+      max_items_per_list: None,
+      max_items: Some(budget),
+    });
+
+    let first = client.channel(Snowflake(CHANNEL_ID)).await.unwrap();
+    let second = client.channel(Snowflake(CHANNEL_ID + 1)).await.unwrap();
+
+    first.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap();
+    second.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap();
+
+    // the channel that was just loaded keeps everything, the other one makes room for it
+    assert_eq!(second.cached_len().await, DISCORD_MESSAGE_BATCH_SIZE as usize);
+    assert_eq!(first.cached_len().await, 10);
+    assert_eq!(client.cache_metrics().await.evictions, DISCORD_MESSAGE_BATCH_SIZE as u64 - 10);
+
+    // what's left of the first channel is still its newest messages
+    let bottom = first.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(bottom.content.get_identifier(), Some(Snowflake(FIRST_MESSAGE_ID + MESSAGE_COUNT - 1)));
+  });
+}
+
+#[test]
+pub fn gap_between_cached_segments_is_filled_by_paging() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+
+    let bottom = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+    let oldest_loaded = Snowflake(bottom.content.get_list_identifier().0 + 1 - DISCORD_MESSAGE_BATCH_SIZE as u64);
+    let found = Snowflake(FIRST_MESSAGE_ID + 10);
+
+    channel.find(&found).await.unwrap().unwrap();
+
+    // both sides are cached, so the gap is reported without asking Discord for anything
+    let requests = mock.request_count();
+    let entry = channel.get_entry(AsyncListIndex::Before(oldest_loaded)).await.unwrap().unwrap();
+
+    let AsyncListEntry::Gap(gap) = entry.content else {
+      panic!("Expected a gap above the newest messages");
+    };
+
+    assert_eq!(
+      gap,
+      AsyncListGap {
+        above: found,
+        below: oldest_loaded
+      }
+    );
+    assert_eq!(mock.request_count(), requests);
+
+    let mut current = found;
+
+    while current != oldest_loaded {
+      current = channel.get(AsyncListIndex::After(current)).await.unwrap().unwrap().content.get_list_identifier();
+    }
+
+    let AsyncListEntry::Item(above) = channel.get_entry(AsyncListIndex::Before(oldest_loaded)).await.unwrap().unwrap().content else {
+      panic!("Expected the gap to be filled");
+    };
+
+    assert_eq!(above.get_identifier(), Some(Snowflake(oldest_loaded.0 - 1)));
+  });
+}
+
+#[test]
+pub fn jump_to_date_starts_at_first_message_sent_then() {
+  block_on(async {
+    let mock = Arc::new(MockDiscord::new());
+    let channel_id = mock.add_direct_message_channel(CHANNEL_ID, MockDiscord::user(2, "friend"));
+
+    let day = chrono::Duration::days(1);
+    let first_day = "2024-06-01T00:00:00Z".parse::<chrono::DateTime<chrono::Utc>>().unwrap();
+    let first_day_start = Snowflake::from_timestamp(first_day + chrono::Duration::hours(12));
+    let second_day_start = Snowflake::from_timestamp(first_day + day + chrono::Duration::hours(12));
+
+    mock.seed_messages(channel_id, first_day_start.0, 10);
+    mock.seed_messages(channel_id, second_day_start.0, 10);
+
+    let client = DiscordClient::mock(mock.clone());
+    let channel = client.channel(Snowflake(CHANNEL_ID)).await.unwrap();
+
+    let anchor = channel.identifier_at(first_day + day).unwrap();
+    let result = channel.get(AsyncListIndex::After(anchor)).await.unwrap().unwrap();
+
+    assert_eq!(result.content.get_identifier(), Some(second_day_start));
+
+    // the list loads upwards from there like from any other message
+    let above = channel.get(AsyncListIndex::Before(second_day_start)).await.unwrap().unwrap();
+
+    assert_eq!(above.content.get_identifier(), Some(Snowflake(first_day_start.0 + 9)));
+
+    // a message sent at exactly that time is included
+    let anchor = channel.identifier_at(second_day_start.timestamp()).unwrap();
+
+    assert_eq!(
+      channel.get(AsyncListIndex::After(anchor)).await.unwrap().unwrap().content.get_identifier(),
+      Some(second_day_start)
+    );
+
+    let anchor = channel.identifier_at(first_day + day * 2).unwrap();
+
+    assert!(channel.get(AsyncListIndex::After(anchor)).await.unwrap().is_none());
+  });
+}
+
+#[test]
+pub fn paging_a_guild_channel_requests_its_members_at_once() {
+  block_on(async {
+    const GUILD_ID: u64 = 10;
+
+    let mock = Arc::new(MockDiscord::new());
+    let member = MockDiscord::user(2, "member");
+    let former_member = MockDiscord::user(3, "former member");
+
+    mock.add_member(GUILD_ID, member.clone(), "nickname");
+
+    for channel_id in [CHANNEL_ID, CHANNEL_ID + 1] {
+      let channel_id = mock.add_guild_channel(channel_id, GUILD_ID);
+
+      mock.seed_messages_from(channel_id, FIRST_MESSAGE_ID, 6, &[member.clone(), former_member.clone()]);
+    }
+
+    let client = DiscordClient::mock(mock.clone());
+    let channel = client.clone().channel(Snowflake(CHANNEL_ID)).await.unwrap();
+    let mut receiver = channel.get_receiver();
+
+    let requests = mock.request_count();
+
+    channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    // only the page itself goes through the API, the authors are asked for over the gateway
+    assert_eq!(mock.request_count(), requests + 1);
+    assert_eq!(mock.member_request_count(), 1);
+
+    // the member's messages are updated once they've arrived
+    for _ in 0..3 {
+      let ChannelEvent::Updated(message) = receiver.recv().await.unwrap() else {
+        panic!("Expected messages to be updated with their author's member");
+      };
+
+      let DiscordMessageData::Received(_, Some(author)) = &message.data else {
+        panic!("Expected the message to have its author's member");
+      };
+
+      assert_eq!(author.nick.as_deref(), Some("nickname"));
+    }
+
+    // both authors are known about now, whether or not they're members
+    let other = client.clone().channel(Snowflake(CHANNEL_ID + 1)).await.unwrap();
+    let bottom = other.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(mock.member_request_count(), 1);
+    assert!(client.members().get(GuildId::new(GUILD_ID), member.id).is_some());
+    assert!(matches!(bottom.content.data, DiscordMessageData::Received(_, None)));
+
+    let above = other.get(AsyncListIndex::Before(bottom.content.get_list_identifier())).await.unwrap().unwrap();
+
+    assert!(matches!(above.content.data, DiscordMessageData::Received(_, Some(_))));
+  });
+}
+
+#[test]
+pub fn resumed_connection_fetches_missed_messages() {
+  block_on(async {
+    let (mock, channel) = seeded_channel().await;
+    let mut receiver = channel.get_receiver();
+
+    let bottom = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(bottom.content.get_identifier(), Some(Snowflake(FIRST_MESSAGE_ID + MESSAGE_COUNT - 1)));
+
+    // sent while the gateway was disconnected, so no events were received for them
+    let missed = mock.seed_messages(ChannelId::new(CHANNEL_ID), FIRST_MESSAGE_ID + MESSAGE_COUNT, 3);
+
+    mock.resume();
+
+    for id in &missed {
+      let ChannelEvent::Created(message) = receiver.recv().await.unwrap() else {
+        panic!("Expected the missed messages to be created");
+      };
+
+      assert_eq!(message.get_identifier(), Some(Snowflake::from(*id)));
+    }
+
+    // they were cached where they belong
+    let requests = mock.request_count();
+    let newest = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(newest.content.get_identifier(), Some(Snowflake::from(missed[2])));
+    assert_eq!(
+      channel.get(AsyncListIndex::After(bottom.content.get_list_identifier())).await.unwrap().unwrap().content.get_identifier(),
+      Some(Snowflake::from(missed[0]))
+    );
+    assert_eq!(mock.request_count(), requests);
+  });
+}
@@ -1,27 +1,65 @@
 use std::{
   collections::HashMap,
-  sync::{Arc, OnceLock, Weak},
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, OnceLock, Weak,
+  },
+  time::Instant,
 };
 
 use atomic_refcell::AtomicRefCell;
-use dashmap::DashMap;
+use scope_backend_cache::{
+  async_list::eviction::{CacheMetrics, EvictionPolicy},
+  persistent::PersistentStore,
+};
+use scope_chat::{
+  channel::ChannelEvent,
+  client::Client,
+  error::{ChatError, ChatErrorKind},
+  guild::{ChannelKind, ChannelListing},
+};
 use serenity::{
   all::{
-    Cache, CacheHttp, ChannelId, Context, CreateMessage, EventHandler, GatewayIntents, GetMessages, GuildId, Http, Member, Message, MessageId,
-    ModelError, Ready, User,
+    Cache, CacheHttp, Channel, ChannelId, ConnectionStage, Context, EventHandler, GatewayIntents, Guild, GuildId, GuildMembersChunkEvent, Http,
+    Member, Message, MessageId, MessagePagination, MessageUpdateEvent, PartialGuild, RatelimitInfo, Ready, ResumedEvent, ShardManager,
+    ShardStageUpdateEvent, UnavailableGuild, User, UserId,
   },
   async_trait,
 };
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, watch, RwLock};
 
-use crate::{channel::DiscordChannel, message::DiscordMessage, snowflake::Snowflake};
+use crate::{
+  api::{CurrentConnection, DiscordApi},
+  channel::DiscordChannel,
+  config::{allowed_intents, DiscordConfig},
+  connection::{Connection, ConnectionState},
+  error::chat_error,
+  guild::DiscordGuild,
+  member::MemberCache,
+  message::{DiscordMessage, DiscordMessageData},
+  scheduler::{Coalescer, Priority, RateLimit, RequestScheduler},
+  snowflake::Snowflake,
+};
 
 #[allow(dead_code)]
+#[derive(Clone)]
 pub struct SerenityClient {
   // enable this when we enable the serenity[voice] feature
   // voice_manager: Option<Arc<dyn VoiceGatewayManager>>
-  http: Arc<Http>,
-  cache: Arc<Cache>,
+  pub(crate) http: Arc<Http>,
+  pub(crate) cache: Arc<Cache>,
+  pub(crate) shard_manager: Arc<ShardManager>,
+}
+
+impl From<&serenity::Client> for SerenityClient {
+  fn from(discord: &serenity::Client) -> Self {
+    SerenityClient {
+      // voice_manager: discord.voice_manager.clone(),
+      cache: discord.cache.clone(),
+      http: discord.http.clone(),
+      shard_manager: discord.shard_manager.clone(),
+    }
+  }
 }
 
 impl CacheHttp for SerenityClient {
@@ -36,47 +74,236 @@ impl CacheHttp for SerenityClient {
 
 #[derive(Default)]
 pub struct DiscordClient {
-  channel_message_event_handlers: RwLock<HashMap<ChannelId, Vec<broadcast::Sender<DiscordMessage>>>>,
-  client: OnceLock<SerenityClient>,
+  channel_message_event_handlers: RwLock<HashMap<ChannelId, Vec<broadcast::Sender<ChannelEvent<DiscordMessage>>>>>,
+  gateway: Arc<CurrentConnection>,
+  // the connection being made, which replaces `gateway` once it's ready, so the cache isn't emptied in the meantime
+  pending_gateway: AtomicRefCell<Option<SerenityClient>>,
+  api: OnceLock<Arc<dyn DiscordApi>>,
+  store: OnceLock<Arc<PersistentStore>>,
+  eviction_policy: Mutex<EvictionPolicy>,
+  clock: AtomicU64,
   user: OnceLock<Arc<User>>,
   channels: RwLock<HashMap<ChannelId, Arc<DiscordChannel>>>,
-  member: DashMap<GuildId, Arc<Member>>,
-  ready_notifier: AtomicRefCell<Option<catty::Sender<()>>>,
+  // held while a channel is being opened, rather than `channels`, which events for the open ones need
+  opening: tokio::sync::Mutex<()>,
+  members: MemberCache,
+  scheduler: RequestScheduler,
+  // pages are told apart by how they were asked for, since `MessagePagination` can't be hashed
+  pages: Coalescer<(ChannelId, String, u8), Vec<Message>>,
+  connection: Connection,
+  guild_changes: watch::Sender<Vec<DiscordGuild>>,
+  // what the client was configured for
+  requested_config: OnceLock<DiscordConfig>,
+  // that, less what Discord didn't allow
+  config: OnceLock<DiscordConfig>,
+  ready_notifier: AtomicRefCell<Option<catty::Sender<Result<(), ChatError>>>>,
   weak: Weak<DiscordClient>,
 }
 
 impl DiscordClient {
-  pub async fn new(token: String) -> Arc<DiscordClient> {
-    let (sender, receiver) = catty::oneshot::<()>();
-
+  /// Connects to Discord with the intents `config` asks for, less the privileged ones the application hasn't been
+  /// allowed.
+  pub async fn new(token: String, config: DiscordConfig) -> Result<Arc<DiscordClient>, ChatError> {
     let client = Arc::new_cyclic(|weak| DiscordClient {
-      ready_notifier: AtomicRefCell::new(Some(sender)),
       weak: weak.clone(),
 
       ..Default::default()
     });
 
-    let mut discord = serenity::Client::builder(token, GatewayIntents::all()).event_handler_arc(client.clone()).await.expect("Error creating client");
+    let _ = client.requested_config.set(config);
 
-    let _ = client.client.set(SerenityClient {
-      // voice_manager: discord.voice_manager.clone(),
-      cache: discord.cache.clone(),
-      http: discord.http.clone(),
-    });
+    // the application says which privileged intents it has, rather than each one Discord refuses costing a connection
+    let intents = match Http::new(&token).get_current_application_info().await {
+      Ok(application) => allowed_intents(config.intents(), application.flags.unwrap_or_default()),
+      Err(e) => {
+        log::warn!("Failed to check which intents are allowed: {}", e);
+
+        config.intents()
+      }
+    };
+
+    if intents != config.intents() {
+      log::warn!("The application isn't allowed {:?}, connecting without them", config.intents() - intents);
+    }
+
+    match client.clone().connect(&token, intents).await {
+      Ok(()) => {}
+      // the flags were out of date, or couldn't be checked, so it's tried once more without any privileged intents
+      Err(e) if e.kind() == ChatErrorKind::Forbidden && intents.intersects(GatewayIntents::privileged()) => {
+        log::warn!("Discord refused {:?}, trying without privileged intents", intents);
+
+        client.clone().connect(&token, intents - GatewayIntents::privileged()).await?;
+      }
+      Err(e) => return Err(e),
+    }
+
+    Ok(client)
+  }
+
+  /// Starts the gateway connection with `intents`, returning once it's ready.
+  async fn connect(self: Arc<Self>, token: &str, intents: GatewayIntents) -> Result<(), ChatError> {
+    let (sender, receiver) = catty::oneshot::<Result<(), ChatError>>();
+
+    *self.ready_notifier.borrow_mut() = Some(sender);
+
+    let mut discord = self.clone().build_gateway(token, intents).await?;
+
+    *self.pending_gateway.borrow_mut() = Some(SerenityClient::from(&discord));
+
+    let gateway_client = self.clone();
+    let token = token.to_owned();
 
     tokio::spawn(async move {
-      if let Err(why) = discord.start().await {
-        panic!("Client error: {why:?}");
+      loop {
+        gateway_client.connection.connecting();
+
+        // the shards reconnect and resume on their own, this only returns once they've given up
+        let Err(why) = discord.start().await else {
+          gateway_client.connection.lost();
+
+          return;
+        };
+
+        log::error!("Discord client error: {why:?}");
+
+        let mut error = chat_error(why);
+
+        // if we never became ready, whoever is waiting on `connect` needs to hear about it
+        if let Some(ready_notifier) = gateway_client.ready_notifier.borrow_mut().take() {
+          gateway_client.connection.failed(error.clone());
+
+          let _ = ready_notifier.send(Err(error));
+
+          return;
+        }
+
+        // a client that has stopped can't be started again, so the connection is made from scratch with a new one
+        loop {
+          let Some(delay) = gateway_client.connection.failed(error) else {
+            return;
+          };
+
+          tokio::time::sleep(delay).await;
+
+          // with the intents Discord accepted, which may be fewer than the ones first tried
+          match gateway_client.clone().build_gateway(&token, gateway_client.config().intents()).await {
+            Ok(new) => {
+              discord = new;
+              *gateway_client.pending_gateway.borrow_mut() = Some(SerenityClient::from(&discord));
+
+              break;
+            }
+            Err(e) => error = e,
+          }
+        }
       }
     });
 
-    receiver.await.expect("The ready notifier was dropped");
+    match receiver.await {
+      Ok(result) => result?,
+      Err(_) => return Err(ChatError::new(ChatErrorKind::Other, "The ready notifier was dropped")),
+    }
+
+    // the connection that became ready is the one kept, along with its cache and the features its intents allow
+    let _ = self.api.set(self.gateway.clone());
+    let _ = self.config.set(self.requested_config().limit_to(intents));
+
+    Ok(())
+  }
+
+  /// A new serenity client, whose gateway events are handled by this one. It has its own cache and shards.
+  async fn build_gateway(self: Arc<Self>, token: &str, intents: GatewayIntents) -> Result<serenity::Client, ChatError> {
+    serenity::Client::builder(token, intents).event_handler_arc(self).await.map_err(chat_error)
+  }
+
+  /// A client that makes its requests through `api`, without connecting to the gateway.
+  pub(crate) fn with_api(api: Arc<dyn DiscordApi>, user: User) -> Arc<DiscordClient> {
+    let client = Arc::new_cyclic(|weak| DiscordClient {
+      weak: weak.clone(),
+
+      ..Default::default()
+    });
+
+    let _ = client.api.set(api);
+    let _ = client.user.set(Arc::new(user));
+
+    client.connection.ready();
 
     client
   }
 
-  pub fn discord(&self) -> &SerenityClient {
-    self.client.get().unwrap()
+  /// The gateway side of the client: serenity's cache. `None` when the client isn't connected to Discord.
+  pub fn discord(&self) -> Option<SerenityClient> {
+    self.gateway.get()
+  }
+
+  pub fn api(&self) -> &dyn DiscordApi {
+    self.api.get().unwrap().as_ref()
+  }
+
+  /// Keeps the messages of channels opened from now on in `store`, so they don't have to be fetched again next time.
+  pub fn set_store(&self, store: Arc<PersistentStore>) {
+    let _ = self.store.set(store);
+  }
+
+  pub fn store(&self) -> Option<&Arc<PersistentStore>> {
+    self.store.get()
+  }
+
+  pub fn set_eviction_policy(&self, policy: EvictionPolicy) {
+    *self.eviction_policy.lock().unwrap() = policy;
+  }
+
+  pub fn eviction_policy(&self) -> EvictionPolicy {
+    *self.eviction_policy.lock().unwrap()
+  }
+
+  pub(crate) fn tick(&self) -> u64 {
+    self.clock.fetch_add(1, Ordering::SeqCst) + 1
+  }
+
+  /// Evicts messages from the least recently used channels until all of them together fit in the policy's budget.
+  /// `in_use` is the channel that was just loaded from, and is evicted from last.
+  pub(crate) async fn enforce_cache_budget(&self, in_use: ChannelId) {
+    let Some(max_items) = self.eviction_policy().max_items else {
+      return;
+    };
+
+    let mut channels = self.channels.read().await.iter().map(|(id, channel)| (*id, channel.clone())).collect::<Vec<_>>();
+    let mut total = 0;
+
+    for (_, channel) in &channels {
+      total += channel.cached_len().await;
+    }
+
+    channels.sort_by_key(|(id, channel)| (*id == in_use, channel.last_used()));
+
+    for (_, channel) in channels {
+      if total <= max_items {
+        break;
+      }
+
+      let len = channel.cached_len().await;
+
+      total -= channel.evict(len.saturating_sub(total - max_items)).await;
+    }
+  }
+
+  /// Cache usage across every channel that has been opened.
+  pub async fn cache_metrics(&self) -> CacheMetrics {
+    let channels = self.channels.read().await.values().cloned().collect::<Vec<_>>();
+    let mut metrics = CacheMetrics::default();
+
+    for channel in channels {
+      metrics = metrics + channel.cache_metrics().await;
+    }
+
+    metrics
+  }
+
+  /// Where a channel's messages are kept in the store. What a user can see differs between accounts, so each has their own.
+  pub(crate) fn store_key(&self, channel_id: ChannelId) -> String {
+    format!("discord/{}/{}", self.own_user().id, channel_id)
   }
 
   pub fn own_user(&self) -> Arc<User> {
@@ -84,51 +311,311 @@ impl DiscordClient {
   }
 
   pub fn own_member(&self, guild: GuildId) -> Option<Arc<Member>> {
-    self.member.get(&guild).map(|v| v.clone())
+    self.members.get(guild, self.own_user().id)
+  }
+
+  pub fn members(&self) -> &MemberCache {
+    &self.members
+  }
+
+  /// Caches members the gateway sent in answer to `DiscordApi::request_guild_members`, and updates the messages that
+  /// were waiting on them.
+  pub(crate) async fn members_chunk(&self, guild_id: GuildId, members: Vec<Member>, not_found: Vec<UserId>) {
+    let members = members.into_iter().map(|member| self.members.insert(guild_id, member)).collect::<Vec<_>>();
+
+    self.members.insert_absent(guild_id, &not_found);
+
+    let channels = self.channels.read().await.values().filter(|channel| channel.guild_id() == Some(guild_id)).cloned().collect::<Vec<_>>();
+
+    for channel in channels {
+      for message in channel.members_loaded(&members, &not_found).await {
+        self.dispatch_channel_event(channel.channel_id(), ChannelEvent::Updated(message)).await;
+      }
+    }
   }
 
-  pub async fn add_channel_message_sender(&self, channel: ChannelId, sender: broadcast::Sender<DiscordMessage>) {
+  pub async fn add_channel_message_sender(&self, channel: ChannelId, sender: broadcast::Sender<ChannelEvent<DiscordMessage>>) {
     self.channel_message_event_handlers.write().await.entry(channel).or_default().push(sender);
   }
 
-  pub async fn channel(self: Arc<Self>, channel_id: Snowflake) -> Arc<DiscordChannel> {
+  pub(crate) async fn dispatch_channel_event(&self, channel_id: ChannelId, event: ChannelEvent<DiscordMessage>) {
+    let channel = self.channels.read().await.get(&channel_id).cloned();
+
+    if let Some(channel) = channel {
+      channel.apply_event(&event).await;
+    }
+
+    if let Some(vec) = self.channel_message_event_handlers.read().await.get(&channel_id) {
+      for sender in vec {
+        let _ = sender.send(event.clone());
+      }
+    }
+  }
+
+  pub(crate) async fn message_created(&self, msg: Message) {
+    // nobody is listening to channels that haven't been opened
+    let Some(channel) = self.channels.read().await.get(&msg.channel_id).cloned() else {
+      return;
+    };
+
+    let message = channel.load_message(msg);
+
+    channel.request_members(std::slice::from_ref(&message)).await;
+
+    self.dispatch_channel_event(channel.channel_id(), ChannelEvent::Created(message)).await;
+  }
+
+  pub(crate) async fn message_updated(&self, new: Option<Message>, event: MessageUpdateEvent) {
+    let channel = self.channels.read().await.get(&event.channel_id).cloned();
+
+    let Some(channel) = channel else {
+      return;
+    };
+
+    let message = match channel.cached(event.id.into()).await {
+      Some(DiscordMessage {
+        data: DiscordMessageData::Received(old, member),
+        channel: serenity_channel,
+        ..
+      }) => {
+        let message = new.unwrap_or_else(|| {
+          let mut message = (*old).clone();
+          event.apply_to_message(&mut message);
+          message
+        });
+
+        DiscordMessage::from_serenity(self.weak.upgrade().unwrap(), Arc::new(message), serenity_channel, member)
+      }
+      _ => match new {
+        Some(message) => channel.load_message(message),
+        // nobody has seen the original message, so there's nothing to update
+        None => return,
+      },
+    };
+
+    self.dispatch_channel_event(event.channel_id, ChannelEvent::Updated(message)).await;
+  }
+
+  pub async fn channel(self: Arc<Self>, channel_id: Snowflake) -> Result<Arc<DiscordChannel>, ChatError> {
     let channel_id = ChannelId::new(channel_id.0);
 
-    let self_clone = self.clone();
-    let mut channels = self_clone.channels.write().await;
-    let existing = channels.get(&channel_id);
+    // so a channel asked for twice at once is only made once
+    let opening = self.opening.lock().await;
+    let existing = self.channels.read().await.get(&channel_id).cloned();
 
     if let Some(existing) = existing {
-      return existing.clone();
+      return Ok(existing);
     }
 
-    let new = Arc::new(DiscordChannel::new(self, channel_id).await);
+    let new = Arc::new(DiscordChannel::new(self.clone(), channel_id).await?);
+
+    self.channels.write().await.insert(channel_id, new.clone());
 
-    channels.insert(channel_id, new.clone());
+    drop(opening);
+
+    // the messages saved last time can be shown straight away, and whatever was sent since follows once it's fetched
+    if new.cached_bottom().await.is_some() {
+      let channel = new.clone();
+
+      tokio::spawn(async move { self.fetch_missed(&channel).await });
+    }
 
-    new
+    Ok(new)
   }
 
-  pub async fn send_message(&self, channel_id: ChannelId, content: String, nonce: String) {
-    channel_id
-      .send_message(
-        self.discord().http.clone(),
-        CreateMessage::new().content(content).enforce_nonce(true).nonce(serenity::all::Nonce::String(nonce)),
-      )
+  /// The features the client is connected for, which are the configured ones unless Discord refused the intents some
+  /// of them need.
+  pub fn config(&self) -> DiscordConfig {
+    self.config.get().copied().unwrap_or_default()
+  }
+
+  /// The features the client was configured for, including any Discord didn't allow.
+  pub fn requested_config(&self) -> DiscordConfig {
+    self.requested_config.get().copied().unwrap_or_default()
+  }
+
+  pub fn connection_state(&self) -> ConnectionState {
+    self.connection.state()
+  }
+
+  /// Follows the gateway connection's state, starting with the current one.
+  pub fn subscribe_connection(&self) -> watch::Receiver<ConnectionState> {
+    self.connection.subscribe()
+  }
+
+  /// Called once the gateway connection is ready, or has resumed. After a reconnection, the channels that are open
+  /// fetch the messages that were sent while it was down, since their events were missed.
+  pub(crate) async fn connected(&self) {
+    if !self.connection.ready() {
+      return;
+    }
+
+    let channels = self.channels.read().await.values().cloned().collect::<Vec<_>>();
+
+    for channel in channels {
+      self.fetch_missed(&channel).await;
+    }
+  }
+
+  /// Fetches the messages sent in `channel` since the bottom of it was cached, and tells listeners about them.
+  async fn fetch_missed(&self, channel: &DiscordChannel) {
+    match channel.backfill().await {
+      Ok(messages) => {
+        for message in messages {
+          self.dispatch_channel_event(channel.channel_id(), ChannelEvent::Created(message)).await;
+        }
+      }
+      Err(e) => log::error!("Failed to fetch missed messages: {}", e),
+    }
+  }
+
+  /// Follows the buckets requests are being held back in, starting with the current ones. The ones serenity is
+  /// waiting out are included, as it reports them.
+  pub fn subscribe_rate_limits(&self) -> watch::Receiver<Vec<RateLimit>> {
+    self.scheduler.subscribe()
+  }
+
+  /// The buckets Discord is rate limiting requests in right now, both the ones serenity is waiting out and the ones
+  /// requests are being retried in.
+  pub async fn rate_limits(&self) -> Vec<RateLimit> {
+    let mut rate_limits = self.scheduler.rate_limits();
+
+    rate_limits.extend(self.api().rate_limits().await);
+
+    rate_limits
+  }
+
+  pub async fn get_channel(&self, channel_id: ChannelId) -> Result<Channel, ChatError> {
+    self.scheduler.run(&format!("channels/{}", channel_id), Priority::Load, || self.api().get_channel(channel_id)).await
+  }
+
+  pub async fn send_message(&self, channel_id: ChannelId, content: String, nonce: String) -> Result<(), ChatError> {
+    // the nonce is enforced, so a retried send can't be posted twice
+    self
+      .scheduler
+      .run(&messages_bucket(channel_id), Priority::Send, || {
+        self.api().send_message(channel_id, content.clone(), nonce.clone())
+      })
       .await
-      .unwrap();
   }
 
-  pub async fn get_messages(&self, channel_id: ChannelId, builder: GetMessages) -> Vec<Message> {
-    println!("Discord: get_messages: {:?}", builder);
-    // FIXME: proper error handling
-    channel_id.messages(self.discord().http.clone(), builder).await.unwrap()
+  /// Fetches a page of messages. Asking for a page that is already being fetched waits for that instead.
+  pub async fn get_messages(
+    &self,
+    channel_id: ChannelId,
+    target: Option<MessagePagination>,
+    limit: u8,
+    priority: Priority,
+  ) -> Result<Vec<Message>, ChatError> {
+    log::debug!("Discord: get_messages: {:?} (limit {})", target, limit);
+
+    self
+      .pages
+      .run((channel_id, format!("{:?}", target), limit), || {
+        self.scheduler.run(&messages_bucket(channel_id), priority, || {
+          self.api().get_messages(channel_id, target, limit)
+        })
+      })
+      .await
   }
 
-  pub async fn get_specific_message(&self, channel_id: ChannelId, message_id: MessageId) -> Option<Message> {
-    println!("Discord: get_specific_messages");
-    // FIXME: proper error handling
-    Some(channel_id.message(self.discord().http.clone(), message_id).await.unwrap())
+  pub async fn get_specific_message(&self, channel_id: ChannelId, message_id: MessageId) -> Result<Option<Message>, ChatError> {
+    log::debug!("Discord: get_specific_messages");
+
+    self
+      .scheduler
+      .run(&messages_bucket(channel_id), Priority::Load, || {
+        self.api().get_message(channel_id, message_id)
+      })
+      .await
+  }
+
+  pub fn guilds(&self) -> Vec<DiscordGuild> {
+    let Some(discord) = self.discord() else {
+      return vec![];
+    };
+
+    let cache = &discord.cache;
+
+    let mut guilds = cache.guilds();
+    guilds.sort();
+
+    guilds.into_iter().filter_map(|id| cache.guild(id).map(|guild| DiscordGuild::from_serenity(&guild))).collect()
+  }
+
+  /// Follows the guilds the user is in, starting with the current ones. They change as the gateway tells the client
+  /// about guilds joined, left or edited, and as the guilds that were unavailable at first arrive.
+  pub fn subscribe_guilds(&self) -> watch::Receiver<Vec<DiscordGuild>> {
+    self.guild_changes.subscribe()
+  }
+
+  // called once serenity's cache has the change
+  fn guilds_changed(&self) {
+    self.guild_changes.send_replace(self.guilds());
+  }
+
+  pub fn guild(&self, guild_id: Snowflake) -> Option<DiscordGuild> {
+    self.discord()?.cache.guild(GuildId::new(guild_id.0)).map(|guild| DiscordGuild::from_serenity(&guild))
+  }
+
+  pub async fn direct_message_channels(&self) -> Result<Vec<ChannelListing<Snowflake>>, ChatError> {
+    // private channels aren't kept in serenity's cache, so these always come from the API
+    let mut channels = self.scheduler.run("users/@me/channels", Priority::Load, || self.api().get_dm_channels()).await?;
+
+    // most recently active first
+    channels.sort_by_key(|channel| std::cmp::Reverse(channel.last_message_id.unwrap_or(MessageId::new(channel.id.get()))));
+
+    Ok(
+      channels
+        .into_iter()
+        .map(|channel| ChannelListing {
+          identifier: channel.id.into(),
+          name: channel.recipient.display_name().to_owned().into(),
+          kind: ChannelKind::DirectMessage,
+        })
+        .collect(),
+    )
+  }
+}
+
+// like Discord's routes, which is roughly how it rate limits requests
+fn messages_bucket(channel_id: ChannelId) -> String {
+  format!("channels/{}/messages", channel_id)
+}
+
+// the bucket requests to a route serenity reports on were scheduled in
+fn scheduler_bucket(url: &str) -> String {
+  let route = url.split_once("/api/v10/").map_or(url, |(_, route)| route);
+
+  match route.split('/').collect::<Vec<_>>()[..] {
+    // a channel's messages are all scheduled together, whichever of them is asked for
+    ["channels", channel_id, "messages", ..] => format!("channels/{}/messages", channel_id),
+    _ => route.to_owned(),
+  }
+}
+
+impl Client for DiscordClient {
+  type Identifier = Snowflake;
+  type Channel = Arc<DiscordChannel>;
+  type Guild = DiscordGuild;
+
+  async fn channel(&self, identifier: Snowflake) -> Result<Arc<DiscordChannel>, ChatError> {
+    match self.weak.upgrade() {
+      Some(client) => client.channel(identifier).await,
+      None => Err(ChatError::new(ChatErrorKind::Other, "The client has been dropped")),
+    }
+  }
+
+  async fn guilds(&self) -> Vec<DiscordGuild> {
+    DiscordClient::guilds(self)
+  }
+
+  async fn guild(&self, identifier: Snowflake) -> Option<DiscordGuild> {
+    DiscordClient::guild(self, identifier)
+  }
+
+  async fn direct_message_channels(&self) -> Result<Vec<ChannelListing<Snowflake>>, ChatError> {
+    DiscordClient::direct_message_channels(self).await
   }
 }
 
@@ -137,30 +624,73 @@ impl EventHandler for DiscordClient {
   async fn ready(&self, _: Context, ready: Ready) {
     self.user.get_or_init(|| Arc::new((*ready.user).clone()));
 
+    // a new connection's cache only replaces the old one once it's ready, and the guilds are read again from it, since
+    // ones may have been joined or left in between, or have arrived before it was the client's
+    if let Some(gateway) = self.pending_gateway.borrow_mut().take() {
+      self.gateway.replace(gateway);
+      self.guilds_changed();
+    }
+
     if let Some(ready_notifier) = self.ready_notifier.borrow_mut().take() {
-      ready_notifier.send(()).unwrap();
+      let _ = ready_notifier.send(Ok(()));
     }
+
+    self.connected().await;
   }
 
-  async fn message(&self, _: Context, msg: Message) {
-    if let Some(vec) = self.channel_message_event_handlers.read().await.get(&msg.channel_id) {
-      let msg = Arc::new(msg);
-      let channel = Arc::new(msg.channel(self.discord()).await.unwrap());
-      let member = match msg.member(self.discord()).await {
-        Ok(v) => Ok(Some(Arc::new(v))),
-        Err(serenity::Error::Model(ModelError::ItemMissing)) => Ok(None),
-        Err(e) => Err(e),
-      }
-      .unwrap();
+  async fn resume(&self, _: Context, _: ResumedEvent) {
+    self.connected().await;
+  }
 
-      for sender in vec {
-        let _ = sender.send(DiscordMessage::from_serenity(
-          self.weak.upgrade().unwrap(),
-          msg.clone(),
-          channel.clone(),
-          member.clone(),
-        ));
-      }
+  async fn ratelimit(&self, info: RatelimitInfo) {
+    // serenity waits it out itself, this holds back the requests still queued for the bucket
+    self.scheduler.limit(&scheduler_bucket(&info.path), Instant::now() + info.timeout);
+  }
+
+  async fn shard_stage_update(&self, _: Context, event: ShardStageUpdateEvent) {
+    match event.new {
+      ConnectionStage::Connecting | ConnectionStage::Handshake | ConnectionStage::Identifying => self.connection.connecting(),
+      ConnectionStage::Resuming => self.connection.resuming(),
+      ConnectionStage::Disconnected => self.connection.lost(),
+      // it's only ready once the ready or resumed event arrives
+      _ => {}
     }
   }
+
+  async fn guild_create(&self, _: Context, _: Guild, _: Option<bool>) {
+    self.guilds_changed();
+  }
+
+  async fn guild_update(&self, _: Context, _: Option<Guild>, _: PartialGuild) {
+    self.guilds_changed();
+  }
+
+  async fn guild_delete(&self, _: Context, _: UnavailableGuild, _: Option<Guild>) {
+    self.guilds_changed();
+  }
+
+  async fn message(&self, _: Context, msg: Message) {
+    self.message_created(msg).await;
+  }
+
+  async fn message_update(&self, _: Context, _: Option<Message>, new: Option<Message>, event: MessageUpdateEvent) {
+    self.message_updated(new, event).await;
+  }
+
+  async fn guild_members_chunk(&self, _: Context, chunk: GuildMembersChunkEvent) {
+    self.members_chunk(chunk.guild_id, chunk.members.into_values().collect(), chunk.not_found).await;
+  }
+
+  async fn message_delete(&self, _: Context, channel_id: ChannelId, deleted_message_id: MessageId, _: Option<GuildId>) {
+    self.dispatch_channel_event(channel_id, ChannelEvent::Deleted(deleted_message_id.into())).await;
+  }
+
+  async fn message_delete_bulk(&self, _: Context, channel_id: ChannelId, multiple_deleted_messages_ids: Vec<MessageId>, _: Option<GuildId>) {
+    self
+      .dispatch_channel_event(
+        channel_id,
+        ChannelEvent::BulkDeleted(multiple_deleted_messages_ids.into_iter().map(Snowflake::from).collect()),
+      )
+      .await;
+  }
 }
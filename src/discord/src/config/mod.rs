@@ -0,0 +1,80 @@
+pub mod tests;
+
+use serenity::all::{ApplicationFlags, GatewayIntents};
+
+// the privileged intents, with the application flags saying a bot has been allowed them, in either a large or a small
+// number of guilds
+const PRIVILEGED_INTENTS: [(GatewayIntents, ApplicationFlags); 3] = [
+  (
+    GatewayIntents::MESSAGE_CONTENT,
+    ApplicationFlags::GATEWAY_MESSAGE_CONTENT.union(ApplicationFlags::GATEWAY_MESSAGE_CONTENT_LIMITED),
+  ),
+  (
+    GatewayIntents::GUILD_MEMBERS,
+    ApplicationFlags::GATEWAY_GUILD_MEMBERS.union(ApplicationFlags::GATEWAY_GUILD_MEMBERS_LIMITED),
+  ),
+  (
+    GatewayIntents::GUILD_PRESENCES,
+    ApplicationFlags::GATEWAY_PRESENCE.union(ApplicationFlags::GATEWAY_PRESENCE_LIMITED),
+  ),
+];
+
+/// Which features the client is used for, which decides the gateway intents it asks Discord for.
+///
+/// The privileged intents have to be enabled for bots in the Developer Portal. When Discord refuses them, the client
+/// connects without them, see `DiscordClient::config`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiscordConfig {
+  /// What messages say. Without the privileged `MESSAGE_CONTENT` intent, bots only see the content of messages that
+  /// mention them
+  pub message_content: bool,
+  /// Keeping members up to date as they join, leave or change their nickname, with the privileged `GUILD_MEMBERS` intent
+  pub members: bool,
+  /// Who is online, with the privileged `GUILD_PRESENCES` intent
+  pub presences: bool,
+  /// Direct messages, besides guild channels
+  pub direct_messages: bool,
+}
+
+impl Default for DiscordConfig {
+  fn default() -> Self {
+    DiscordConfig {
+      message_content: true,
+      members: true,
+      // nothing shows presences yet
+      presences: false,
+      direct_messages: true,
+    }
+  }
+}
+
+impl DiscordConfig {
+  pub fn intents(&self) -> GatewayIntents {
+    let mut intents = GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES | GatewayIntents::GUILD_MESSAGE_REACTIONS;
+
+    intents.set(
+      GatewayIntents::DIRECT_MESSAGES | GatewayIntents::DIRECT_MESSAGE_REACTIONS,
+      self.direct_messages,
+    );
+    intents.set(GatewayIntents::MESSAGE_CONTENT, self.message_content);
+    intents.set(GatewayIntents::GUILD_MEMBERS, self.members);
+    intents.set(GatewayIntents::GUILD_PRESENCES, self.presences);
+
+    intents
+  }
+
+  /// Turns off the features that need intents which aren't in `intents`, e.g. because Discord refused them.
+  pub fn limit_to(self, intents: GatewayIntents) -> Self {
+    DiscordConfig {
+      message_content: self.message_content && intents.contains(GatewayIntents::MESSAGE_CONTENT),
+      members: self.members && intents.contains(GatewayIntents::GUILD_MEMBERS),
+      presences: self.presences && intents.contains(GatewayIntents::GUILD_PRESENCES),
+      direct_messages: self.direct_messages && intents.contains(GatewayIntents::DIRECT_MESSAGES),
+    }
+  }
+}
+
+/// `intents`, less the privileged ones the application's `flags` say it hasn't been allowed, which Discord would refuse.
+pub fn allowed_intents(intents: GatewayIntents, flags: ApplicationFlags) -> GatewayIntents {
+  PRIVILEGED_INTENTS.into_iter().filter(|(_, allowed)| !flags.intersects(*allowed)).fold(intents, |intents, (intent, _)| intents - intent)
+}
@@ -0,0 +1,64 @@
+#[allow(unused_imports)]
+use serenity::all::{ApplicationFlags, GatewayIntents};
+
+#[allow(unused_imports)]
+use super::{allowed_intents, DiscordConfig};
+
+#[test]
+pub fn config_only_asks_for_intents_of_enabled_features() {
+  let intents = DiscordConfig {
+    message_content: false,
+    members: false,
+    presences: false,
+    direct_messages: false,
+  }
+  .intents();
+
+  assert!(intents.contains(GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES));
+  assert!(!intents.intersects(GatewayIntents::privileged() | GatewayIntents::DIRECT_MESSAGES));
+
+  let intents = DiscordConfig::default().intents();
+
+  assert!(intents.contains(GatewayIntents::MESSAGE_CONTENT | GatewayIntents::GUILD_MEMBERS | GatewayIntents::DIRECT_MESSAGES));
+  assert!(!intents.contains(GatewayIntents::GUILD_PRESENCES));
+}
+
+#[test]
+pub fn intents_the_application_isnt_allowed_are_dropped() {
+  let intents = DiscordConfig {
+    presences: true,
+    ..Default::default()
+  }
+  .intents();
+
+  // a bot in under 100 guilds that has been given the members intent, and one in more that has message content
+  let allowed = allowed_intents(
+    intents,
+    ApplicationFlags::GATEWAY_GUILD_MEMBERS_LIMITED | ApplicationFlags::GATEWAY_MESSAGE_CONTENT,
+  );
+
+  assert_eq!(intents - allowed, GatewayIntents::GUILD_PRESENCES);
+
+  // with none of them, only the unprivileged intents are left
+  let allowed = allowed_intents(intents, ApplicationFlags::empty());
+
+  assert_eq!(allowed, intents - GatewayIntents::privileged());
+  assert!(allowed.contains(GatewayIntents::GUILD_MESSAGES | GatewayIntents::DIRECT_MESSAGES));
+
+  // and the features that needed them are turned off
+  let config = DiscordConfig::default().limit_to(DiscordConfig::default().intents() - GatewayIntents::MESSAGE_CONTENT);
+
+  assert!(!config.message_content);
+  assert!(config.members && config.direct_messages);
+}
+
+#[test]
+pub fn intents_that_werent_asked_for_arent_added() {
+  let intents = DiscordConfig::default().intents();
+
+  assert_eq!(allowed_intents(intents, ApplicationFlags::all()), intents);
+  assert_eq!(
+    allowed_intents(intents, ApplicationFlags::GATEWAY_PRESENCE),
+    intents - GatewayIntents::MESSAGE_CONTENT - GatewayIntents::GUILD_MEMBERS
+  );
+}
@@ -0,0 +1,126 @@
+pub mod tests;
+
+use std::{
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use scope_chat::error::ChatError;
+use tokio::sync::watch;
+
+// after the gateway connection fails, it's made again after this, then twice as long each time it fails again
+const FIRST_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Where the gateway connection is at. Messages only arrive live while it's `Ready`.
+#[derive(Clone, Debug)]
+pub enum ConnectionState {
+  /// Connecting for the first time, or starting a new session after the last one was lost
+  Connecting,
+  Ready,
+  /// Picking the last session back up, after which the events that were missed are replayed
+  Resuming,
+  Disconnected {
+    /// Why, if the connection failed rather than being closed
+    error: Option<ChatError>,
+    /// When the connection is made again, or `None` if it won't be, or the gateway decides when
+    retry_at: Option<Instant>,
+  },
+}
+
+impl ConnectionState {
+  pub fn is_ready(&self) -> bool {
+    matches!(self, ConnectionState::Ready)
+  }
+
+  /// How long until the connection is made again, if it's waiting to be.
+  pub fn retry_in(&self) -> Option<Duration> {
+    match self {
+      ConnectionState::Disconnected {
+        retry_at: Some(retry_at), ..
+      } => Some(retry_at.saturating_duration_since(Instant::now())),
+      _ => None,
+    }
+  }
+}
+
+/// Tracks the gateway connection as it moves between states, and tells subscribers about each change.
+pub struct Connection {
+  state: watch::Sender<ConnectionState>,
+  progress: Mutex<Progress>,
+}
+
+#[derive(Default)]
+struct Progress {
+  // failures since the connection was last ready
+  failures: u32,
+  was_ready: bool,
+}
+
+impl Default for Connection {
+  fn default() -> Self {
+    Connection {
+      state: watch::Sender::new(ConnectionState::Connecting),
+      progress: Mutex::new(Progress::default()),
+    }
+  }
+}
+
+impl Connection {
+  pub fn state(&self) -> ConnectionState {
+    self.state.borrow().clone()
+  }
+
+  /// Sees the current state, then every change after it.
+  pub fn subscribe(&self) -> watch::Receiver<ConnectionState> {
+    self.state.subscribe()
+  }
+
+  pub fn connecting(&self) {
+    self.state.send_replace(ConnectionState::Connecting);
+  }
+
+  pub fn resuming(&self) {
+    self.state.send_replace(ConnectionState::Resuming);
+  }
+
+  /// Returns whether this is a reconnection, after which whatever was sent in the meantime has to be fetched.
+  pub fn ready(&self) -> bool {
+    let reconnected = {
+      let mut progress = self.progress.lock().unwrap();
+
+      progress.failures = 0;
+
+      std::mem::replace(&mut progress.was_ready, true)
+    };
+
+    self.state.send_replace(ConnectionState::Ready);
+
+    reconnected
+  }
+
+  /// The gateway dropped the connection, and is making it again itself.
+  pub fn lost(&self) {
+    self.state.send_replace(ConnectionState::Disconnected { error: None, retry_at: None });
+  }
+
+  /// The connection failed for good, unless it's made again. Returns how long to wait before doing that, or `None`
+  /// if it won't succeed any better next time, e.g. because the token was rejected.
+  pub fn failed(&self, error: ChatError) -> Option<Duration> {
+    let delay = error.is_retryable().then(|| {
+      let mut progress = self.progress.lock().unwrap();
+      let delay = FIRST_RECONNECT_DELAY.saturating_mul(2u32.saturating_pow(progress.failures)).min(MAX_RECONNECT_DELAY);
+
+      progress.failures += 1;
+
+      delay
+    });
+
+    self.state.send_replace(ConnectionState::Disconnected {
+      error: Some(error),
+      retry_at: delay.map(|delay| Instant::now() + delay),
+    });
+
+    delay
+  }
+}
@@ -0,0 +1,50 @@
+#[allow(unused_imports)]
+use std::time::Duration;
+
+#[allow(unused_imports)]
+use scope_chat::error::{ChatError, ChatErrorKind};
+
+#[allow(unused_imports)]
+use super::{Connection, ConnectionState};
+
+#[test]
+pub fn connection_backs_off_until_it_is_ready_again() {
+  let connection = Connection::default();
+  let mut receiver = connection.subscribe();
+
+  assert!(matches!(connection.state(), ConnectionState::Connecting));
+  assert!(!connection.ready());
+
+  let delays = (0..8).map(|_| connection.failed(ChatError::new(ChatErrorKind::Network, "Connection reset")).unwrap()).collect::<Vec<_>>();
+
+  assert_eq!(&delays[..3], &[Duration::from_secs(1), Duration::from_secs(2), Duration::from_secs(4)]);
+  assert_eq!(delays[7], Duration::from_secs(60));
+
+  assert!(receiver.has_changed().unwrap());
+  assert!(receiver.borrow_and_update().retry_in().unwrap() > Duration::from_secs(59));
+
+  // a connection that was ready before is a reconnection, and starts backing off from the beginning again
+  assert!(connection.ready());
+  assert!(receiver.borrow_and_update().is_ready());
+  assert_eq!(
+    connection.failed(ChatError::new(ChatErrorKind::Network, "Connection reset")),
+    Some(Duration::from_secs(1))
+  );
+}
+
+#[test]
+pub fn connection_stops_when_retrying_would_not_help() {
+  let connection = Connection::default();
+
+  assert_eq!(
+    connection.failed(ChatError::new(ChatErrorKind::Unauthorized, "Authentication failed")),
+    None
+  );
+
+  let ConnectionState::Disconnected { error, retry_at } = connection.state() else {
+    panic!("Expected the connection to be disconnected");
+  };
+
+  assert_eq!(error.unwrap().kind(), ChatErrorKind::Unauthorized);
+  assert!(retry_at.is_none());
+}
@@ -0,0 +1,34 @@
+use scope_chat::error::{ChatError, ChatErrorKind};
+use serenity::{
+  all::{GatewayError, HttpError},
+  Error,
+};
+
+pub(crate) fn chat_error(error: Error) -> ChatError {
+  let kind = match &error {
+    Error::Http(HttpError::UnsuccessfulRequest(response)) => match response.status_code.as_u16() {
+      401 => ChatErrorKind::Unauthorized,
+      403 => ChatErrorKind::Forbidden,
+      404 => ChatErrorKind::NotFound,
+      429 => ChatErrorKind::RateLimited,
+      500..=599 => ChatErrorKind::Unavailable,
+      _ => ChatErrorKind::Other,
+    },
+    Error::Http(HttpError::Request(_)) | Error::Io(_) | Error::Tungstenite(_) => ChatErrorKind::Network,
+    Error::Gateway(GatewayError::InvalidAuthentication) => ChatErrorKind::Unauthorized,
+    Error::Gateway(GatewayError::DisallowedGatewayIntents) => ChatErrorKind::Forbidden,
+    Error::Gateway(GatewayError::InvalidGatewayIntents) => ChatErrorKind::Other,
+    Error::Gateway(_) => ChatErrorKind::Unavailable,
+    _ => ChatErrorKind::Other,
+  };
+
+  let message = match &error {
+    Error::Http(HttpError::UnsuccessfulRequest(response)) => response.error.message.clone(),
+    Error::Gateway(GatewayError::DisallowedGatewayIntents) => {
+      "Discord refused the privileged gateway intents. Bots need them enabled in the Developer Portal".to_owned()
+    }
+    _ => error.to_string(),
+  };
+
+  ChatError::new(kind, message)
+}
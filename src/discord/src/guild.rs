@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use gpui::{div, IntoElement, ParentElement, RenderOnce, SharedString, Styled, WindowContext};
+use scope_chat::{
+  guild::{ChannelCategory, ChannelKind, ChannelListing, Guild},
+  message::IconRenderConfig,
+};
+use serenity::all::{ChannelType, GuildChannel};
+
+use crate::{message::author::DisplayIcon, snowflake::Snowflake};
+
+#[derive(Clone)]
+pub struct DiscordGuild {
+  id: Snowflake,
+  name: SharedString,
+  icon: Option<String>,
+  categories: Arc<Vec<ChannelCategory<Snowflake>>>,
+}
+
+impl DiscordGuild {
+  pub fn from_serenity(guild: &serenity::model::guild::Guild) -> Self {
+    DiscordGuild {
+      id: guild.id.into(),
+      name: guild.name.clone().into(),
+      icon: guild.icon_url(),
+      categories: Arc::new(categorize(guild.channels.values())),
+    }
+  }
+}
+
+pub(crate) fn channel_kind(kind: ChannelType) -> ChannelKind {
+  match kind {
+    ChannelType::Text => ChannelKind::Text,
+    ChannelType::News => ChannelKind::Announcement,
+    ChannelType::Voice | ChannelType::Stage => ChannelKind::Voice,
+    ChannelType::Forum => ChannelKind::Forum,
+    ChannelType::Private | ChannelType::GroupDm => ChannelKind::DirectMessage,
+    _ => ChannelKind::Other,
+  }
+}
+
+fn listing(channel: &GuildChannel) -> ChannelListing<Snowflake> {
+  ChannelListing {
+    identifier: channel.id.into(),
+    name: channel.name.clone().into(),
+    kind: channel_kind(channel.kind),
+  }
+}
+
+fn categorize<'a>(channels: impl Iterator<Item = &'a GuildChannel>) -> Vec<ChannelCategory<Snowflake>> {
+  // discord sorts by position, and falls back to the channel id when positions collide
+  let mut channels = channels.collect::<Vec<_>>();
+  channels.sort_by_key(|channel| (channel.position, channel.id));
+
+  let mut uncategorized = ChannelCategory {
+    identifier: None,
+    name: None,
+    channels: vec![],
+  };
+
+  let mut categories = channels
+    .iter()
+    .filter(|channel| channel.kind == ChannelType::Category)
+    .map(|category| ChannelCategory {
+      identifier: Some(category.id.into()),
+      name: Some(category.name.clone().into()),
+      channels: vec![],
+    })
+    .collect::<Vec<_>>();
+
+  for channel in channels.iter().filter(|channel| channel.kind != ChannelType::Category) {
+    let parent = channel.parent_id.map(Snowflake::from);
+
+    match categories.iter_mut().find(|category| parent.is_some() && category.identifier == parent) {
+      Some(category) => category.channels.push(listing(channel)),
+      None => uncategorized.channels.push(listing(channel)),
+    }
+  }
+
+  if !uncategorized.channels.is_empty() {
+    categories.insert(0, uncategorized);
+  }
+
+  categories
+}
+
+impl Guild for DiscordGuild {
+  type Identifier = Snowflake;
+  type ChannelIdentifier = Snowflake;
+  type Icon = GuildIcon;
+
+  fn get_identifier(&self) -> Self::Identifier {
+    self.id
+  }
+
+  fn get_name(&self) -> SharedString {
+    self.name.clone()
+  }
+
+  fn get_icon(&self, config: IconRenderConfig) -> Self::Icon {
+    match &self.icon {
+      Some(url) => GuildIcon::Image(DisplayIcon(url.clone(), config)),
+      None => GuildIcon::Acronym(self.name.split_whitespace().filter_map(|word| word.chars().next()).take(3).collect::<String>().into()),
+    }
+  }
+
+  fn get_categories(&self) -> Vec<ChannelCategory<Self::ChannelIdentifier>> {
+    (*self.categories).clone()
+  }
+}
+
+#[derive(Clone, IntoElement, Debug)]
+pub enum GuildIcon {
+  Image(DisplayIcon),
+  Acronym(SharedString),
+}
+
+impl RenderOnce for GuildIcon {
+  fn render(self, _: &mut WindowContext) -> impl IntoElement {
+    match self {
+      GuildIcon::Image(icon) => div().w_full().h_full().child(icon),
+      GuildIcon::Acronym(acronym) => div().w_full().h_full().rounded_full().flex().items_center().justify_center().text_sm().child(acronym),
+    }
+  }
+}
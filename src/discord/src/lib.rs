@@ -1,4 +1,13 @@
+pub mod api;
 pub mod channel;
 pub mod client;
+pub mod config;
+pub mod connection;
+pub(crate) mod error;
+pub mod guild;
+pub mod markdown;
+pub mod member;
 pub mod message;
+pub mod mock;
+pub mod scheduler;
 pub mod snowflake;
@@ -0,0 +1,754 @@
+pub mod tests;
+
+use chrono::DateTime;
+use scope_chat::content::{Block, Emoji, Inline, List, Mention, MentionKind, RichContent, Span, TextStyle, TimestampFormat};
+
+/// Parses a message written in Discord's markdown dialect.
+///
+/// This follows what Discord's client does, which is based on simple-markdown: inline formatting is matched lazily
+/// from left to right, `*` and `_` italics compete with `**` bold and `__` underline for the longest match, and
+/// quotes can't be nested. Mentions are left without names, which the caller can fill in from what it knows about
+/// the message.
+pub fn parse(text: &str) -> RichContent {
+  RichContent {
+    blocks: parse_blocks(text, false),
+  }
+}
+
+// a line of a list, before the list is put together
+struct ListLine<'t> {
+  indent: usize,
+  // `None` for bullets
+  number: Option<u64>,
+  text: &'t str,
+}
+
+fn parse_blocks(text: &str, in_quote: bool) -> Vec<Block> {
+  let mut blocks = vec![];
+  // the lines of the paragraph being collected, which may be blank
+  let mut paragraph: Option<String> = None;
+  let mut rest = text;
+
+  fn flush(blocks: &mut Vec<Block>, paragraph: &mut Option<String>) {
+    if let Some(paragraph) = paragraph.take() {
+      blocks.push(Block::Paragraph(parse_inline(&paragraph)));
+    }
+  }
+
+  loop {
+    let (line, after) = split_line(rest);
+
+    // quotes come first, so a code block can be quoted
+    if !in_quote {
+      if line.starts_with(">>> ") {
+        flush(&mut blocks, &mut paragraph);
+
+        // everything from here on is quoted
+        blocks.push(Block::Quote(parse_blocks(&rest[">>> ".len()..], true)));
+
+        break;
+      }
+
+      if line.starts_with("> ") {
+        flush(&mut blocks, &mut paragraph);
+
+        let mut quoted = vec![];
+        let mut next = Some(rest);
+
+        while let Some(current) = next {
+          let (line, after) = split_line(current);
+
+          let Some(stripped) = line.strip_prefix("> ") else {
+            break;
+          };
+
+          quoted.push(stripped);
+          next = after;
+        }
+
+        blocks.push(Block::Quote(parse_blocks(&quoted.join("\n"), true)));
+
+        match next {
+          Some(next) => rest = next,
+          None => break,
+        }
+
+        continue;
+      }
+    }
+
+    // code blocks can start anywhere on a line, and take everything up to where they're closed
+    if let Some((start, block, consumed)) = find_code_block(rest, line.len()) {
+      if start > 0 {
+        append_line(&mut paragraph, &line[..start]);
+      }
+
+      flush(&mut blocks, &mut paragraph);
+      blocks.push(block);
+
+      rest = &rest[start + consumed..];
+      // the code block ends its line, unless something follows it on the same line
+      rest = rest.strip_prefix('\n').unwrap_or(rest);
+
+      if rest.is_empty() {
+        break;
+      }
+
+      continue;
+    }
+
+    if let Some(block) = heading(line) {
+      flush(&mut blocks, &mut paragraph);
+      blocks.push(block);
+    } else if list_line(line).is_some() {
+      flush(&mut blocks, &mut paragraph);
+
+      let mut lines = vec![];
+      let mut next = Some(rest);
+
+      while let Some(current) = next {
+        let (line, after) = split_line(current);
+
+        let Some(list_line) = list_line(line) else {
+          break;
+        };
+
+        lines.push(list_line);
+        next = after;
+      }
+
+      blocks.extend(list_blocks(&lines));
+
+      match next {
+        Some(next) => rest = next,
+        None => break,
+      }
+
+      continue;
+    } else {
+      append_line(&mut paragraph, line);
+    }
+
+    match after {
+      Some(after) => rest = after,
+      None => break,
+    }
+  }
+
+  flush(&mut blocks, &mut paragraph);
+
+  blocks
+}
+
+// the first line of `text`, and everything after it if there's anything at all, even an empty line
+fn split_line(text: &str) -> (&str, Option<&str>) {
+  match text.split_once('\n') {
+    Some((line, after)) => (line, Some(after)),
+    None => (text, None),
+  }
+}
+
+fn append_line(paragraph: &mut Option<String>, line: &str) {
+  match paragraph {
+    Some(paragraph) => {
+      paragraph.push('\n');
+      paragraph.push_str(line);
+    }
+    None => *paragraph = Some(line.to_owned()),
+  }
+}
+
+// the first code block that starts within the first `line_len` bytes of `text`, with where it starts and how long it is
+fn find_code_block(text: &str, line_len: usize) -> Option<(usize, Block, usize)> {
+  let line = &text[..line_len];
+  let mut from = 0;
+
+  while let Some(offset) = line[from..].find("```") {
+    let start = from + offset;
+
+    if !is_escaped(line, start) {
+      if let Some((block, consumed)) = code_block(&text[start..]) {
+        return Some((start, block, consumed));
+      }
+    }
+
+    from = start + 1;
+  }
+
+  None
+}
+
+fn is_escaped(text: &str, index: usize) -> bool {
+  text[..index].chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
+
+// ```language
+// code
+// ```
+fn code_block(text: &str) -> Option<(Block, usize)> {
+  let content_start = 3;
+  let close = content_start + text[content_start..].find("```")?;
+  let content = &text[content_start..close];
+
+  if content.trim().is_empty() {
+    return None;
+  }
+
+  let (language, code) = match content.split_once('\n') {
+    Some((first, code)) if is_language(first) => (Some(first.to_owned()), code),
+    Some(("", code)) => (None, code),
+    _ => (None, content),
+  };
+
+  let code = code.strip_suffix('\n').unwrap_or(code);
+
+  Some((
+    Block::CodeBlock {
+      language,
+      code: code.to_owned(),
+    },
+    close + 3,
+  ))
+}
+
+fn is_language(text: &str) -> bool {
+  !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.' | '#'))
+}
+
+// `# `, `## ` and `### ` headings, and `-# ` subtext
+fn heading(line: &str) -> Option<Block> {
+  if let Some(content) = line.strip_prefix("-# ") {
+    return (!content.trim().is_empty()).then(|| Block::Subtext(parse_inline(content.trim())));
+  }
+
+  let level = line.chars().take_while(|c| *c == '#').count();
+  let content = line[level..].strip_prefix(' ')?;
+
+  if !(1..=3).contains(&level) || content.trim().is_empty() {
+    return None;
+  }
+
+  Some(Block::Heading {
+    level: level as u8,
+    content: parse_inline(content.trim()),
+  })
+}
+
+// `- item`, `* item` or `1. item`, indented to nest it
+fn list_line(line: &str) -> Option<ListLine<'_>> {
+  let text = line.trim_start_matches(' ');
+  let indent = line.len() - text.len();
+
+  let (number, text) = if let Some(text) = text.strip_prefix("- ").or_else(|| text.strip_prefix("* ")) {
+    (None, text)
+  } else {
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+
+    if !(1..=9).contains(&digits) {
+      return None;
+    }
+
+    (Some(text[..digits].parse().ok()?), text[digits..].strip_prefix(". ")?)
+  };
+
+  (!text.trim().is_empty()).then_some(ListLine { indent, number, text })
+}
+
+// items indented further than the one before them are nested in it, and switching between bullets and numbers starts
+// a new list
+fn list_blocks(lines: &[ListLine]) -> Vec<Block> {
+  let mut blocks = vec![];
+  let mut i = 0;
+
+  while i < lines.len() {
+    let first = &lines[i];
+    let mut list = List {
+      start: first.number,
+      items: vec![],
+    };
+
+    while i < lines.len() && lines[i].number.is_some() == first.number.is_some() {
+      let item = &lines[i];
+
+      i += 1;
+
+      let children = i;
+
+      while i < lines.len() && lines[i].indent > item.indent {
+        i += 1;
+      }
+
+      let mut content = vec![Block::Paragraph(parse_inline(item.text))];
+
+      content.extend(list_blocks(&lines[children..i]));
+      list.items.push(content);
+    }
+
+    blocks.push(Block::List(list));
+  }
+
+  blocks
+}
+
+fn parse_inline(text: &str) -> Vec<Inline> {
+  let mut inlines = Inlines::default();
+
+  inlines.parse(text, TextStyle::default(), false);
+  inlines.0
+}
+
+#[derive(Default)]
+struct Inlines(Vec<Inline>);
+
+impl Inlines {
+  fn text(&mut self, text: &str, style: TextStyle) {
+    if let Some(Inline::Text(span)) = self.0.last_mut() {
+      if span.style == style {
+        span.text.push_str(text);
+
+        return;
+      }
+    }
+
+    self.0.push(Inline::Text(Span {
+      text: text.to_owned(),
+      style,
+    }));
+  }
+
+  fn parse(&mut self, text: &str, style: TextStyle, in_link: bool) {
+    let mut i = 0;
+
+    while i < text.len() {
+      let rest = &text[i..];
+      let after_word = text[..i].chars().next_back().is_some_and(is_word);
+
+      if let Some(consumed) = self.rule(rest, after_word, style, in_link) {
+        i += consumed;
+
+        continue;
+      }
+
+      let c = rest.chars().next().unwrap();
+
+      self.text(&rest[..c.len_utf8()], style);
+      i += c.len_utf8();
+    }
+  }
+
+  // parses whatever starts at the beginning of `rest`, returning how much of it was used, or `None` if it's plain text
+  fn rule(&mut self, rest: &str, after_word: bool, style: TextStyle, in_link: bool) -> Option<usize> {
+    let first = rest.chars().next()?;
+
+    match first {
+      '\\' => {
+        let escaped = rest[1..].chars().next().filter(|c| !c.is_alphanumeric() && !c.is_whitespace())?;
+
+        self.text(&rest[1..1 + escaped.len_utf8()], style);
+
+        Some(1 + escaped.len_utf8())
+      }
+      '\n' => {
+        self.0.push(Inline::LineBreak);
+
+        Some(1)
+      }
+      '`' => {
+        let (code, consumed) = inline_code(rest)?;
+
+        self.0.push(Inline::Code(code.to_owned()));
+
+        Some(consumed)
+      }
+      '<' => self.angle_brackets(rest, style, in_link),
+      '@' => {
+        let (kind, consumed) = if rest.starts_with("@everyone") {
+          (MentionKind::Everyone, "@everyone".len())
+        } else if rest.starts_with("@here") {
+          (MentionKind::Here, "@here".len())
+        } else {
+          return None;
+        };
+
+        self.0.push(Inline::Mention(Mention {
+          kind,
+          id: rest[1..consumed].to_owned(),
+          name: None,
+        }));
+
+        Some(consumed)
+      }
+      '[' if !in_link => {
+        let (label, url, consumed) = masked_link(rest)?;
+        let mut content = Inlines::default();
+
+        content.parse(label, style, true);
+        self.0.push(Inline::Link {
+          url: url.to_owned(),
+          content: content.0,
+        });
+
+        Some(consumed)
+      }
+      'h' if !in_link => {
+        let url = url(rest)?;
+
+        self.0.push(Inline::Link {
+          url: url.to_owned(),
+          content: vec![Inline::Text(Span { text: url.to_owned(), style })],
+        });
+
+        Some(url.len())
+      }
+      '|' => {
+        let (inner, consumed) = delimited(rest, "||", None, |_| true)?;
+        let mut content = Inlines::default();
+
+        // everything in it is hidden, not just its text
+        content.parse(inner, style, in_link);
+        self.0.push(Inline::Spoiler(content.0));
+
+        Some(consumed)
+      }
+      '~' => {
+        if !rest.starts_with("~~") || rest[2..].starts_with(char::is_whitespace) {
+          return None;
+        }
+
+        let (inner, consumed) = delimited(rest, "~~", None, |inner| !inner.ends_with(char::is_whitespace))?;
+
+        self.parse(
+          inner,
+          TextStyle {
+            strikethrough: true,
+            ..style
+          },
+          in_link,
+        );
+
+        Some(consumed)
+      }
+      '*' | '_' => {
+        let (inner, consumed, style) = emphasis(rest, after_word, style)?;
+
+        self.parse(inner, style, in_link);
+
+        Some(consumed)
+      }
+      _ => None,
+    }
+  }
+
+  // mentions, custom emoji, timestamps and links that don't embed, all in `<...>`
+  fn angle_brackets(&mut self, rest: &str, style: TextStyle, in_link: bool) -> Option<usize> {
+    let end = rest.find('>')?;
+    let inner = &rest[1..end];
+    let consumed = end + 1;
+
+    let mention = |kind, id: &str| {
+      is_id(id).then(|| {
+        Inline::Mention(Mention {
+          kind,
+          id: id.to_owned(),
+          name: None,
+        })
+      })
+    };
+
+    let inline = if let Some(id) = inner.strip_prefix("@&") {
+      mention(MentionKind::Role, id)?
+    } else if let Some(id) = inner.strip_prefix("@!").or_else(|| inner.strip_prefix('@')) {
+      mention(MentionKind::User, id)?
+    } else if let Some(id) = inner.strip_prefix('#') {
+      mention(MentionKind::Channel, id)?
+    } else if let Some(text) = inner.strip_prefix("t:") {
+      timestamp(text)?
+    } else if inner.starts_with(':') || inner.starts_with("a:") {
+      custom_emoji(inner)?
+    } else if !in_link && (inner.starts_with("http://") || inner.starts_with("https://")) && !inner.contains(char::is_whitespace) {
+      Inline::Link {
+        url: inner.to_owned(),
+        content: vec![Inline::Text(Span {
+          text: inner.to_owned(),
+          style,
+        })],
+      }
+    } else {
+      return None;
+    };
+
+    self.0.push(inline);
+
+    Some(consumed)
+  }
+}
+
+fn is_id(text: &str) -> bool {
+  !text.is_empty() && text.len() <= 20 && text.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_word(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_'
+}
+
+// `<t:1618953630>` or `<t:1618953630:R>`, without the brackets and `t:`
+fn timestamp(text: &str) -> Option<Inline> {
+  let (seconds, format) = match text.split_once(':') {
+    Some((seconds, format)) => (seconds, Some(format)),
+    None => (text, None),
+  };
+
+  let format = match format {
+    None => TimestampFormat::default(),
+    Some("t") => TimestampFormat::ShortTime,
+    Some("T") => TimestampFormat::LongTime,
+    Some("d") => TimestampFormat::ShortDate,
+    Some("D") => TimestampFormat::LongDate,
+    Some("f") => TimestampFormat::ShortDateTime,
+    Some("F") => TimestampFormat::LongDateTime,
+    Some("R") => TimestampFormat::Relative,
+    Some(_) => return None,
+  };
+
+  let digits = seconds.strip_prefix('-').unwrap_or(seconds);
+
+  if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
+
+  let time = DateTime::from_timestamp(seconds.parse().ok()?, 0)?;
+
+  Some(Inline::Timestamp { time, format })
+}
+
+// `<:name:id>` or `<a:name:id>` when it's animated, without the brackets
+fn custom_emoji(text: &str) -> Option<Inline> {
+  let (animated, text) = match text.strip_prefix("a:") {
+    Some(text) => (true, text),
+    None => (false, text.strip_prefix(':')?),
+  };
+
+  let (name, id) = text.split_once(':')?;
+
+  if name.len() < 2 || !name.chars().all(is_word) || !is_id(id) {
+    return None;
+  }
+
+  Some(Inline::Emoji(Emoji::Custom {
+    name: name.to_owned(),
+    url: format!("https://cdn.discordapp.com/emojis/{}.{}", id, if animated { "gif" } else { "png" }),
+    animated,
+  }))
+}
+
+// `code`, or ``code with ` in it``
+fn inline_code(text: &str) -> Option<(&str, usize)> {
+  let ticks = text.chars().take_while(|c| *c == '`').count();
+  let mut from = ticks;
+
+  loop {
+    let start = from + text[from..].find('`')?;
+    let run = text[start..].chars().take_while(|c| *c == '`').count();
+
+    if run == ticks && start > ticks {
+      let code = &text[ticks..start];
+
+      // a space is how code starts or ends with a backtick, and isn't part of it
+      let code = match (code.strip_prefix(' '), ticks > 1) {
+        (Some(trimmed), true) if trimmed.starts_with('`') => trimmed,
+        _ => code,
+      };
+      let code = match (code.strip_suffix(' '), ticks > 1) {
+        (Some(trimmed), true) if trimmed.ends_with('`') => trimmed,
+        _ => code,
+      };
+
+      return Some((code, start + run));
+    }
+
+    from = start + run;
+  }
+}
+
+// [label](https://example.com) or [label](<https://example.com>)
+fn masked_link(text: &str) -> Option<(&str, &str, usize)> {
+  let mut depth = 0;
+  let mut label_end = None;
+  let mut chars = text.char_indices().skip(1);
+
+  while let Some((i, c)) = chars.next() {
+    match c {
+      '\\' => {
+        chars.next();
+      }
+      '[' => depth += 1,
+      ']' if depth == 0 => {
+        label_end = Some(i);
+        break;
+      }
+      ']' => depth -= 1,
+      _ => {}
+    }
+  }
+
+  let label_end = label_end?;
+  let label = &text[1..label_end];
+  let target = text[label_end + 1..].strip_prefix('(')?;
+  let target_end = target.find(')')?;
+  let url = target[..target_end].trim();
+  let url = url.strip_prefix('<').and_then(|url| url.strip_suffix('>')).unwrap_or(url);
+
+  if label.trim().is_empty() || !(url.starts_with("http://") || url.starts_with("https://")) || url.contains(char::is_whitespace) {
+    return None;
+  }
+
+  Some((label, url, label_end + 2 + target_end + 1))
+}
+
+// a bare link, which doesn't take punctuation that ends the sentence it's in
+fn url(text: &str) -> Option<&str> {
+  let scheme = if text.starts_with("https://") {
+    "https://".len()
+  } else if text.starts_with("http://") {
+    "http://".len()
+  } else {
+    return None;
+  };
+
+  let end = text.find(|c: char| c.is_whitespace() || c == '<').unwrap_or(text.len());
+  let mut url = &text[..end];
+
+  loop {
+    let trimmed = url.trim_end_matches(['.', ',', ':', ';', '"', '\'', ']']);
+    // a closing parenthesis is kept when it closes one in the link, like in Wikipedia's
+    let trimmed = match trimmed.strip_suffix(')') {
+      Some(without) if without.matches('(').count() <= without.matches(')').count() => without,
+      _ => trimmed,
+    };
+
+    if trimmed.len() == url.len() {
+      break;
+    }
+
+    url = trimmed;
+  }
+
+  (url.len() > scheme).then_some(url)
+}
+
+// `text` starts with `delimiter`. Finds the first unescaped `delimiter` after some content, where `accept` accepts the
+// content, returning the content and how long the whole thing is.
+fn delimited<'t>(text: &'t str, delimiter: &str, not_followed_by: Option<char>, accept: impl Fn(&str) -> bool) -> Option<(&'t str, usize)> {
+  let start = delimiter.len();
+  let mut i = start;
+
+  if !text.starts_with(delimiter) {
+    return None;
+  }
+
+  while i < text.len() {
+    let tail = &text[i..];
+
+    if i > start && tail.starts_with(delimiter) {
+      let followed_by = tail[delimiter.len()..].chars().next();
+
+      if (not_followed_by.is_none() || followed_by != not_followed_by) && accept(&text[start..i]) {
+        return Some((&text[start..i], i + delimiter.len()));
+      }
+    }
+
+    let c = tail.chars().next().unwrap();
+
+    i += c.len_utf8();
+
+    if c == '\\' {
+      if let Some(escaped) = text[i..].chars().next() {
+        i += escaped.len_utf8();
+      }
+    }
+  }
+
+  None
+}
+
+// `*italic*`, `_italic_`, `**bold**` and `__underline__`. Like Discord, the longest of them wins, and italics win ties.
+// `_italic_` has to start at the start of a word, like it has to end at the end of one.
+fn emphasis(text: &str, after_word: bool, style: TextStyle) -> Option<(&str, usize, TextStyle)> {
+  let italic = if text.starts_with('*') {
+    star_italic(text)
+  } else if !after_word {
+    underscore_italic(text)
+  } else {
+    None
+  };
+  let strong = if text.starts_with('*') {
+    delimited(text, "**", Some('*'), |_| true).map(|(inner, consumed)| (inner, consumed, TextStyle { bold: true, ..style }))
+  } else {
+    delimited(text, "__", Some('_'), |_| true).map(|(inner, consumed)| (inner, consumed, TextStyle { underline: true, ..style }))
+  };
+
+  let italic = italic.map(|(inner, consumed)| (inner, consumed, TextStyle { italic: true, ..style }));
+
+  match (italic, strong) {
+    (Some(italic), Some(strong)) if strong.1 > italic.1 => Some(strong),
+    (Some(italic), _) => Some(italic),
+    (None, strong) => strong,
+  }
+}
+
+// `*italic*`, which can't start or end with whitespace, but can have `**bold**` in it
+fn star_italic(text: &str) -> Option<(&str, usize)> {
+  if text[1..].starts_with(char::is_whitespace) {
+    return None;
+  }
+
+  let mut i = 1;
+
+  while i < text.len() {
+    let tail = &text[i..];
+
+    if tail.starts_with("**") {
+      i += 2;
+    } else if tail.starts_with('*') {
+      return (i > 1).then(|| (&text[1..i], i + 1));
+    } else if tail.starts_with('\\') {
+      i += 1;
+      i += text[i..].chars().next().map_or(0, char::len_utf8);
+    } else if tail.starts_with(char::is_whitespace) {
+      i += tail.chars().take_while(|c| c.is_whitespace()).map(char::len_utf8).sum::<usize>();
+
+      // whitespace can only be followed by more of the text, or bold
+      if text[i..].starts_with('*') && !text[i..].starts_with("**") || i == text.len() {
+        return None;
+      }
+    } else {
+      i += tail.chars().next().unwrap().len_utf8();
+    }
+  }
+
+  None
+}
+
+// `_italic_`, which has to end at the end of a word, so `snake_case` isn't italic
+fn underscore_italic(text: &str) -> Option<(&str, usize)> {
+  let mut i = 1;
+
+  while i < text.len() {
+    let tail = &text[i..];
+
+    if tail.starts_with("__") {
+      i += 2;
+    } else if tail.starts_with('_') {
+      let at_word_end = !text[i + 1..].starts_with(is_word);
+
+      return (i > 1 && at_word_end).then(|| (&text[1..i], i + 1));
+    } else if tail.starts_with('\\') {
+      i += 1;
+      i += text[i..].chars().next().map_or(0, char::len_utf8);
+    } else {
+      i += tail.chars().next().unwrap().len_utf8();
+    }
+  }
+
+  None
+}
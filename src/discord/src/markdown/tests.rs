@@ -0,0 +1,194 @@
+#[allow(unused_imports)]
+use scope_chat::content::{Block, Emoji, Inline, MentionKind, TimestampFormat};
+
+#[allow(unused_imports)]
+use super::parse;
+
+// Each fixture file has cases like this, where the expected tree has one node per line, and children are indented
+// under their parent:
+//
+// === name of the case
+// the message
+// ---
+// the tree it's parsed into
+#[allow(dead_code)]
+fn check_fixtures(fixtures: &str) {
+  let mut failures = vec![];
+  let mut cases = 0;
+
+  for case in fixtures.split("=== ").skip(1) {
+    let (name, case) = case.split_once('\n').expect("a case has a name, then the message");
+    let (input, expected) = case.split_once("\n---\n").unwrap_or_else(|| panic!("{} has no expected tree", name));
+
+    let actual = dump(&parse(input).blocks);
+    let expected = expected.trim_end();
+
+    cases += 1;
+
+    if actual != expected {
+      failures.push(format!(
+        "{}\n  message:\n{}\n  expected:\n{}\n  parsed:\n{}",
+        name,
+        indent(input),
+        indent(expected),
+        indent(&actual)
+      ));
+    }
+  }
+
+  assert!(cases > 0, "no cases in the fixtures");
+  assert!(
+    failures.is_empty(),
+    "{} of {} cases failed:\n\n{}",
+    failures.len(),
+    cases,
+    failures.join("\n\n")
+  );
+}
+
+#[allow(dead_code)]
+fn indent(text: &str) -> String {
+  text.lines().map(|line| format!("    {}", line)).collect::<Vec<_>>().join("\n")
+}
+
+#[allow(dead_code)]
+fn dump(blocks: &[Block]) -> String {
+  let mut lines = vec![];
+
+  dump_blocks(blocks, 0, &mut lines);
+
+  lines.join("\n")
+}
+
+#[allow(dead_code)]
+fn dump_blocks(blocks: &[Block], depth: usize, lines: &mut Vec<String>) {
+  let pad = "  ".repeat(depth);
+
+  for block in blocks {
+    match block {
+      Block::Paragraph(content) => {
+        lines.push(format!("{}p", pad));
+        dump_inlines(content, depth + 1, lines);
+      }
+      Block::Heading { level, content } => {
+        lines.push(format!("{}h{}", pad, level));
+        dump_inlines(content, depth + 1, lines);
+      }
+      Block::Subtext(content) => {
+        lines.push(format!("{}subtext", pad));
+        dump_inlines(content, depth + 1, lines);
+      }
+      Block::CodeBlock { language, code } => match language {
+        Some(language) => lines.push(format!("{}codeblock {} {:?}", pad, language, code)),
+        None => lines.push(format!("{}codeblock {:?}", pad, code)),
+      },
+      Block::Quote(blocks) => {
+        lines.push(format!("{}quote", pad));
+        dump_blocks(blocks, depth + 1, lines);
+      }
+      Block::List(list) => {
+        match list.start {
+          Some(start) => lines.push(format!("{}list {}", pad, start)),
+          None => lines.push(format!("{}list -", pad)),
+        }
+
+        for item in &list.items {
+          lines.push(format!("{}  item", pad));
+          dump_blocks(item, depth + 2, lines);
+        }
+      }
+    }
+  }
+}
+
+#[allow(dead_code)]
+fn dump_inlines(inlines: &[Inline], depth: usize, lines: &mut Vec<String>) {
+  let pad = "  ".repeat(depth);
+
+  for inline in inlines {
+    match inline {
+      Inline::Text(span) => {
+        let style = [
+          (span.style.bold, "bold"),
+          (span.style.italic, "italic"),
+          (span.style.underline, "underline"),
+          (span.style.strikethrough, "strike"),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| format!(" {}", name))
+        .collect::<String>();
+
+        lines.push(format!("{}text {:?}{}", pad, span.text, style));
+      }
+      Inline::Code(code) => lines.push(format!("{}code {:?}", pad, code)),
+      Inline::Link { url, content } => {
+        lines.push(format!("{}link {}", pad, url));
+        dump_inlines(content, depth + 1, lines);
+      }
+      Inline::Mention(mention) => {
+        let kind = match mention.kind {
+          MentionKind::User => "user",
+          MentionKind::Channel => "channel",
+          MentionKind::Role => "role",
+          MentionKind::Everyone => "everyone",
+          MentionKind::Here => "here",
+        };
+
+        match mention.kind {
+          MentionKind::Everyone | MentionKind::Here => lines.push(format!("{}mention {}", pad, kind)),
+          _ => lines.push(format!("{}mention {} {}", pad, kind, mention.id)),
+        }
+      }
+      Inline::Emoji(Emoji::Unicode(emoji)) => lines.push(format!("{}emoji {}", pad, emoji)),
+      Inline::Emoji(Emoji::Custom { name, url, animated }) => {
+        let animated = if *animated { " animated" } else { "" };
+
+        lines.push(format!("{}emoji {} {}{}", pad, name, url, animated));
+      }
+      Inline::Timestamp { time, format } => {
+        let format = match format {
+          TimestampFormat::ShortTime => "t",
+          TimestampFormat::LongTime => "T",
+          TimestampFormat::ShortDate => "d",
+          TimestampFormat::LongDate => "D",
+          TimestampFormat::ShortDateTime => "f",
+          TimestampFormat::LongDateTime => "F",
+          TimestampFormat::Relative => "R",
+        };
+
+        lines.push(format!("{}timestamp {} {}", pad, time.timestamp(), format));
+      }
+      Inline::Spoiler(content) => {
+        lines.push(format!("{}spoiler", pad));
+        dump_inlines(content, depth + 1, lines);
+      }
+      Inline::LineBreak => lines.push(format!("{}br", pad)),
+    }
+  }
+}
+
+#[test]
+pub fn markdown_inline_formatting() {
+  check_fixtures(include_str!("fixtures/inline.txt"));
+}
+
+#[test]
+pub fn markdown_blocks() {
+  check_fixtures(include_str!("fixtures/blocks.txt"));
+}
+
+#[test]
+pub fn markdown_mentions_emoji_timestamps_and_links() {
+  check_fixtures(include_str!("fixtures/entities.txt"));
+}
+
+#[test]
+pub fn markdown_escapes() {
+  check_fixtures(include_str!("fixtures/escapes.txt"));
+}
+
+#[test]
+pub fn markdown_nesting() {
+  check_fixtures(include_str!("fixtures/nesting.txt"));
+}
@@ -0,0 +1,122 @@
+use std::{
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use serenity::all::{GuildId, Member, Message, PartialMember, UserId};
+
+/// The guild members that messages have been sent by, so their nicknames and guild avatars can be shown without
+/// asking Discord about every message's author.
+///
+/// Members come from the partial member Discord includes with messages sent over the gateway, and from the
+/// `GUILD_MEMBERS_CHUNK` events that answer `DiscordApi::request_guild_members`.
+#[derive(Default)]
+pub struct MemberCache {
+  // `None` for users Discord said aren't members, e.g. because they've left the guild
+  members: DashMap<(GuildId, UserId), Option<Arc<Member>>>,
+  // asked for, but not answered yet, and when
+  requested: DashMap<(GuildId, UserId), Instant>,
+}
+
+// the gateway doesn't say when a request for members is lost, e.g. when it disconnects before answering, so members
+// that haven't been heard about by then are asked for again
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl MemberCache {
+  pub fn get(&self, guild_id: GuildId, user_id: UserId) -> Option<Arc<Member>> {
+    self.members.get(&(guild_id, user_id)).and_then(|member| member.clone())
+  }
+
+  /// Whether it's known if this user is a member, either way.
+  pub fn contains(&self, guild_id: GuildId, user_id: UserId) -> bool {
+    self.members.contains_key(&(guild_id, user_id))
+  }
+
+  pub fn insert(&self, guild_id: GuildId, member: Member) -> Arc<Member> {
+    let key = (guild_id, member.user.id);
+    let member = Arc::new(member);
+
+    self.requested.remove(&key);
+    self.members.insert(key, Some(member.clone()));
+
+    member
+  }
+
+  /// Remembers that these users aren't members, so they aren't asked for again.
+  pub fn insert_absent(&self, guild_id: GuildId, user_ids: &[UserId]) {
+    for user_id in user_ids {
+      self.requested.remove(&(guild_id, *user_id));
+      self.members.insert((guild_id, *user_id), None);
+    }
+  }
+
+  /// The member who sent `message`, if Discord sent it along with the message. It's cached, since it's newer than
+  /// whatever was known about them before.
+  pub fn insert_from_message(&self, guild_id: GuildId, message: &Message) -> Option<Arc<Member>> {
+    let partial = message.member.as_deref()?;
+
+    Some(self.insert(guild_id, member_from_partial(guild_id, partial, message)))
+  }
+
+  /// Which of `user_ids` aren't known about and haven't been asked for recently, marking them as asked for.
+  pub(crate) fn claim_missing(&self, guild_id: GuildId, user_ids: impl IntoIterator<Item = UserId>) -> Vec<UserId> {
+    let now = Instant::now();
+    let mut missing = vec![];
+
+    for user_id in user_ids {
+      let key = (guild_id, user_id);
+
+      if self.members.contains_key(&key) {
+        continue;
+      }
+
+      let claimed = match self.requested.entry(key) {
+        Entry::Occupied(mut requested) => {
+          let expired = now.saturating_duration_since(*requested.get()) >= REQUEST_TIMEOUT;
+
+          if expired {
+            requested.insert(now);
+          }
+
+          expired
+        }
+        Entry::Vacant(requested) => {
+          requested.insert(now);
+          true
+        }
+      };
+
+      if claimed {
+        missing.push(user_id);
+      }
+    }
+
+    missing
+  }
+
+  /// Forgets that these users were asked for, e.g. because the request couldn't be sent, so they can be asked for again.
+  pub(crate) fn release(&self, guild_id: GuildId, user_ids: &[UserId]) {
+    for user_id in user_ids {
+      self.requested.remove(&(guild_id, *user_id));
+    }
+  }
+}
+
+// the partial member on a message is everything but the user, which is the message's author
+fn member_from_partial(guild_id: GuildId, partial: &PartialMember, message: &Message) -> Member {
+  let mut member = Member::default();
+
+  member.guild_id = guild_id;
+  member.user = partial.user.clone().unwrap_or_else(|| message.author.clone());
+  member.nick = partial.nick.clone();
+  member.roles = partial.roles.clone();
+  member.joined_at = partial.joined_at;
+  member.premium_since = partial.premium_since;
+  member.deaf = partial.deaf;
+  member.mute = partial.mute;
+  member.pending = partial.pending;
+  member.permissions = partial.permissions;
+
+  member
+}
@@ -1,27 +1,311 @@
-use gpui::{div, IntoElement, ParentElement, Render, Styled, ViewContext};
-use serenity::all::Message;
+use std::{collections::HashSet, ops::Range};
+
+use gpui::{
+  div, px, rgb, AnyElement, ElementId, Font, Hsla, InteractiveText, IntoElement, ParentElement, Render, StrikethroughStyle, Styled, StyledText,
+  TextRun, UnderlineStyle, ViewContext, WeakView,
+};
+use scope_chat::{
+  content::{Block, Emoji, Inline, RichContent},
+  message::SendStatus,
+};
+
+#[cfg(target_os = "macos")]
+const MONOSPACE_FONT: &str = "Menlo";
+#[cfg(target_os = "windows")]
+const MONOSPACE_FONT: &str = "Consolas";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const MONOSPACE_FONT: &str = "DejaVu Sans Mono";
 
 #[derive(Clone, Debug)]
 pub struct DiscordMessageContent {
-  pub content: String,
-  pub is_pending: bool,
+  pub content: RichContent,
+  /// `None` for messages received from Discord
+  pub send_status: Option<SendStatus>,
+  /// The spoilers that have been clicked to show them, numbered in the order they're in
+  pub revealed_spoilers: HashSet<usize>,
 }
 
 impl DiscordMessageContent {
-  pub fn pending(content: String) -> DiscordMessageContent {
-    DiscordMessageContent { content, is_pending: true }
+  pub fn pending(content: RichContent, status: SendStatus) -> DiscordMessageContent {
+    DiscordMessageContent {
+      content,
+      send_status: Some(status),
+      revealed_spoilers: HashSet::new(),
+    }
   }
 
-  pub fn received(message: &Message) -> DiscordMessageContent {
+  pub fn received(content: RichContent) -> DiscordMessageContent {
     DiscordMessageContent {
-      content: message.content.clone(),
-      is_pending: false,
+      content,
+      send_status: None,
+      revealed_spoilers: HashSet::new(),
     }
   }
 }
 
 impl Render for DiscordMessageContent {
-  fn render(&mut self, _: &mut ViewContext<DiscordMessageContent>) -> impl IntoElement {
-    div().opacity(if self.is_pending { 0.25 } else { 1.0 }).child(self.content.clone())
+  fn render(&mut self, cx: &mut ViewContext<DiscordMessageContent>) -> impl IntoElement {
+    let opacity = match self.send_status {
+      None => 1.0,
+      Some(SendStatus::Failed(_)) => 0.75,
+      Some(SendStatus::Pending | SendStatus::Sent) => 0.25,
+    };
+
+    let color: Hsla = match self.send_status {
+      Some(SendStatus::Failed(_)) => rgb(0xF38BA8).into(),
+      _ => rgb(0xFFFFFF).into(),
+    };
+
+    let mut renderer = Renderer {
+      view: cx.view().downgrade(),
+      font: cx.text_style().font(),
+      color,
+      revealed_spoilers: self.revealed_spoilers.clone(),
+      spoilers: 0,
+      texts: 0,
+    };
+
+    div().flex().flex_col().opacity(opacity).text_color(color).children(renderer.blocks(&self.content.blocks))
+  }
+}
+
+// what clicking part of a text does
+enum Click {
+  OpenUrl(String),
+  RevealSpoiler(usize),
+}
+
+// plain text in `font`, for `StyledRuns::push` to set the length of
+fn text_run(font: &Font, color: Hsla) -> TextRun {
+  TextRun {
+    len: 0,
+    font: font.clone(),
+    color,
+    background_color: None,
+    underline: None,
+    strikethrough: None,
+  }
+}
+
+// a text being put together from inlines, as runs of differently styled text
+#[derive(Default)]
+struct StyledRuns {
+  text: String,
+  runs: Vec<TextRun>,
+  clicks: Vec<(Range<usize>, Click)>,
+}
+
+impl StyledRuns {
+  // returns where `text` ended up
+  fn push(&mut self, text: &str, run: TextRun) -> Range<usize> {
+    let start = self.text.len();
+
+    self.text.push_str(text);
+    self.runs.push(TextRun { len: text.len(), ..run });
+
+    start..self.text.len()
+  }
+}
+
+struct Renderer {
+  view: WeakView<DiscordMessageContent>,
+  font: Font,
+  color: Hsla,
+  revealed_spoilers: HashSet<usize>,
+  // how many spoilers and texts there are before what's being rendered, which is what they're numbered by
+  spoilers: usize,
+  texts: usize,
+}
+
+impl Renderer {
+  fn blocks(&mut self, blocks: &[Block]) -> Vec<AnyElement> {
+    blocks.iter().map(|block| self.block(block)).collect()
+  }
+
+  fn block(&mut self, block: &Block) -> AnyElement {
+    match block {
+      Block::Paragraph(content) => self.text(content, self.font.clone(), self.color).into_any_element(),
+      Block::Heading { level, content } => {
+        let heading = div().mt_1().child(self.text(content, self.font.bold(), self.color));
+
+        match level {
+          1 => heading.text_2xl(),
+          2 => heading.text_xl(),
+          _ => heading.text_lg(),
+        }
+        .into_any_element()
+      }
+      Block::Subtext(content) => div().text_xs().child(self.text(content, self.font.clone(), rgb(0xAFBAC7).into())).into_any_element(),
+      Block::CodeBlock { code, .. } => div()
+        .my_1()
+        .p_2()
+        .rounded_md()
+        .border_1()
+        .border_color(rgb(0x3F4248))
+        .bg(rgb(0x2B2D31))
+        .font_family(MONOSPACE_FONT)
+        .text_sm()
+        .child(code.clone())
+        .into_any_element(),
+      Block::Quote(blocks) => {
+        div().flex().flex_col().pl_3().border_l_4().border_color(rgb(0x4E5058)).children(self.blocks(blocks)).into_any_element()
+      }
+      Block::List(list) => div()
+        .flex()
+        .flex_col()
+        .children(list.items.iter().enumerate().map(|(i, item)| {
+          let marker = match list.start {
+            Some(start) => format!("{}.", start + i as u64),
+            None => "•".to_owned(),
+          };
+
+          div()
+            .flex()
+            .flex_row()
+            .gap_2()
+            .child(div().flex_shrink_0().child(marker))
+            .child(div().flex().flex_col().min_w_0().children(self.blocks(item)))
+        }))
+        .into_any_element(),
+    }
+  }
+
+  fn text(&mut self, content: &[Inline], font: Font, color: Hsla) -> impl IntoElement {
+    let mut runs = StyledRuns::default();
+
+    self.inlines(content, &mut runs, &font, color);
+
+    // an empty line still takes up a line
+    if runs.text.is_empty() {
+      runs.push(" ", text_run(&font, color));
+    }
+
+    let id = ElementId::NamedInteger("message-text".into(), self.texts);
+    self.texts += 1;
+
+    let (ranges, clicks): (Vec<_>, Vec<_>) = runs.clicks.into_iter().unzip();
+    let view = self.view.clone();
+
+    InteractiveText::new(id, StyledText::new(runs.text).with_runs(runs.runs)).on_click(ranges, move |index, cx| match &clicks[index] {
+      Click::OpenUrl(url) => cx.open_url(url),
+      Click::RevealSpoiler(spoiler) => {
+        let spoiler = *spoiler;
+
+        let _ = view.update(cx, |content, cx| {
+          content.revealed_spoilers.insert(spoiler);
+          cx.notify();
+        });
+      }
+    })
+  }
+
+  fn inlines(&mut self, content: &[Inline], runs: &mut StyledRuns, font: &Font, color: Hsla) {
+    for inline in content {
+      match inline {
+        Inline::Text(span) => {
+          let mut font = font.clone();
+
+          if span.style.bold {
+            font = font.bold();
+          }
+
+          if span.style.italic {
+            font = font.italic();
+          }
+
+          let run = TextRun {
+            underline: span.style.underline.then(|| UnderlineStyle {
+              thickness: px(1.),
+              ..Default::default()
+            }),
+            strikethrough: span.style.strikethrough.then(|| StrikethroughStyle {
+              thickness: px(1.),
+              ..Default::default()
+            }),
+            ..text_run(&font, color)
+          };
+
+          runs.push(&span.text, run);
+        }
+        Inline::Code(code) => {
+          let font = Font {
+            family: MONOSPACE_FONT.into(),
+            ..font.clone()
+          };
+
+          runs.push(
+            code,
+            TextRun {
+              background_color: Some(rgb(0x2B2D31).into()),
+              ..text_run(&font, color)
+            },
+          );
+        }
+        Inline::Link { url, content } => {
+          let start = runs.text.len();
+
+          self.inlines(content, runs, font, rgb(0x89B4FA).into());
+
+          // hidden spoilers in the link were added first, so they're revealed before the link can be clicked
+          runs.clicks.push((start..runs.text.len(), Click::OpenUrl(url.clone())));
+        }
+        Inline::Mention(mention) => {
+          runs.push(
+            &mention.label(),
+            TextRun {
+              background_color: Some(rgb(0x3C4270).into()),
+              ..text_run(font, rgb(0xC9CDFB).into())
+            },
+          );
+        }
+        Inline::Emoji(Emoji::Unicode(emoji)) => {
+          runs.push(emoji, text_run(font, color));
+        }
+        Inline::Emoji(Emoji::Custom { name, .. }) => {
+          runs.push(&format!(":{}:", name), text_run(font, rgb(0xAFBAC7).into()));
+        }
+        Inline::Timestamp { time, format } => {
+          runs.push(
+            &format.format(*time),
+            TextRun {
+              background_color: Some(rgb(0x3F4248).into()),
+              ..text_run(font, color)
+            },
+          );
+        }
+        Inline::Spoiler(content) => {
+          let spoiler = self.spoilers;
+          let start = runs.text.len();
+          let first_run = runs.runs.len();
+
+          self.spoilers += 1;
+          self.inlines(content, runs, font, color);
+
+          if self.revealed_spoilers.contains(&spoiler) {
+            for run in &mut runs.runs[first_run..] {
+              run.background_color = run.background_color.or(Some(rgb(0x3F4248).into()));
+            }
+
+            continue;
+          }
+
+          // everything in it is hidden behind a block of the same colour, and can't be clicked until it's revealed
+          let hidden: Hsla = rgb(0x1E1F22).into();
+
+          for run in &mut runs.runs[first_run..] {
+            run.color = hidden;
+            run.background_color = Some(hidden);
+            run.underline = None;
+            run.strikethrough = None;
+          }
+
+          runs.clicks.retain(|(range, _)| range.start < start);
+          runs.clicks.push((start..runs.text.len(), Click::RevealSpoiler(spoiler)));
+        }
+        Inline::LineBreak => {
+          runs.push("\n", text_run(font, color));
+        }
+      }
+    }
   }
 }
@@ -4,10 +4,16 @@ use author::DiscordMessageAuthor;
 use chrono::{DateTime, Utc};
 use content::DiscordMessageContent;
 use gpui::{View, VisualContext, WindowContext};
-use scope_chat::{async_list::AsyncListItem, message::Message};
-use serenity::all::{ModelError, Nonce};
+use scope_backend_cache::persistent::PersistentItem;
+use scope_chat::{
+  async_list::AsyncListItem,
+  content::{MentionKind, RichContent},
+  message::{Message, SendStatus},
+};
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, Nonce, RoleId, UserId};
 
-use crate::{client::DiscordClient, snowflake::Snowflake};
+use crate::{client::DiscordClient, markdown, snowflake::Snowflake};
 
 pub mod author;
 pub mod content;
@@ -19,6 +25,7 @@ pub enum DiscordMessageData {
     content: String,
     sent_time: DateTime<Utc>,
     list_item_id: Snowflake,
+    status: SendStatus,
   },
   Received(Arc<serenity::model::channel::Message>, Option<Arc<serenity::model::guild::Member>>),
 }
@@ -32,24 +39,6 @@ pub struct DiscordMessage {
 }
 
 impl DiscordMessage {
-  pub async fn load_serenity(client: Arc<DiscordClient>, msg: Arc<serenity::model::channel::Message>) -> Self {
-    let channel = Arc::new(msg.channel(client.discord()).await.unwrap());
-    let member = match msg.member(client.discord()).await {
-      Ok(v) => Ok(Some(Arc::new(v))),
-      Err(serenity::Error::Model(ModelError::ItemMissing)) => Ok(None),
-      Err(e) => Err(e),
-    }
-    .unwrap();
-
-    Self {
-      client,
-      channel,
-      data: DiscordMessageData::Received(msg, member),
-
-      content: OnceLock::new(),
-    }
-  }
-
   pub fn from_serenity(
     client: Arc<DiscordClient>,
     msg: Arc<serenity::model::channel::Message>,
@@ -64,6 +53,36 @@ impl DiscordMessage {
       content: OnceLock::new(),
     }
   }
+
+  // the names of the users, channels and roles a received message mentions, as far as they're known
+  fn name_mentions(&self, content: &mut RichContent, message: &serenity::model::channel::Message) {
+    let discord = self.client.discord();
+    let cache = discord.as_ref().map(|discord| &discord.cache);
+
+    content.for_each_mention_mut(|mention| {
+      let Ok(id) = mention.id.parse::<u64>() else {
+        return;
+      };
+
+      mention.name = match mention.kind {
+        MentionKind::User => {
+          let member = message.guild_id.and_then(|guild_id| self.client.members().get(guild_id, UserId::new(id)));
+
+          match member {
+            Some(member) => Some(member.display_name().to_owned()),
+            None => message.mentions.iter().find(|user| user.id.get() == id).map(|user| user.display_name().to_owned()),
+          }
+        }
+        MentionKind::Channel => cache.and_then(|cache| cache.channel(ChannelId::new(id)).map(|channel| channel.name.clone())),
+        MentionKind::Role => cache.and_then(|cache| {
+          let guild = cache.guild(message.guild_id?)?;
+
+          guild.roles.get(&RoleId::new(id)).map(|role| role.name.clone())
+        }),
+        MentionKind::Everyone | MentionKind::Here => None,
+      };
+    });
+  }
 }
 
 enum NonceState<'r> {
@@ -129,8 +148,8 @@ impl Message for DiscordMessage {
       .content
       .get_or_init(|| {
         let content = match &self.data {
-          DiscordMessageData::Pending { content, .. } => DiscordMessageContent::pending(content.clone()),
-          DiscordMessageData::Received(message, _) => DiscordMessageContent::received(message),
+          DiscordMessageData::Pending { status, .. } => DiscordMessageContent::pending(self.get_rich_content(), status.clone()),
+          DiscordMessageData::Received(..) => DiscordMessageContent::received(self.get_rich_content()),
         };
 
         cx.new_view(|_cx| content)
@@ -138,6 +157,19 @@ impl Message for DiscordMessage {
       .clone()
   }
 
+  fn get_rich_content(&self) -> RichContent {
+    match &self.data {
+      DiscordMessageData::Pending { content, .. } => markdown::parse(content),
+      DiscordMessageData::Received(message, _) => {
+        let mut content = markdown::parse(&message.content);
+
+        self.name_mentions(&mut content, message);
+
+        content
+      }
+    }
+  }
+
   fn get_identifier(&self) -> Option<Snowflake> {
     match &self.data {
       DiscordMessageData::Received(message, _) => Some(message.id.into()),
@@ -167,6 +199,40 @@ impl Message for DiscordMessage {
       DiscordMessageData::Received(message, _) => DateTime::from_timestamp_millis(message.timestamp.timestamp_millis()),
     }
   }
+
+  fn get_send_status(&self) -> SendStatus {
+    match &self.data {
+      DiscordMessageData::Pending { status, .. } => status.clone(),
+      DiscordMessageData::Received(..) => SendStatus::Sent,
+    }
+  }
+
+  fn with_send_status(&self, status: SendStatus) -> Self {
+    let data = match &self.data {
+      DiscordMessageData::Pending {
+        nonce,
+        content,
+        sent_time,
+        list_item_id,
+        ..
+      } => DiscordMessageData::Pending {
+        nonce: nonce.clone(),
+        content: content.clone(),
+        sent_time: *sent_time,
+        list_item_id: *list_item_id,
+        status,
+      },
+      DiscordMessageData::Received(..) => return self.clone(),
+    };
+
+    // the content view is rendered from the status, so it has to be rebuilt
+    Self {
+      client: self.client.clone(),
+      channel: self.channel.clone(),
+      data,
+      content: OnceLock::new(),
+    }
+  }
 }
 
 impl AsyncListItem for DiscordMessage {
@@ -179,3 +245,33 @@ impl AsyncListItem for DiscordMessage {
     }
   }
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct StoredMessage {
+  message: serenity::model::channel::Message,
+  member: Option<serenity::model::guild::Member>,
+}
+
+impl PersistentItem for DiscordMessage {
+  type Stored = StoredMessage;
+  type Context = (Arc<DiscordClient>, Arc<serenity::model::channel::Channel>);
+
+  fn to_stored(&self) -> Option<StoredMessage> {
+    match &self.data {
+      DiscordMessageData::Received(message, member) => Some(StoredMessage {
+        message: (**message).clone(),
+        member: member.as_deref().cloned(),
+      }),
+      DiscordMessageData::Pending { .. } => None,
+    }
+  }
+
+  fn from_stored(stored: StoredMessage, (client, channel): &Self::Context) -> Option<Self> {
+    Some(DiscordMessage::from_serenity(
+      client.clone(),
+      Arc::new(stored.message),
+      channel.clone(),
+      stored.member.map(Arc::new),
+    ))
+  }
+}
@@ -0,0 +1,410 @@
+use std::{
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+  },
+};
+
+use scope_chat::{
+  channel::ChannelEvent,
+  error::{ChatError, ChatErrorKind},
+};
+use serenity::{
+  all::{
+    Channel, ChannelId, ChannelType, GuildChannel, GuildId, Member, Message, MessageId, MessagePagination, Nonce, PrivateChannel, Timestamp, User,
+    UserId,
+  },
+  async_trait,
+};
+use tokio::sync::broadcast;
+
+use crate::{api::DiscordApi, client::DiscordClient, snowflake::Snowflake};
+
+/// Gateway events the mock emits, standing in for the ones Discord would send.
+#[derive(Clone, Debug)]
+pub enum MockEvent {
+  MessageCreate(Message),
+  /// Only the fields that changed are sent, like Discord does when the message isn't in serenity's cache.
+  MessageUpdate(ChannelId, MessageId, String),
+  MessageDelete(ChannelId, MessageId),
+  MessageDeleteBulk(ChannelId, Vec<MessageId>),
+  /// The answer to `request_guild_members`: the members that were found, and the users that weren't.
+  GuildMembersChunk(GuildId, Vec<Member>, Vec<UserId>),
+  /// The gateway connection was lost and resumed, so any events in between were missed.
+  Resumed,
+}
+
+struct MockChannel {
+  channel: Channel,
+  // oldest first, like the channel itself
+  messages: Vec<Message>,
+}
+
+/// A local stand-in for Discord, seeded with fixture channels and messages.
+///
+/// It answers the REST endpoints in `DiscordApi` the way Discord does (including paging order), and
+/// messages sent through it are echoed back as gateway events, nonce included.
+pub struct MockDiscord {
+  user: User,
+  channels: Mutex<HashMap<ChannelId, MockChannel>>,
+  events: broadcast::Sender<MockEvent>,
+  members: Mutex<HashMap<(GuildId, UserId), Member>>,
+  requests: AtomicUsize,
+  member_requests: AtomicUsize,
+  failure: Mutex<Option<ChatError>>,
+}
+
+impl Default for MockDiscord {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl MockDiscord {
+  pub fn new() -> Self {
+    let (events, _) = broadcast::channel(16);
+
+    MockDiscord {
+      user: Self::user(1, "scope"),
+      channels: Mutex::new(HashMap::new()),
+      events,
+      members: Mutex::new(HashMap::new()),
+      requests: AtomicUsize::new(0),
+      member_requests: AtomicUsize::new(0),
+      failure: Mutex::new(None),
+    }
+  }
+
+  pub fn user(id: u64, name: &str) -> User {
+    let mut user = User::default();
+
+    user.id = UserId::new(id);
+    user.name = name.to_owned();
+
+    user
+  }
+
+  /// The user the client is logged in as.
+  pub fn own_user(&self) -> &User {
+    &self.user
+  }
+
+  pub fn add_direct_message_channel(&self, channel_id: u64, recipient: User) -> ChannelId {
+    let mut channel = PrivateChannel::default();
+
+    channel.id = ChannelId::new(channel_id);
+    channel.kind = ChannelType::Private;
+    channel.recipient = recipient;
+
+    self.channels.lock().unwrap().insert(
+      channel.id,
+      MockChannel {
+        channel: Channel::Private(channel),
+        messages: vec![],
+      },
+    );
+
+    ChannelId::new(channel_id)
+  }
+
+  pub fn add_guild_channel(&self, channel_id: u64, guild_id: u64) -> ChannelId {
+    let mut channel = GuildChannel::default();
+
+    channel.id = ChannelId::new(channel_id);
+    channel.guild_id = GuildId::new(guild_id);
+    channel.kind = ChannelType::Text;
+
+    self.channels.lock().unwrap().insert(
+      channel.id,
+      MockChannel {
+        channel: Channel::Guild(channel),
+        messages: vec![],
+      },
+    );
+
+    ChannelId::new(channel_id)
+  }
+
+  pub fn add_member(&self, guild_id: u64, user: User, nick: &str) -> Member {
+    let mut member = Member::default();
+
+    member.guild_id = GuildId::new(guild_id);
+    member.user = user;
+    member.nick = Some(nick.to_owned());
+
+    self.members.lock().unwrap().insert((member.guild_id, member.user.id), member.clone());
+
+    member
+  }
+
+  /// Adds a message to the bottom of a channel, without emitting an event for it.
+  pub fn add_message(&self, channel_id: ChannelId, message_id: u64, author: User, content: &str) -> Message {
+    self.push_message(channel_id, message_id, author, content, None)
+  }
+
+  fn push_message(&self, channel_id: ChannelId, message_id: u64, author: User, content: &str, nonce: Option<Nonce>) -> Message {
+    let mut message = Message::default();
+
+    message.id = MessageId::new(message_id);
+    message.channel_id = channel_id;
+    message.author = author;
+    message.content = content.to_owned();
+    message.nonce = nonce;
+    // a minute apart, so consecutive messages group together
+    message.timestamp = Timestamp::from_unix_timestamp(1_700_000_000 + (message_id as i64) * 60).unwrap();
+
+    let mut channels = self.channels.lock().unwrap();
+    let channel = channels.get_mut(&channel_id).expect("Add the channel before its messages");
+
+    channel.messages.push(message.clone());
+
+    match &mut channel.channel {
+      Channel::Private(channel) => channel.last_message_id = Some(message.id),
+      Channel::Guild(channel) => channel.last_message_id = Some(message.id),
+      _ => unreachable!(),
+    }
+
+    message
+  }
+
+  /// Fills a direct message channel with `count` messages from its recipient, with consecutive identifiers starting
+  /// at `first_id`.
+  pub fn seed_messages(&self, channel_id: ChannelId, first_id: u64, count: u64) -> Vec<MessageId> {
+    let author = match &self.channels.lock().unwrap().get(&channel_id).expect("Add the channel before its messages").channel {
+      Channel::Private(channel) => channel.recipient.clone(),
+      _ => panic!("Only direct message channels have an author to seed messages from"),
+    };
+
+    self.seed_messages_from(channel_id, first_id, count, &[author])
+  }
+
+  /// Like `seed_messages`, but taking turns between `authors`.
+  pub fn seed_messages_from(&self, channel_id: ChannelId, first_id: u64, count: u64, authors: &[User]) -> Vec<MessageId> {
+    (first_id..first_id + count)
+      .zip(authors.iter().cycle())
+      .map(|(id, author)| self.add_message(channel_id, id, author.clone(), &format!("Message {}", id)).id)
+      .collect()
+  }
+
+  /// Deletes a message and emits the matching event.
+  pub fn delete_message(&self, channel_id: ChannelId, message_id: MessageId) {
+    if let Some(channel) = self.channels.lock().unwrap().get_mut(&channel_id) {
+      channel.messages.retain(|message| message.id != message_id);
+    }
+
+    let _ = self.events.send(MockEvent::MessageDelete(channel_id, message_id));
+  }
+
+  /// Changes the content of a message and emits the matching event.
+  pub fn edit_message(&self, channel_id: ChannelId, message_id: MessageId, content: &str) {
+    if let Some(channel) = self.channels.lock().unwrap().get_mut(&channel_id) {
+      for message in channel.messages.iter_mut().filter(|message| message.id == message_id) {
+        message.content = content.to_owned();
+      }
+    }
+
+    let _ = self.events.send(MockEvent::MessageUpdate(channel_id, message_id, content.to_owned()));
+  }
+
+  /// Deletes several messages at once and emits the matching event.
+  pub fn delete_messages(&self, channel_id: ChannelId, message_ids: Vec<MessageId>) {
+    if let Some(channel) = self.channels.lock().unwrap().get_mut(&channel_id) {
+      channel.messages.retain(|message| !message_ids.contains(&message.id));
+    }
+
+    let _ = self.events.send(MockEvent::MessageDeleteBulk(channel_id, message_ids));
+  }
+
+  /// Resumes the gateway connection, as if it had dropped. Messages added since, with `add_message`, were missed.
+  pub fn resume(&self) {
+    let _ = self.events.send(MockEvent::Resumed);
+  }
+
+  /// While set, every request fails with this error.
+  pub fn fail_requests(&self, error: Option<ChatError>) {
+    *self.failure.lock().unwrap() = error;
+  }
+
+  /// How many REST requests have been made, so tests can tell what was served from the cache.
+  pub fn request_count(&self) -> usize {
+    self.requests.load(Ordering::SeqCst)
+  }
+
+  /// How many times members have been asked for over the gateway, which `request_count` doesn't include.
+  pub fn member_request_count(&self) -> usize {
+    self.member_requests.load(Ordering::SeqCst)
+  }
+
+  pub fn subscribe(&self) -> broadcast::Receiver<MockEvent> {
+    self.events.subscribe()
+  }
+
+  fn request(&self) -> Result<(), ChatError> {
+    self.requests.fetch_add(1, Ordering::SeqCst);
+
+    match &*self.failure.lock().unwrap() {
+      Some(error) => Err(error.clone()),
+      None => Ok(()),
+    }
+  }
+
+  fn not_found(what: &str) -> ChatError {
+    ChatError::new(ChatErrorKind::NotFound, format!("Unknown {}", what))
+  }
+}
+
+#[async_trait]
+impl DiscordApi for MockDiscord {
+  async fn get_channel(&self, channel_id: ChannelId) -> Result<Channel, ChatError> {
+    self.request()?;
+
+    let channels = self.channels.lock().unwrap();
+    let channel = channels.get(&channel_id).ok_or_else(|| Self::not_found("Channel"))?;
+
+    Ok(channel.channel.clone())
+  }
+
+  async fn get_messages(&self, channel_id: ChannelId, target: Option<MessagePagination>, limit: u8) -> Result<Vec<Message>, ChatError> {
+    self.request()?;
+
+    let channels = self.channels.lock().unwrap();
+    let messages = &channels.get(&channel_id).ok_or_else(|| Self::not_found("Channel"))?.messages;
+    let limit = limit as usize;
+
+    let page: Vec<Message> = match target {
+      None => messages.iter().rev().take(limit).cloned().collect(),
+      Some(MessagePagination::Before(id)) => messages.iter().rev().filter(|message| message.id < id).take(limit).cloned().collect(),
+      // the messages closest to `id`, still returned newest first
+      Some(MessagePagination::After(id)) => {
+        let start = messages.partition_point(|message| message.id <= id);
+
+        messages[start..].iter().take(limit).rev().cloned().collect()
+      }
+      Some(MessagePagination::Around(id)) => {
+        let start = messages.partition_point(|message| message.id < id).saturating_sub(limit / 2);
+
+        messages.iter().skip(start).take(limit).rev().cloned().collect()
+      }
+      Some(_) => unimplemented!(),
+    };
+
+    Ok(page)
+  }
+
+  async fn get_message(&self, channel_id: ChannelId, message_id: MessageId) -> Result<Option<Message>, ChatError> {
+    self.request()?;
+
+    let channels = self.channels.lock().unwrap();
+    let messages = &channels.get(&channel_id).ok_or_else(|| Self::not_found("Channel"))?.messages;
+
+    Ok(messages.iter().find(|message| message.id == message_id).cloned())
+  }
+
+  async fn get_member(&self, guild_id: GuildId, user_id: UserId) -> Result<Option<Member>, ChatError> {
+    self.request()?;
+
+    Ok(self.members.lock().unwrap().get(&(guild_id, user_id)).cloned())
+  }
+
+  async fn send_message(&self, channel_id: ChannelId, content: String, nonce: String) -> Result<(), ChatError> {
+    self.request()?;
+
+    let next_id = {
+      let channels = self.channels.lock().unwrap();
+      let messages = &channels.get(&channel_id).ok_or_else(|| Self::not_found("Channel"))?.messages;
+
+      messages.last().map(|message| message.id.get() + 1).unwrap_or(1)
+    };
+
+    let message = self.push_message(channel_id, next_id, self.user.clone(), &content, Some(Nonce::String(nonce)));
+
+    let _ = self.events.send(MockEvent::MessageCreate(message));
+
+    Ok(())
+  }
+
+  async fn get_dm_channels(&self) -> Result<Vec<PrivateChannel>, ChatError> {
+    self.request()?;
+
+    Ok(
+      self
+        .channels
+        .lock()
+        .unwrap()
+        .values()
+        .filter_map(|channel| match &channel.channel {
+          Channel::Private(channel) => Some(channel.clone()),
+          _ => None,
+        })
+        .collect(),
+    )
+  }
+
+  async fn request_guild_members(&self, guild_id: GuildId, user_ids: Vec<UserId>) -> Result<(), ChatError> {
+    self.member_requests.fetch_add(1, Ordering::SeqCst);
+
+    let mut found = vec![];
+    let mut not_found = vec![];
+
+    for user_id in user_ids {
+      match self.members.lock().unwrap().get(&(guild_id, user_id)) {
+        Some(member) => found.push(member.clone()),
+        None => not_found.push(user_id),
+      }
+    }
+
+    // answered over the gateway, like Discord would
+    let _ = self.events.send(MockEvent::GuildMembersChunk(guild_id, found, not_found));
+
+    Ok(())
+  }
+}
+
+impl DiscordClient {
+  /// A client backed by `mock` instead of Discord. No gateway connection is made; the events the mock
+  /// emits are handled as if they had come from one. Must be called from within a tokio runtime.
+  pub fn mock(mock: Arc<MockDiscord>) -> Arc<DiscordClient> {
+    let client = DiscordClient::with_api(mock.clone(), mock.own_user().clone());
+
+    let mut events = mock.subscribe();
+    let gateway_client = Arc::downgrade(&client);
+
+    tokio::spawn(async move {
+      while let Ok(event) = events.recv().await {
+        let Some(client) = gateway_client.upgrade() else {
+          break;
+        };
+
+        match event {
+          MockEvent::MessageCreate(message) => client.message_created(message).await,
+          MockEvent::GuildMembersChunk(guild_id, members, not_found) => client.members_chunk(guild_id, members, not_found).await,
+          MockEvent::MessageUpdate(channel_id, message_id, content) => {
+            let event = serde_json::from_value(serde_json::json!({
+              "id": message_id,
+              "channel_id": channel_id,
+              "content": content,
+            }))
+            .expect("A message update needs only its identifiers");
+
+            client.message_updated(None, event).await
+          }
+          MockEvent::MessageDelete(channel_id, message_id) => {
+            client.dispatch_channel_event(channel_id, ChannelEvent::Deleted(message_id.into())).await
+          }
+          MockEvent::MessageDeleteBulk(channel_id, message_ids) => {
+            client
+              .dispatch_channel_event(
+                channel_id,
+                ChannelEvent::BulkDeleted(message_ids.into_iter().map(Snowflake::from).collect()),
+              )
+              .await
+          }
+          MockEvent::Resumed => client.connected().await,
+        }
+      }
+    });
+
+    client
+  }
+}
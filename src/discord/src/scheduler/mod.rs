@@ -0,0 +1,344 @@
+pub mod tests;
+
+use std::{
+  cmp::Ordering,
+  collections::{BinaryHeap, HashMap},
+  future::Future,
+  hash::Hash,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use scope_chat::error::{ChatError, ChatErrorKind};
+use tokio::sync::{oneshot, watch};
+
+// serenity already queues requests per route, this only keeps background work from crowding out the user
+const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+// when Discord says a request was rate limited without saying for how long, it's retried after this, then twice as
+// long each time after that
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RATE_LIMIT_RETRIES: usize = 3;
+
+/// How urgent a request is. When requests have to wait their turn, more urgent ones go first.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Priority {
+  /// Nobody is waiting on it yet, like catching up with messages sent while the app was closed
+  Prefetch,
+  /// Something the user is waiting to see, like the page of messages they scrolled to
+  Load,
+  /// Something the user did, like sending a message
+  Send,
+}
+
+/// A group of requests that can't be made again until `retry_at`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RateLimit {
+  /// Which requests are limited, usually a route like `channels/123/messages`
+  pub bucket: String,
+  pub retry_at: Instant,
+}
+
+impl RateLimit {
+  pub fn retry_in(&self) -> Duration {
+    self.retry_at.saturating_duration_since(Instant::now())
+  }
+}
+
+/// Decides when each request to Discord is made: only so many at once, the most urgent first, and none in a bucket
+/// that is rate limited.
+pub struct RequestScheduler {
+  max_in_flight: usize,
+  slots: Mutex<Slots>,
+  rate_limits: Mutex<HashMap<String, Instant>>,
+  rate_limit_changes: watch::Sender<Vec<RateLimit>>,
+}
+
+#[derive(Default)]
+struct Slots {
+  in_flight: usize,
+  waiting: BinaryHeap<Waiter>,
+  next_sequence: u64,
+}
+
+struct Waiter {
+  priority: Priority,
+  // requests with the same priority go in the order they were made
+  sequence: u64,
+  ready: oneshot::Sender<()>,
+}
+
+impl PartialEq for Waiter {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+// the greatest waiter is the one to go next
+impl Ord for Waiter {
+  fn cmp(&self, other: &Self) -> Ordering {
+    self.priority.cmp(&other.priority).then(other.sequence.cmp(&self.sequence))
+  }
+}
+
+/// A request's turn. The next one goes once this is dropped.
+pub struct Permit<'s> {
+  scheduler: &'s RequestScheduler,
+}
+
+impl Drop for Permit<'_> {
+  fn drop(&mut self) {
+    self.scheduler.release();
+  }
+}
+
+// gives back a slot that was handed over to a request that stopped waiting for it
+struct Waiting<'s> {
+  scheduler: &'s RequestScheduler,
+  receiver: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for Waiting<'_> {
+  fn drop(&mut self) {
+    if let Some(mut receiver) = self.receiver.take() {
+      receiver.close();
+
+      if receiver.try_recv().is_ok() {
+        self.scheduler.release();
+      }
+    }
+  }
+}
+
+impl Default for RequestScheduler {
+  fn default() -> Self {
+    Self::new(DEFAULT_MAX_IN_FLIGHT)
+  }
+}
+
+impl RequestScheduler {
+  pub fn new(max_in_flight: usize) -> Self {
+    assert!(max_in_flight > 0);
+
+    RequestScheduler {
+      max_in_flight,
+      slots: Mutex::new(Slots::default()),
+      rate_limits: Mutex::new(HashMap::new()),
+      rate_limit_changes: watch::Sender::new(vec![]),
+    }
+  }
+
+  /// Waits for a turn to make a request.
+  pub async fn acquire(&self, priority: Priority) -> Permit<'_> {
+    let receiver = {
+      let mut slots = self.slots.lock().unwrap();
+
+      if slots.in_flight < self.max_in_flight && slots.waiting.is_empty() {
+        slots.in_flight += 1;
+
+        return Permit { scheduler: self };
+      }
+
+      let (ready, receiver) = oneshot::channel();
+      let sequence = slots.next_sequence;
+
+      slots.next_sequence += 1;
+      slots.waiting.push(Waiter { priority, sequence, ready });
+
+      receiver
+    };
+
+    let mut waiting = Waiting {
+      scheduler: self,
+      receiver: Some(receiver),
+    };
+
+    // the slot is handed over by whoever releases it, so it's already counted as in flight
+    let _ = waiting.receiver.as_mut().unwrap().await;
+
+    waiting.receiver = None;
+
+    Permit { scheduler: self }
+  }
+
+  fn release(&self) {
+    let mut slots = self.slots.lock().unwrap();
+
+    while let Some(waiter) = slots.waiting.pop() {
+      // a waiter that stopped waiting doesn't get the slot
+      if waiter.ready.send(()).is_ok() {
+        return;
+      }
+    }
+
+    slots.in_flight -= 1;
+  }
+
+  /// How many requests are waiting for a turn.
+  pub fn waiting(&self) -> usize {
+    self.slots.lock().unwrap().waiting.len()
+  }
+
+  /// Makes a request when it's its turn, retrying it a few times if it's rate limited.
+  pub async fn run<T, F, Fut>(&self, bucket: &str, priority: Priority, mut request: F) -> Result<T, ChatError>
+  where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ChatError>>,
+  {
+    let mut retries = 0;
+    let mut delay = FIRST_RETRY_DELAY;
+
+    loop {
+      if let Some(retry_at) = self.limited_until(bucket) {
+        tokio::time::sleep(retry_at.saturating_duration_since(Instant::now())).await;
+      }
+
+      let result = {
+        let _permit = self.acquire(priority).await;
+
+        request().await
+      };
+
+      match result {
+        Err(e) if e.kind() == ChatErrorKind::RateLimited && retries < MAX_RATE_LIMIT_RETRIES => {
+          // when the bucket resets, if Discord said so, otherwise a guess
+          let retry_at = self.limited_until(bucket).unwrap_or_else(|| {
+            let retry_at = Instant::now() + delay;
+
+            self.limit(bucket, retry_at);
+            delay *= 2;
+
+            retry_at
+          });
+
+          log::warn!(
+            "Rate limited on {}, retrying in {:?}",
+            bucket,
+            retry_at.saturating_duration_since(Instant::now())
+          );
+
+          retries += 1;
+        }
+        result => return result,
+      }
+    }
+  }
+
+  /// Holds back requests in `bucket` until `retry_at`, e.g. when Discord says that's when the bucket resets.
+  pub fn limit(&self, bucket: &str, retry_at: Instant) {
+    self.rate_limits.lock().unwrap().insert(bucket.to_owned(), retry_at);
+
+    self.rate_limit_changes.send_replace(self.rate_limits());
+  }
+
+  /// Follows the buckets that are rate limited, sent again each time one is limited. Nothing is sent when a rate
+  /// limit runs out, `RateLimit::retry_in` says when it does.
+  pub fn subscribe(&self) -> watch::Receiver<Vec<RateLimit>> {
+    self.rate_limit_changes.subscribe()
+  }
+
+  fn limited_until(&self, bucket: &str) -> Option<Instant> {
+    self.rate_limits.lock().unwrap().get(bucket).copied().filter(|retry_at| *retry_at > Instant::now())
+  }
+
+  /// The buckets that are rate limited right now.
+  pub fn rate_limits(&self) -> Vec<RateLimit> {
+    let now = Instant::now();
+    let mut rate_limits = self.rate_limits.lock().unwrap();
+
+    rate_limits.retain(|_, retry_at| *retry_at > now);
+
+    rate_limits
+      .iter()
+      .map(|(bucket, retry_at)| RateLimit {
+        bucket: bucket.clone(),
+        retry_at: *retry_at,
+      })
+      .collect()
+  }
+}
+
+// everyone waiting on a request that's in flight
+type Followers<T> = Vec<oneshot::Sender<Result<T, ChatError>>>;
+
+/// Shares the result of a request with everyone who asks for the same thing while it's in flight, so it's only made once.
+pub struct Coalescer<K, T> {
+  in_flight: Mutex<HashMap<K, Followers<T>>>,
+}
+
+impl<K, T> Default for Coalescer<K, T> {
+  fn default() -> Self {
+    Coalescer {
+      in_flight: Mutex::new(HashMap::new()),
+    }
+  }
+}
+
+impl<K: Hash + Eq + Clone, T: Clone> Coalescer<K, T> {
+  pub async fn run<Fut: Future<Output = Result<T, ChatError>>>(&self, key: K, request: impl FnOnce() -> Fut) -> Result<T, ChatError> {
+    let receiver = {
+      let mut in_flight = self.in_flight.lock().unwrap();
+
+      match in_flight.get_mut(&key) {
+        Some(waiting) => {
+          let (sender, receiver) = oneshot::channel();
+
+          waiting.push(sender);
+
+          Some(receiver)
+        }
+        None => {
+          in_flight.insert(key.clone(), vec![]);
+
+          None
+        }
+      }
+    };
+
+    if let Some(receiver) = receiver {
+      return receiver.await.unwrap_or_else(|_| Err(ChatError::new(ChatErrorKind::Other, "The request was cancelled")));
+    }
+
+    let mut leader = Leader {
+      coalescer: self,
+      key: Some(key),
+    };
+    let result = request().await;
+
+    for waiting in leader.finish() {
+      let _ = waiting.send(result.clone());
+    }
+
+    result
+  }
+}
+
+// makes sure a request that is dropped before it finishes doesn't leave everyone else waiting on it forever
+struct Leader<'c, K: Hash + Eq, T> {
+  coalescer: &'c Coalescer<K, T>,
+  key: Option<K>,
+}
+
+impl<K: Hash + Eq, T> Leader<'_, K, T> {
+  fn finish(&mut self) -> Followers<T> {
+    let key = self.key.take().unwrap();
+
+    self.coalescer.in_flight.lock().unwrap().remove(&key).unwrap_or_default()
+  }
+}
+
+impl<K: Hash + Eq, T> Drop for Leader<'_, K, T> {
+  fn drop(&mut self) {
+    // dropping the senders tells whoever was waiting that the request was cancelled
+    if let Some(key) = self.key.take() {
+      self.coalescer.in_flight.lock().unwrap().remove(&key);
+    }
+  }
+}
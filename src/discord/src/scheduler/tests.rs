@@ -0,0 +1,224 @@
+#[allow(unused_imports)]
+use std::{
+  future::Future,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use scope_chat::error::{ChatError, ChatErrorKind};
+
+#[allow(unused_imports)]
+use super::{Coalescer, Priority, RequestScheduler};
+
+#[allow(dead_code)]
+fn block_on<F: Future>(future: F) -> F::Output {
+  tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(future)
+}
+
+#[test]
+pub fn scheduler_runs_most_urgent_waiting_request_first() {
+  block_on(async {
+    let scheduler = Arc::new(RequestScheduler::new(1));
+    let order = Arc::new(Mutex::new(vec![]));
+
+    let first = scheduler.acquire(Priority::Load).await;
+
+    let handles = [Priority::Prefetch, Priority::Load, Priority::Send, Priority::Prefetch].map(|priority| {
+      let scheduler = scheduler.clone();
+      let order = order.clone();
+
+      tokio::spawn(async move {
+        let _permit = scheduler.acquire(priority).await;
+
+        order.lock().unwrap().push(priority);
+      })
+    });
+
+    while scheduler.waiting() < handles.len() {
+      tokio::task::yield_now().await;
+    }
+
+    drop(first);
+
+    for handle in handles {
+      handle.await.unwrap();
+    }
+
+    assert_eq!(
+      *order.lock().unwrap(),
+      vec![Priority::Send, Priority::Load, Priority::Prefetch, Priority::Prefetch]
+    );
+  });
+}
+
+#[test]
+pub fn scheduler_gives_up_slots_of_requests_that_stopped_waiting() {
+  block_on(async {
+    let scheduler = Arc::new(RequestScheduler::new(1));
+
+    let first = scheduler.acquire(Priority::Load).await;
+
+    let waiting = {
+      let scheduler = scheduler.clone();
+
+      tokio::spawn(async move {
+        let _permit = scheduler.acquire(Priority::Send).await;
+      })
+    };
+
+    while scheduler.waiting() == 0 {
+      tokio::task::yield_now().await;
+    }
+
+    waiting.abort();
+    let _ = waiting.await;
+
+    drop(first);
+
+    // the only slot is free again
+    drop(scheduler.acquire(Priority::Prefetch).await);
+  });
+}
+
+#[test]
+pub fn coalescer_makes_duplicate_requests_once() {
+  block_on(async {
+    let coalescer = Arc::new(Coalescer::<u64, u64>::default());
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let request = |coalescer: Arc<Coalescer<u64, u64>>, calls: Arc<AtomicUsize>, key: u64| {
+      tokio::spawn(async move {
+        coalescer
+          .run(key, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+
+            // stays in flight long enough for the others to join it
+            tokio::time::sleep(Duration::from_millis(10)).await;
+
+            Ok(key * 10)
+          })
+          .await
+      })
+    };
+
+    let handles = [1, 1, 2].map(|key| request(coalescer.clone(), calls.clone(), key));
+    let mut results = vec![];
+
+    for handle in handles {
+      results.push(handle.await.unwrap().unwrap());
+    }
+
+    assert_eq!(results, vec![10, 10, 20]);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // nothing is in flight anymore, so this is made again
+    request(coalescer.clone(), calls.clone(), 1).await.unwrap().unwrap();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+  });
+}
+
+#[test]
+pub fn coalescer_cancels_followers_of_dropped_request() {
+  block_on(async {
+    let coalescer = Arc::new(Coalescer::<u64, u64>::default());
+
+    let leader = {
+      let coalescer = coalescer.clone();
+
+      tokio::spawn(async move {
+        coalescer
+          .run(1, || async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+
+            Ok(1)
+          })
+          .await
+      })
+    };
+
+    tokio::task::yield_now().await;
+
+    let follower = {
+      let coalescer = coalescer.clone();
+
+      tokio::spawn(async move { coalescer.run(1, || async { Ok(2) }).await })
+    };
+
+    tokio::task::yield_now().await;
+
+    leader.abort();
+
+    assert!(follower.await.unwrap().is_err());
+
+    // and the next request for it is made as usual
+    assert_eq!(coalescer.run(1, || async { Ok(3) }).await.unwrap(), 3);
+  });
+}
+
+#[test]
+pub fn scheduler_reports_current_rate_limits() {
+  let scheduler = RequestScheduler::new(1);
+
+  scheduler.limit("channels/1/messages", Instant::now() + Duration::from_secs(60));
+  scheduler.limit("channels/2/messages", Instant::now() - Duration::from_secs(1));
+
+  let rate_limits = scheduler.rate_limits();
+
+  assert_eq!(rate_limits.len(), 1);
+  assert_eq!(rate_limits[0].bucket, "channels/1/messages");
+  assert!(rate_limits[0].retry_in() > Duration::from_secs(59));
+}
+
+#[test]
+pub fn scheduler_sends_rate_limits_as_buckets_are_limited() {
+  let scheduler = RequestScheduler::new(1);
+  let mut receiver = scheduler.subscribe();
+
+  assert!(receiver.borrow_and_update().is_empty());
+
+  scheduler.limit("channels/1/messages", Instant::now() + Duration::from_secs(60));
+
+  assert!(receiver.has_changed().unwrap());
+  assert_eq!(
+    receiver.borrow_and_update().iter().map(|rate_limit| rate_limit.bucket.as_str()).collect::<Vec<_>>(),
+    vec!["channels/1/messages"]
+  );
+}
+
+#[test]
+pub fn scheduler_retries_rate_limited_request_when_bucket_resets() {
+  block_on(async {
+    let scheduler = RequestScheduler::new(1);
+    let attempts = AtomicUsize::new(0);
+    let reset_in = Duration::from_millis(200);
+    let start = Instant::now();
+
+    let result = scheduler
+      .run("channels/1/messages", Priority::Load, || {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+
+        // Discord tells us when the bucket resets before the request fails
+        if attempt == 0 {
+          scheduler.limit("channels/1/messages", Instant::now() + reset_in);
+        }
+
+        async move {
+          match attempt {
+            0 => Err(ChatError::new(ChatErrorKind::RateLimited, "You are being rate limited")),
+            _ => Ok(attempt),
+          }
+        }
+      })
+      .await;
+
+    assert_eq!(result.unwrap(), 1);
+    assert!(start.elapsed() >= reset_in);
+    // sooner than guessing would have retried it
+    assert!(start.elapsed() < Duration::from_secs(1));
+  });
+}
@@ -0,0 +1,123 @@
+pub mod tests;
+
+use std::{fmt::Display, num::ParseIntError, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
+use serenity::all::{ChannelId, GuildId, MessageId, UserId};
+
+/// The first millisecond of 2015, which is when Discord starts counting snowflake timestamps from.
+pub const DISCORD_EPOCH: u64 = 1_420_070_400_000;
+
+const TIMESTAMP_SHIFT: u32 = 22;
+const WORKER_SHIFT: u32 = 17;
+const PROCESS_SHIFT: u32 = 12;
+
+// snowflakes start with their creation time, so they sort oldest first
+#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Copy, Debug)]
+pub struct Snowflake(pub u64);
+
+impl Snowflake {
+  /// A snowflake made now, with the rest of it random, so it sorts after everything that already exists.
+  pub fn random() -> Snowflake {
+    Snowflake(Snowflake::from_timestamp(Utc::now()).0 | (rand::random::<u64>() & ((1 << TIMESTAMP_SHIFT) - 1)))
+  }
+
+  /// The smallest snowflake made at `time`, for finding things by when they were made. Times before the Discord
+  /// epoch all give the smallest snowflake there is, and times too late to fit give the latest one.
+  pub fn from_timestamp(time: DateTime<Utc>) -> Snowflake {
+    let milliseconds = (time.timestamp_millis().max(0) as u64).saturating_sub(DISCORD_EPOCH).min(u64::MAX >> TIMESTAMP_SHIFT);
+
+    Snowflake(milliseconds << TIMESTAMP_SHIFT)
+  }
+
+  pub fn timestamp(&self) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(((self.0 >> TIMESTAMP_SHIFT) + DISCORD_EPOCH) as i64).unwrap()
+  }
+
+  pub fn worker_id(&self) -> u8 {
+    ((self.0 >> WORKER_SHIFT) & 0x1F) as u8
+  }
+
+  pub fn process_id(&self) -> u8 {
+    ((self.0 >> PROCESS_SHIFT) & 0x1F) as u8
+  }
+
+  /// Counts up for every snowflake the same process makes in the same millisecond.
+  pub fn increment(&self) -> u16 {
+    (self.0 & 0xFFF) as u16
+  }
+}
+
+impl Display for Snowflake {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    self.0.fmt(f)
+  }
+}
+
+impl FromStr for Snowflake {
+  type Err = ParseIntError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    s.parse().map(Snowflake)
+  }
+}
+
+// Discord sends snowflakes as strings, since they don't fit in a javascript number
+impl Serialize for Snowflake {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+impl<'de> Deserialize<'de> for Snowflake {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    deserializer.deserialize_any(SnowflakeVisitor)
+  }
+}
+
+struct SnowflakeVisitor;
+
+impl Visitor<'_> for SnowflakeVisitor {
+  type Value = Snowflake;
+
+  fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    formatter.write_str("a snowflake, as a string or an integer")
+  }
+
+  fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+    Ok(Snowflake(v))
+  }
+
+  fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+    u64::try_from(v).map(Snowflake).map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+  }
+
+  fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+    v.parse().map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+  }
+}
+
+impl From<UserId> for Snowflake {
+  fn from(value: UserId) -> Self {
+    Snowflake(value.get())
+  }
+}
+
+impl From<GuildId> for Snowflake {
+  fn from(value: GuildId) -> Self {
+    Snowflake(value.get())
+  }
+}
+
+impl From<ChannelId> for Snowflake {
+  fn from(value: ChannelId) -> Self {
+    Snowflake(value.get())
+  }
+}
+
+impl From<MessageId> for Snowflake {
+  fn from(value: MessageId) -> Self {
+    Snowflake(value.get())
+  }
+}
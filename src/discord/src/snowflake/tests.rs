@@ -0,0 +1,56 @@
+#[allow(unused_imports)]
+use chrono::{DateTime, TimeZone, Utc};
+
+#[allow(unused_imports)]
+use super::{Snowflake, DISCORD_EPOCH};
+
+// the example from Discord's API reference
+#[allow(dead_code)]
+const EXAMPLE: Snowflake = Snowflake(175928847299117063);
+
+#[test]
+pub fn snowflake_fields() {
+  assert_eq!(EXAMPLE.timestamp(), Utc.timestamp_millis_opt(1462015105796).unwrap());
+  assert_eq!(EXAMPLE.worker_id(), 1);
+  assert_eq!(EXAMPLE.process_id(), 0);
+  assert_eq!(EXAMPLE.increment(), 7);
+}
+
+#[test]
+pub fn snowflake_from_timestamp_sorts_before_everything_made_then() {
+  let made = Snowflake::from_timestamp(EXAMPLE.timestamp());
+
+  assert_eq!(made.timestamp(), EXAMPLE.timestamp());
+  assert!(made <= EXAMPLE);
+  assert!(Snowflake::from_timestamp(EXAMPLE.timestamp() + chrono::Duration::milliseconds(1)) > EXAMPLE);
+
+  assert_eq!(Snowflake::from_timestamp(DateTime::UNIX_EPOCH), Snowflake(0));
+  assert_eq!(Snowflake(0).timestamp().timestamp_millis() as u64, DISCORD_EPOCH);
+
+  // past what the timestamp bits can hold, the latest timestamp there is rather than wrapping around
+  let latest = Snowflake::from_timestamp(Utc.with_ymd_and_hms(9999, 1, 1, 0, 0, 0).unwrap());
+
+  assert_eq!(latest.0 >> 22, u64::MAX >> 22);
+  assert_eq!(latest.increment(), 0);
+  assert!(latest > Snowflake::from_timestamp(Utc::now()));
+}
+
+#[test]
+pub fn random_snowflakes_sort_after_existing_ones() {
+  assert!(Snowflake::random() > EXAMPLE);
+}
+
+#[test]
+pub fn snowflake_parses_and_prints() {
+  assert_eq!("175928847299117063".parse::<Snowflake>(), Ok(EXAMPLE));
+  assert!("not a snowflake".parse::<Snowflake>().is_err());
+  assert_eq!(EXAMPLE.to_string(), "175928847299117063");
+}
+
+#[test]
+pub fn snowflake_serde() {
+  assert_eq!(serde_json::to_string(&EXAMPLE).unwrap(), "\"175928847299117063\"");
+  assert_eq!(serde_json::from_str::<Snowflake>("\"175928847299117063\"").unwrap(), EXAMPLE);
+  assert_eq!(serde_json::from_str::<Snowflake>("175928847299117063").unwrap(), EXAMPLE);
+  assert!(serde_json::from_str::<Snowflake>("-1").is_err());
+}
@@ -0,0 +1,135 @@
+use std::{
+  future::Future,
+  sync::{Arc, OnceLock},
+};
+
+use chrono::Utc;
+use scope_chat::{
+  async_list::{AsyncList, AsyncListIndex, AsyncListResult},
+  channel::{Channel, ChannelEvent},
+  error::{ChatError, ChatErrorKind},
+  message::SendStatus,
+};
+use tokio::sync::broadcast;
+
+use crate::{
+  client::{unknown_channel, MemoryClient},
+  message::MemoryMessage,
+};
+
+pub struct MemoryChannel {
+  client: Arc<MemoryClient>,
+  id: u64,
+  receiver: broadcast::Receiver<ChannelEvent<MemoryMessage>>,
+}
+
+impl MemoryChannel {
+  pub(crate) fn new(client: Arc<MemoryClient>, id: u64, receiver: broadcast::Receiver<ChannelEvent<MemoryMessage>>) -> Self {
+    MemoryChannel { client, id, receiver }
+  }
+}
+
+impl Clone for MemoryChannel {
+  fn clone(&self) -> Self {
+    Self {
+      client: self.client.clone(),
+      id: self.id,
+      receiver: self.receiver.resubscribe(),
+    }
+  }
+}
+
+impl Channel for MemoryChannel {
+  type Message = MemoryMessage;
+  type Identifier = u64;
+
+  fn get_receiver(&self) -> broadcast::Receiver<ChannelEvent<MemoryMessage>> {
+    self.receiver.resubscribe()
+  }
+
+  fn pending_message(&self, content: String, nonce: String) -> MemoryMessage {
+    MemoryMessage {
+      id: None,
+      list_id: self.client.allocate_id(),
+      author: self.client.own_user().clone(),
+      content,
+      timestamp: Utc::now(),
+      nonce: Some(nonce),
+      status: SendStatus::Pending,
+      view: OnceLock::new(),
+    }
+  }
+
+  fn send_message(&self, message: &MemoryMessage) -> impl Future<Output = Result<(), ChatError>> + Send {
+    let client = self.client.clone();
+    let channel_id = self.id;
+    let unsent = message.id.is_none().then(|| (message.content.clone(), message.nonce.clone()));
+
+    async move {
+      let Some((content, nonce)) = unsent else {
+        return Err(ChatError::new(ChatErrorKind::Other, "This message has already been sent"));
+      };
+
+      client.request().await?;
+      client.post(channel_id, client.own_user().clone(), content, nonce).await?;
+
+      Ok(())
+    }
+  }
+
+  fn get_identifier(&self) -> u64 {
+    self.id
+  }
+}
+
+impl AsyncList for MemoryChannel {
+  type Content = MemoryMessage;
+
+  async fn bounded_at_top_by(&self) -> Result<Option<u64>, ChatError> {
+    self.client.request().await?;
+
+    let channels = self.client.channels.lock().await;
+
+    Ok(channels.get(&self.id).ok_or_else(unknown_channel)?.messages.first().map(|message| message.list_id))
+  }
+
+  async fn bounded_at_bottom_by(&self) -> Result<Option<u64>, ChatError> {
+    self.client.request().await?;
+
+    let channels = self.client.channels.lock().await;
+
+    Ok(channels.get(&self.id).ok_or_else(unknown_channel)?.messages.last().map(|message| message.list_id))
+  }
+
+  async fn find(&self, identifier: &u64) -> Result<Option<MemoryMessage>, ChatError> {
+    self.client.request().await?;
+
+    let channels = self.client.channels.lock().await;
+    let messages = &channels.get(&self.id).ok_or_else(unknown_channel)?.messages;
+
+    Ok(messages.binary_search_by_key(identifier, |message| message.list_id).ok().map(|index| messages[index].clone()))
+  }
+
+  async fn get(&self, index: AsyncListIndex<u64>) -> Result<Option<AsyncListResult<MemoryMessage>>, ChatError> {
+    self.client.request().await?;
+
+    let channels = self.client.channels.lock().await;
+    let messages = &channels.get(&self.id).ok_or_else(unknown_channel)?.messages;
+
+    // identifiers only grow, so an anchor can be placed even if it was deleted, or is a pending message
+    let position = match index {
+      AsyncListIndex::RelativeToTop(offset) => Some(offset),
+      AsyncListIndex::RelativeToBottom(offset) => messages.len().checked_sub(offset + 1),
+      AsyncListIndex::Before(anchor) => messages.partition_point(|message| message.list_id < anchor).checked_sub(1),
+      AsyncListIndex::After(anchor) => Some(messages.partition_point(|message| message.list_id <= anchor)),
+    };
+
+    Ok(position.and_then(|position| {
+      messages.get(position).map(|message| AsyncListResult {
+        content: message.clone(),
+        is_top: position == 0,
+        is_bottom: position == messages.len() - 1,
+      })
+    }))
+  }
+}
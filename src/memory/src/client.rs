@@ -0,0 +1,299 @@
+use std::{
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, RwLock, Weak,
+  },
+};
+
+use chrono::{DateTime, Utc};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use scope_chat::{
+  channel::ChannelEvent,
+  client::Client,
+  error::{ChatError, ChatErrorKind},
+  guild::{ChannelCategory, ChannelKind, ChannelListing},
+};
+use tokio::sync::{broadcast, Mutex};
+
+use crate::{
+  channel::MemoryChannel,
+  config::MemoryConfig,
+  generate,
+  guild::MemoryGuild,
+  message::{MemoryMessage, MemoryUser},
+};
+
+// generated conversations start here, in November 2023
+const GENERATED_HISTORY_START: i64 = 1_700_000_000;
+const GENERATED_USER_COUNT: usize = 8;
+const CHANNELS_PER_CATEGORY: usize = 3;
+// how many events a view can fall behind on before it has to reload the channel
+const EVENT_CAPACITY: usize = 256;
+
+pub(crate) struct ChannelData {
+  // oldest first, and so also in order of identifier
+  pub(crate) messages: Vec<MemoryMessage>,
+  pub(crate) events: broadcast::Sender<ChannelEvent<MemoryMessage>>,
+}
+
+impl ChannelData {
+  fn new(messages: Vec<MemoryMessage>) -> ChannelData {
+    let (events, _) = broadcast::channel(EVENT_CAPACITY);
+
+    ChannelData { messages, events }
+  }
+}
+
+/// A chat backend that lives entirely in memory, serving data generated from a `MemoryConfig`.
+///
+/// Every request waits out the configured latency and fails at the configured rate, so loading and
+/// error states can be worked on without a network connection.
+pub struct MemoryClient {
+  config: RwLock<MemoryConfig>,
+  user: MemoryUser,
+  users: Vec<MemoryUser>,
+  guilds: Vec<MemoryGuild>,
+  direct_messages: Vec<ChannelListing<u64>>,
+  pub(crate) channels: Mutex<HashMap<u64, ChannelData>>,
+  next_id: AtomicU64,
+  weak: Weak<MemoryClient>,
+}
+
+impl MemoryClient {
+  pub fn new(config: MemoryConfig) -> Arc<MemoryClient> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let next_id = AtomicU64::new(1);
+    let id = || next_id.fetch_add(1, Ordering::Relaxed);
+
+    let user = MemoryUser {
+      id: id(),
+      name: "You".into(),
+      color: generate::color(&mut rng),
+    };
+
+    let users: Vec<MemoryUser> = (0..GENERATED_USER_COUNT)
+      .map(|_| MemoryUser {
+        id: id(),
+        name: generate::person(&mut rng).into(),
+        color: generate::color(&mut rng),
+      })
+      .collect();
+
+    let mut channels = HashMap::new();
+    let mut guilds = vec![];
+
+    let mut members = users.clone();
+    members.push(user.clone());
+
+    for _ in 0..config.guilds {
+      let guild_id = id();
+      let mut categories: Vec<ChannelCategory<u64>> = vec![];
+
+      for index in 0..config.channels_per_guild {
+        if index % CHANNELS_PER_CATEGORY == 0 {
+          // the first few channels are uncategorized
+          let category = index / CHANNELS_PER_CATEGORY;
+
+          categories.push(ChannelCategory {
+            identifier: (category > 0).then(&id),
+            name: (category > 0).then(|| generate::category_name(category - 1).into()),
+            channels: vec![],
+          });
+        }
+
+        let channel_id = id();
+
+        channels.insert(
+          channel_id,
+          ChannelData::new(conversation(&mut rng, &id, &members, config.messages_per_channel)),
+        );
+        categories.last_mut().unwrap().channels.push(ChannelListing {
+          identifier: channel_id,
+          name: generate::channel_name(&mut rng, index).into(),
+          kind: ChannelKind::Text,
+        });
+      }
+
+      guilds.push(MemoryGuild {
+        id: guild_id,
+        name: generate::guild_name(&mut rng).into(),
+        color: generate::color(&mut rng),
+        categories: Arc::new(categories),
+      });
+    }
+
+    let mut direct_messages = vec![];
+
+    for recipient in users.iter().cycle().take(config.direct_messages) {
+      let channel_id = id();
+      let participants = [user.clone(), recipient.clone()];
+
+      channels.insert(
+        channel_id,
+        ChannelData::new(conversation(&mut rng, &id, &participants, config.messages_per_channel)),
+      );
+      direct_messages.push(ChannelListing {
+        identifier: channel_id,
+        name: recipient.name.clone(),
+        kind: ChannelKind::DirectMessage,
+      });
+    }
+
+    Arc::new_cyclic(|weak| MemoryClient {
+      config: RwLock::new(config),
+      user,
+      users,
+      guilds,
+      direct_messages,
+      channels: Mutex::new(channels),
+      next_id,
+      weak: weak.clone(),
+    })
+  }
+
+  pub fn config(&self) -> MemoryConfig {
+    self.config.read().unwrap().clone()
+  }
+
+  /// Changes the latency and failure injection from here on. The generated data stays as it is.
+  pub fn set_config(&self, config: MemoryConfig) {
+    *self.config.write().unwrap() = config;
+  }
+
+  pub fn own_user(&self) -> &MemoryUser {
+    &self.user
+  }
+
+  pub(crate) fn allocate_id(&self) -> u64 {
+    self.next_id.fetch_add(1, Ordering::Relaxed)
+  }
+
+  /// Waits out the configured latency, then fails if the configured failure rate says so.
+  pub(crate) async fn request(&self) -> Result<(), ChatError> {
+    let (delay, failure) = {
+      let config = self.config.read().unwrap();
+      let mut rng = rand::thread_rng();
+
+      let delay = config.latency + config.jitter.mul_f64(rng.gen());
+      let failure = rng.gen_bool(config.failure_rate.clamp(0.0, 1.0)).then_some(config.failure_kind);
+
+      (delay, failure)
+    };
+
+    if !delay.is_zero() {
+      tokio::time::sleep(delay).await;
+    }
+
+    match failure {
+      Some(kind) => Err(ChatError::new(kind, "Injected failure")),
+      None => Ok(()),
+    }
+  }
+
+  /// Adds a message to the bottom of a channel and tells the channel's listeners about it.
+  pub async fn post(&self, channel_id: u64, author: MemoryUser, content: String, nonce: Option<String>) -> Result<MemoryMessage, ChatError> {
+    let mut channels = self.channels.lock().await;
+    let data = channels.get_mut(&channel_id).ok_or_else(unknown_channel)?;
+
+    let message = MemoryMessage::new(self.allocate_id(), author, content, Utc::now(), nonce);
+
+    data.messages.push(message.clone());
+    let _ = data.events.send(ChannelEvent::Created(message.clone()));
+
+    Ok(message)
+  }
+
+  /// Posts something from a random member, to make a channel look alive.
+  pub async fn simulate_message(&self, channel_id: u64) -> Result<MemoryMessage, ChatError> {
+    let (author, content) = {
+      let mut rng = StdRng::from_entropy();
+
+      (self.users.choose(&mut rng).unwrap().clone(), generate::sentence(&mut rng))
+    };
+
+    self.post(channel_id, author, content, None).await
+  }
+
+  /// Deletes a message and tells the channel's listeners about it. Returns `false` if there was no such message.
+  pub async fn delete_message(&self, channel_id: u64, message_id: u64) -> bool {
+    let mut channels = self.channels.lock().await;
+
+    let Some(data) = channels.get_mut(&channel_id) else {
+      return false;
+    };
+
+    let Ok(index) = data.messages.binary_search_by_key(&message_id, |message| message.list_id) else {
+      return false;
+    };
+
+    data.messages.remove(index);
+    let _ = data.events.send(ChannelEvent::Deleted(message_id));
+
+    true
+  }
+}
+
+pub(crate) fn unknown_channel() -> ChatError {
+  ChatError::new(ChatErrorKind::NotFound, "Unknown channel")
+}
+
+/// Bursts of messages from the same author, with the occasional long pause between conversations.
+fn conversation(rng: &mut StdRng, id: &impl Fn() -> u64, authors: &[MemoryUser], count: usize) -> Vec<MemoryMessage> {
+  let mut timestamp = GENERATED_HISTORY_START;
+  let mut author = authors.choose(rng).unwrap();
+
+  (0..count)
+    .map(|_| {
+      if rng.gen_bool(0.4) {
+        author = authors.choose(rng).unwrap();
+      }
+
+      timestamp += if rng.gen_bool(0.05) {
+        rng.gen_range(3_600..86_400)
+      } else {
+        rng.gen_range(5..240)
+      };
+
+      MemoryMessage::new(
+        id(),
+        author.clone(),
+        generate::sentence(rng),
+        DateTime::from_timestamp(timestamp, 0).unwrap(),
+        None,
+      )
+    })
+    .collect()
+}
+
+impl Client for MemoryClient {
+  type Identifier = u64;
+  type Channel = MemoryChannel;
+  type Guild = MemoryGuild;
+
+  async fn channel(&self, identifier: u64) -> Result<MemoryChannel, ChatError> {
+    self.request().await?;
+
+    let Some(client) = self.weak.upgrade() else {
+      return Err(ChatError::new(ChatErrorKind::Other, "The client has been dropped"));
+    };
+
+    let receiver = self.channels.lock().await.get(&identifier).ok_or_else(unknown_channel)?.events.subscribe();
+
+    Ok(MemoryChannel::new(client, identifier, receiver))
+  }
+
+  async fn guilds(&self) -> Vec<MemoryGuild> {
+    self.guilds.clone()
+  }
+
+  async fn guild(&self, identifier: u64) -> Option<MemoryGuild> {
+    self.guilds.iter().find(|guild| guild.id == identifier).cloned()
+  }
+
+  async fn direct_message_channels(&self) -> Result<Vec<ChannelListing<u64>>, ChatError> {
+    self.request().await?;
+
+    Ok(self.direct_messages.clone())
+  }
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use scope_chat::error::ChatErrorKind;
+
+/// What the in-memory backend generates, and how badly it behaves while serving it.
+#[derive(Clone, Debug)]
+pub struct MemoryConfig {
+  /// The same seed always generates the same guilds, channels and messages
+  pub seed: u64,
+  pub guilds: usize,
+  pub channels_per_guild: usize,
+  pub direct_messages: usize,
+  pub messages_per_channel: usize,
+
+  /// How long every request takes
+  pub latency: Duration,
+  /// Up to this much extra time is randomly added to `latency`
+  pub jitter: Duration,
+  /// The chance, from 0 to 1, that a request fails
+  pub failure_rate: f64,
+  pub failure_kind: ChatErrorKind,
+}
+
+impl Default for MemoryConfig {
+  fn default() -> Self {
+    MemoryConfig {
+      seed: 0,
+      guilds: 3,
+      channels_per_guild: 6,
+      direct_messages: 4,
+      messages_per_channel: 500,
+
+      latency: Duration::from_millis(150),
+      jitter: Duration::from_millis(100),
+      failure_rate: 0.0,
+      failure_kind: ChatErrorKind::Network,
+    }
+  }
+}
+
+impl MemoryConfig {
+  /// Answers immediately and never fails.
+  pub fn instant() -> Self {
+    MemoryConfig {
+      latency: Duration::ZERO,
+      jitter: Duration::ZERO,
+      ..Default::default()
+    }
+  }
+
+  pub fn with_seed(mut self, seed: u64) -> MemoryConfig {
+    self.seed = seed;
+    self
+  }
+
+  pub fn with_latency(mut self, latency: Duration, jitter: Duration) -> MemoryConfig {
+    self.latency = latency;
+    self.jitter = jitter;
+    self
+  }
+
+  pub fn with_failures(mut self, failure_rate: f64, failure_kind: ChatErrorKind) -> MemoryConfig {
+    self.failure_rate = failure_rate.clamp(0.0, 1.0);
+    self.failure_kind = failure_kind;
+    self
+  }
+}
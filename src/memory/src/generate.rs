@@ -0,0 +1,63 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+
+const WORDS: &[&str] = &[
+  "the", "a", "we", "should", "probably", "just", "ship", "it", "tomorrow", "cache", "message", "list", "scroll", "bug", "fix", "works", "on", "my",
+  "machine", "why", "is", "this", "so", "slow", "gateway", "rate", "limit", "again", "lol", "nice", "agreed", "rust", "borrow", "checker", "wins",
+  "today", "anyone", "seen", "the", "new", "build", "looks", "great", "broken", "merged", "review", "please", "thanks",
+];
+
+const FIRST_NAMES: &[&str] = &[
+  "Ada", "Grace", "Linus", "Margaret", "Ken", "Barbara", "Dennis", "Frances", "Alan", "Radia", "Edsger", "Hedy",
+];
+const LAST_NAMES: &[&str] = &[
+  "Lovelace", "Hopper", "Torvalds", "Hamilton", "Thompson", "Liskov", "Ritchie", "Allen", "Turing", "Perlman",
+];
+
+const GUILD_NOUNS: &[&str] = &["Garden", "Workshop", "Lounge", "Observatory", "Library", "Harbor", "Foundry", "Arcade"];
+const GUILD_ADJECTIVES: &[&str] = &["Quiet", "Cozy", "Rusty", "Cosmic", "Secret", "Sunny", "Late Night", "Friendly"];
+
+const CHANNEL_NAMES: &[&str] = &[
+  "general",
+  "random",
+  "help",
+  "showcase",
+  "off-topic",
+  "announcements",
+  "dev",
+  "design",
+  "memes",
+  "music",
+];
+const CATEGORY_NAMES: &[&str] = &["Text Channels", "Community", "Projects", "Archive"];
+
+// catppuccin mocha accents, so generated avatars fit the rest of the UI
+pub(crate) const COLORS: &[u32] = &[0xF38BA8, 0xFAB387, 0xF9E2AF, 0xA6E3A1, 0x94E2D5, 0x89B4FA, 0xCBA6F7, 0xF5C2E7];
+
+pub(crate) fn sentence(rng: &mut StdRng) -> String {
+  let length = rng.gen_range(1..=16);
+
+  (0..length).map(|_| *WORDS.choose(rng).unwrap()).collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn person(rng: &mut StdRng) -> String {
+  format!("{} {}", FIRST_NAMES.choose(rng).unwrap(), LAST_NAMES.choose(rng).unwrap())
+}
+
+pub(crate) fn guild_name(rng: &mut StdRng) -> String {
+  format!("{} {}", GUILD_ADJECTIVES.choose(rng).unwrap(), GUILD_NOUNS.choose(rng).unwrap())
+}
+
+pub(crate) fn channel_name(rng: &mut StdRng, index: usize) -> String {
+  match CHANNEL_NAMES.get(index) {
+    Some(name) => name.to_string(),
+    None => format!("{}-{}", CHANNEL_NAMES.choose(rng).unwrap(), index),
+  }
+}
+
+pub(crate) fn category_name(index: usize) -> String {
+  CATEGORY_NAMES.get(index).map(|name| name.to_string()).unwrap_or_else(|| format!("Category {}", index + 1))
+}
+
+pub(crate) fn color(rng: &mut StdRng) -> u32 {
+  *COLORS.choose(rng).unwrap()
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use gpui::SharedString;
+use scope_chat::{
+  guild::{ChannelCategory, Guild},
+  message::IconRenderConfig,
+};
+
+use crate::message::MemoryIcon;
+
+#[derive(Clone)]
+pub struct MemoryGuild {
+  pub id: u64,
+  pub name: SharedString,
+  pub color: u32,
+  pub categories: Arc<Vec<ChannelCategory<u64>>>,
+}
+
+impl Guild for MemoryGuild {
+  type Identifier = u64;
+  type ChannelIdentifier = u64;
+  type Icon = MemoryIcon;
+
+  fn get_identifier(&self) -> u64 {
+    self.id
+  }
+
+  fn get_name(&self) -> SharedString {
+    self.name.clone()
+  }
+
+  fn get_icon(&self, _: IconRenderConfig) -> MemoryIcon {
+    MemoryIcon::new(&self.name, self.color)
+  }
+
+  fn get_categories(&self) -> Vec<ChannelCategory<u64>> {
+    (*self.categories).clone()
+  }
+}
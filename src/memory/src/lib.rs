@@ -0,0 +1,7 @@
+pub mod channel;
+pub mod client;
+pub mod config;
+mod generate;
+pub mod guild;
+pub mod message;
+pub mod tests;
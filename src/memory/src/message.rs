@@ -0,0 +1,195 @@
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use gpui::{
+  div, prelude::FluentBuilder, rgb, IntoElement, ParentElement, Render, RenderOnce, SharedString, Styled, View, ViewContext, VisualContext,
+  WindowContext,
+};
+use scope_chat::{
+  async_list::AsyncListItem,
+  content::RichContent,
+  message::{IconRenderConfig, Message, MessageAuthor, SendStatus},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryUser {
+  pub id: u64,
+  pub name: SharedString,
+  pub color: u32,
+}
+
+impl MessageAuthor for MemoryUser {
+  type Identifier = u64;
+  type DisplayName = SharedString;
+  type Icon = MemoryIcon;
+
+  fn get_display_name(&self) -> SharedString {
+    self.name.clone()
+  }
+
+  fn get_icon(&self, _: IconRenderConfig) -> MemoryIcon {
+    MemoryIcon::new(&self.name, self.color)
+  }
+
+  fn get_identifier(&self) -> u64 {
+    self.id
+  }
+}
+
+/// Initials on a coloured circle, standing in for an avatar.
+#[derive(Clone, Debug, IntoElement)]
+pub struct MemoryIcon {
+  pub initials: SharedString,
+  pub color: u32,
+}
+
+impl MemoryIcon {
+  pub fn new(name: &str, color: u32) -> MemoryIcon {
+    MemoryIcon {
+      initials: name.split_whitespace().filter_map(|word| word.chars().next()).take(2).collect::<String>().into(),
+      color,
+    }
+  }
+}
+
+impl RenderOnce for MemoryIcon {
+  fn render(self, _: &mut WindowContext) -> impl IntoElement {
+    div()
+      .w_full()
+      .h_full()
+      .rounded_full()
+      .flex()
+      .items_center()
+      .justify_center()
+      .bg(rgb(self.color))
+      .text_color(rgb(0xFFFFFF))
+      .text_sm()
+      .child(self.initials)
+  }
+}
+
+#[derive(Clone)]
+pub struct MemoryMessage {
+  /// `None` until the message has been sent
+  pub id: Option<u64>,
+  pub list_id: u64,
+  pub author: MemoryUser,
+  pub content: String,
+  pub timestamp: DateTime<Utc>,
+  pub nonce: Option<String>,
+  pub status: SendStatus,
+
+  pub view: OnceLock<View<MemoryMessageContent>>,
+}
+
+impl MemoryMessage {
+  pub fn new(id: u64, author: MemoryUser, content: String, timestamp: DateTime<Utc>, nonce: Option<String>) -> MemoryMessage {
+    MemoryMessage {
+      id: Some(id),
+      list_id: id,
+      author,
+      content,
+      timestamp,
+      nonce,
+      status: SendStatus::Sent,
+      view: OnceLock::new(),
+    }
+  }
+}
+
+struct NonceRef<'r>(Option<&'r str>);
+
+impl PartialEq for NonceRef<'_> {
+  fn eq(&self, other: &Self) -> bool {
+    // messages without a nonce never match anything
+    matches!((self.0, other.0), (Some(left), Some(right)) if left == right)
+  }
+}
+
+impl Message for MemoryMessage {
+  type Identifier = u64;
+  type Author = MemoryUser;
+  type Content = MemoryMessageContent;
+
+  fn get_author(&self) -> MemoryUser {
+    self.author.clone()
+  }
+
+  fn get_content(&self, cx: &mut WindowContext) -> View<MemoryMessageContent> {
+    self
+      .view
+      .get_or_init(|| {
+        let content = MemoryMessageContent {
+          content: self.get_rich_content(),
+          send_status: self.id.is_none().then(|| self.status.clone()),
+        };
+
+        cx.new_view(|_cx| content)
+      })
+      .clone()
+  }
+
+  fn get_rich_content(&self) -> RichContent {
+    RichContent::plain(&self.content)
+  }
+
+  fn get_identifier(&self) -> Option<u64> {
+    self.id
+  }
+
+  fn get_nonce(&self) -> impl PartialEq {
+    NonceRef(self.nonce.as_deref())
+  }
+
+  fn should_group(&self, previous: &Self) -> bool {
+    const MAX_MEMORY_MESSAGE_GAP_SECS_FOR_GROUP: i64 = 5 * 60;
+
+    self.timestamp.signed_duration_since(previous.timestamp).num_seconds() <= MAX_MEMORY_MESSAGE_GAP_SECS_FOR_GROUP
+  }
+
+  fn get_timestamp(&self) -> Option<DateTime<Utc>> {
+    Some(self.timestamp)
+  }
+
+  fn get_send_status(&self) -> SendStatus {
+    self.status.clone()
+  }
+
+  fn with_send_status(&self, status: SendStatus) -> Self {
+    if self.id.is_some() {
+      return self.clone();
+    }
+
+    MemoryMessage {
+      status,
+      view: OnceLock::new(),
+      ..self.clone()
+    }
+  }
+}
+
+impl AsyncListItem for MemoryMessage {
+  type Identifier = u64;
+
+  fn get_list_identifier(&self) -> u64 {
+    self.list_id
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct MemoryMessageContent {
+  pub content: RichContent,
+  /// `None` for messages that have been sent
+  pub send_status: Option<SendStatus>,
+}
+
+impl Render for MemoryMessageContent {
+  fn render(&mut self, _: &mut ViewContext<MemoryMessageContent>) -> impl IntoElement {
+    let failed = matches!(self.send_status, Some(SendStatus::Failed(_)));
+
+    div()
+      .opacity(if self.send_status.is_some() && !failed { 0.25 } else { 1.0 })
+      .when(failed, |d| d.text_color(rgb(0xF38BA8)))
+      .child(self.content.plain_text())
+  }
+}
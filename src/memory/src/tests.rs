@@ -0,0 +1,135 @@
+use std::future::Future;
+
+#[allow(unused_imports)]
+use scope_chat::{
+  async_list::{AsyncList, AsyncListIndex, AsyncListItem},
+  channel::{Channel, ChannelEvent},
+  client::Client,
+  error::ChatErrorKind,
+  guild::Guild,
+  message::{Message, MessageAuthor},
+};
+
+#[allow(unused_imports)]
+use crate::{channel::MemoryChannel, client::MemoryClient, config::MemoryConfig};
+
+#[allow(dead_code)]
+fn block_on<F: Future>(future: F) -> F::Output {
+  tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(future)
+}
+
+#[allow(dead_code)]
+async fn first_channel(client: &MemoryClient) -> MemoryChannel {
+  let guilds = client.guilds().await;
+  let listing = guilds[0].get_categories()[0].channels[0].clone();
+
+  client.channel(listing.identifier).await.unwrap()
+}
+
+#[test]
+pub fn memory_client_generates_same_data_for_same_seed() {
+  block_on(async {
+    let left = MemoryClient::new(MemoryConfig::instant().with_seed(7));
+    let right = MemoryClient::new(MemoryConfig::instant().with_seed(7));
+
+    let left_names: Vec<_> = left.guilds().await.iter().map(|guild| guild.get_name()).collect();
+    let right_names: Vec<_> = right.guilds().await.iter().map(|guild| guild.get_name()).collect();
+
+    assert_eq!(left_names, right_names);
+
+    let left_bottom = first_channel(&left).await.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+    let right_bottom = first_channel(&right).await.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(left_bottom.content.content, right_bottom.content.content);
+    assert_eq!(
+      left_bottom.content.get_author().get_display_name(),
+      right_bottom.content.get_author().get_display_name()
+    );
+  });
+}
+
+#[test]
+pub fn memory_channel_pages_from_bottom_to_top() {
+  block_on(async {
+    let config = MemoryConfig::instant();
+    let count = config.messages_per_channel;
+    let client = MemoryClient::new(config);
+    let channel = first_channel(&client).await;
+
+    let mut current = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+    let mut seen = 1;
+
+    assert!(current.is_bottom);
+
+    while let Some(result) = channel.get(AsyncListIndex::Before(current.content.get_list_identifier())).await.unwrap() {
+      assert!(result.content.get_list_identifier() < current.content.get_list_identifier());
+
+      current = result;
+      seen += 1;
+    }
+
+    assert_eq!(seen, count);
+    assert!(current.is_top);
+    assert_eq!(channel.bounded_at_top_by().await.unwrap(), Some(current.content.get_list_identifier()));
+  });
+}
+
+#[test]
+pub fn memory_channel_indexes_relative_to_top() {
+  block_on(async {
+    let client = MemoryClient::new(MemoryConfig::instant());
+    let channel = first_channel(&client).await;
+
+    let top = channel.get(AsyncListIndex::RelativeToTop(0)).await.unwrap().unwrap();
+    let second = channel.get(AsyncListIndex::RelativeToTop(1)).await.unwrap().unwrap();
+
+    assert!(top.is_top);
+    assert!(!second.is_top);
+    assert_eq!(
+      channel.get(AsyncListIndex::After(top.content.get_list_identifier())).await.unwrap().unwrap().content.get_list_identifier(),
+      second.content.get_list_identifier()
+    );
+    assert!(channel.get(AsyncListIndex::Before(top.content.get_list_identifier())).await.unwrap().is_none());
+  });
+}
+
+#[test]
+pub fn memory_channel_echoes_sent_messages() {
+  block_on(async {
+    let client = MemoryClient::new(MemoryConfig::instant());
+    let channel = first_channel(&client).await;
+    let mut receiver = channel.get_receiver();
+
+    let pending = channel.pending_message("Hello".to_owned(), "memory-nonce".to_owned());
+
+    channel.send_message(&pending).await.unwrap();
+
+    let ChannelEvent::Created(message) = receiver.recv().await.unwrap() else {
+      panic!("Expected the sent message to be echoed back");
+    };
+
+    assert!(message.get_nonce() == pending.get_nonce());
+    assert_eq!(message.get_author(), *client.own_user());
+
+    let bottom = channel.get(AsyncListIndex::RelativeToBottom(0)).await.unwrap().unwrap();
+
+    assert_eq!(bottom.content.get_identifier(), message.get_identifier());
+  });
+}
+
+#[test]
+pub fn memory_client_injects_failures() {
+  block_on(async {
+    let client = MemoryClient::new(MemoryConfig::instant().with_failures(1.0, ChatErrorKind::Unavailable));
+    let guilds = client.guilds().await;
+    let listing = guilds[0].get_categories()[0].channels[0].clone();
+
+    let error = client.channel(listing.identifier).await.err().unwrap();
+
+    assert_eq!(error.kind(), ChatErrorKind::Unavailable);
+
+    client.set_config(MemoryConfig::instant());
+
+    assert!(client.channel(listing.identifier).await.is_ok());
+  });
+}
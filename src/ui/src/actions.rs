@@ -1,3 +1,3 @@
 use gpui::actions;
 
-actions!(scope, [Quit, Hide]);
+actions!(scope, [Quit, Hide, JumpToBeginning, JumpToPresent]);
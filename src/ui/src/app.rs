@@ -1,55 +1,447 @@
+use std::{sync::Arc, time::Duration};
+
 use components::theme::ActiveTheme;
-use gpui::{div, img, rgb, Context, Model, ParentElement, Render, Styled, View, ViewContext, VisualContext};
-use scope_backend_discord::{channel::DiscordChannel, client::DiscordClient, snowflake::Snowflake};
+use gpui::{div, img, rgb, AsyncAppContext, Context, Model, ParentElement, Render, Styled, View, ViewContext, VisualContext};
+use scope_backend_cache::persistent::PersistentStore;
+use scope_backend_discord::{
+  channel::DiscordChannel, client::DiscordClient, config::DiscordConfig, connection::ConnectionState, snowflake::Snowflake,
+};
+use scope_backend_memory::{channel::MemoryChannel, client::MemoryClient, config::MemoryConfig};
+use scope_chat::{
+  client::Client,
+  error::{ChatError, ChatErrorKind},
+  guild::Guild,
+};
+use tokio::sync::watch;
+
+use crate::{
+  channel::{message_list::StartAt, ChannelView},
+  navigation::Navigation,
+  sidebar::{channel_sidebar, guild_rail},
+};
 
-use crate::channel::ChannelView;
+// how often the countdowns in the title bar go down
+const COUNTDOWN_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct App {
-  channel: Model<Option<View<ChannelView<DiscordChannel>>>>,
+  navigation: Model<Navigation>,
+  // only with SCOPE_BACKEND=memory, which shows one generated channel instead of connecting to Discord
+  memory_view: Option<View<ChannelView<MemoryChannel>>>,
 }
 
 impl App {
   pub fn new(ctx: &mut ViewContext<'_, Self>) -> App {
-    let token = dotenv::var("DISCORD_TOKEN").expect("Must provide DISCORD_TOKEN in .env");
-    let demo_channel_id = dotenv::var("DEMO_CHANNEL_ID").expect("Must provide DEMO_CHANNEL_ID in .env");
+    if dotenv::var("SCOPE_BACKEND").is_ok_and(|backend| backend == "memory") {
+      let navigation = ctx.new_model(|_| Navigation::default());
+
+      App::open_memory_channel(ctx);
+
+      return App {
+        navigation,
+        memory_view: None,
+      };
+    }
+
+    let token = dotenv::var("DISCORD_TOKEN").ok();
+    let config = discord_config();
+    let demo_channel_id = dotenv::var("DEMO_CHANNEL_ID").ok().and_then(|id| id.parse().ok()).map(Snowflake);
+    let demo_message_id = dotenv::var("DEMO_MESSAGE_ID").ok().and_then(|id| id.parse().ok()).map(Snowflake);
 
     let mut context = ctx.to_async();
 
-    let channel = ctx.new_model(|_| None);
+    let navigation = ctx.new_model(|_| Navigation::default());
+
+    let async_navigation = navigation.clone();
+    let view = ctx.view().downgrade();
+
+    ctx
+      .foreground_executor()
+      .spawn(async move {
+        let connected = match token {
+          Some(token) => DiscordClient::new(token, config).await,
+          None => Err(ChatError::new(ChatErrorKind::Unauthorized, "Set DISCORD_TOKEN in .env to log in")),
+        };
+
+        let client = match connected {
+          Ok(client) => client,
+          Err(e) => {
+            log::error!("Failed to connect to Discord: {}", e);
+
+            async_navigation
+              .update(&mut context, |navigation, cx| {
+                navigation.error = Some(e);
+                cx.notify()
+              })
+              .unwrap();
+
+            return;
+          }
+        };
+
+        match open_message_store() {
+          Ok(store) => client.set_store(Arc::new(store)),
+          // everything still works, it just has to be fetched again next time
+          Err(e) => log::error!("Failed to open the message cache: {}", e),
+        }
+
+        let guilds = client.guilds();
+        let direct_messages = client.direct_message_channels().await.unwrap_or_else(|e| {
+          log::error!("Failed to load direct messages: {}", e);
+          vec![]
+        });
+
+        async_navigation
+          .update(&mut context, |navigation, cx| {
+            navigation.client = Some(client.clone());
+            navigation.guilds = guilds;
+            navigation.direct_messages = direct_messages;
+
+            if let Some(demo_channel_id) = demo_channel_id {
+              navigation.select_guild(navigation.guild_of(demo_channel_id));
+            }
+
+            cx.notify()
+          })
+          .unwrap();
+
+        context
+          .foreground_executor()
+          .spawn(follow(
+            client.subscribe_connection(),
+            async_navigation.clone(),
+            context.clone(),
+            |navigation, state| navigation.connection = Some(state),
+          ))
+          .detach();
+
+        // guilds are joined and left, and the ones that were unavailable when the client connected arrive later
+        context
+          .foreground_executor()
+          .spawn(follow(
+            client.subscribe_guilds(),
+            async_navigation.clone(),
+            context.clone(),
+            |navigation, guilds| navigation.guilds = guilds,
+          ))
+          .detach();
+
+        context
+          .foreground_executor()
+          .spawn(follow(
+            client.subscribe_rate_limits(),
+            async_navigation.clone(),
+            context.clone(),
+            |navigation, rate_limits| navigation.rate_limit = rate_limits.into_iter().max_by_key(|rate_limit| rate_limit.retry_at),
+          ))
+          .detach();
+
+        if let Some(demo_channel_id) = demo_channel_id {
+          view
+            .update(&mut context, |app, cx| match demo_message_id {
+              Some(demo_message_id) => app.jump_to_message(demo_channel_id, demo_message_id, cx),
+              None => app.open_channel(demo_channel_id, cx),
+            })
+            .unwrap();
+        }
+
+        loop {
+          context.background_executor().timer(COUNTDOWN_INTERVAL).await;
+
+          let updated = async_navigation.update(&mut context, |navigation, cx| {
+            let reconnecting = navigation.connection.as_ref().is_some_and(|connection| connection.retry_in().is_some());
+            let rate_limited = navigation.rate_limit.is_some();
+
+            // rate limits that have run out aren't sent, so they're dropped here
+            navigation.rate_limit = navigation.rate_limit.take().filter(|rate_limit| !rate_limit.retry_in().is_zero());
 
-    let async_channel = channel.clone();
+            // the countdowns change even when the rate limit and connection don't
+            if rate_limited || reconnecting {
+              cx.notify()
+            }
+          });
+
+          // the window was closed
+          if updated.is_err() {
+            return;
+          }
+        }
+      })
+      .detach();
+
+    ctx.observe(&navigation, |_, _, cx| cx.notify()).detach();
+
+    App {
+      navigation,
+      memory_view: None,
+    }
+  }
+
+  /// Opens the first text channel the in-memory backend generates, for working on the channel view without Discord.
+  fn open_memory_channel(ctx: &mut ViewContext<Self>) {
+    let client = MemoryClient::new(memory_config());
+
+    let mut context = ctx.to_async();
+    let view = ctx.view().downgrade();
 
     ctx
       .foreground_executor()
       .spawn(async move {
-        let client = DiscordClient::new(token).await;
-        let channel = client.channel(Snowflake(demo_channel_id.parse().unwrap())).await;
-        let view = context.new_view(|cx| ChannelView::<DiscordChannel>::create(cx, channel)).unwrap();
-
-        async_channel
-          .update(&mut context, |a, b| {
-            *a = Some(view);
-            b.notify()
+        let guild = client.guilds().await.into_iter().next();
+        let listing = guild
+          .into_iter()
+          .flat_map(|guild| guild.get_categories())
+          .flat_map(|category| category.channels)
+          .find(|listing| listing.kind.is_text_based());
+
+        let Some(listing) = listing else {
+          log::error!("The in-memory backend didn't generate any text channels");
+          return;
+        };
+
+        let channel = match client.channel(listing.identifier).await {
+          Ok(channel) => channel,
+          Err(e) => {
+            log::error!("Failed to open in-memory channel {}: {}", listing.identifier, e);
+
+            view
+              .update(&mut context, |app, cx| {
+                app.navigation.update(cx, |navigation, cx| {
+                  navigation.error = Some(e);
+                  cx.notify()
+                })
+              })
+              .unwrap();
+
+            return;
+          }
+        };
+
+        let channel_view = context.new_view(|cx| ChannelView::create(cx, Arc::new(channel), StartAt::Bottom)).unwrap();
+
+        view
+          .update(&mut context, |app, cx| {
+            app.memory_view = Some(channel_view);
+            cx.notify()
+          })
+          .unwrap();
+      })
+      .detach();
+  }
+
+  pub fn select_guild(&mut self, guild: Option<Snowflake>, cx: &mut ViewContext<Self>) {
+    let last_channel = self.navigation.update(cx, |navigation, cx| {
+      let last_channel = navigation.select_guild(guild);
+
+      cx.notify();
+
+      last_channel
+    });
+
+    if let Some(channel) = last_channel {
+      self.open_channel(channel, cx);
+    }
+  }
+
+  pub fn open_channel(&mut self, channel_id: Snowflake, cx: &mut ViewContext<Self>) {
+    self.open_channel_at(channel_id, None, cx);
+  }
+
+  /// Opens a channel centered on one of its messages, e.g. one that was linked to.
+  pub fn jump_to_message(&mut self, channel_id: Snowflake, message_id: Snowflake, cx: &mut ViewContext<Self>) {
+    self.open_channel_at(channel_id, Some(StartAt::Message(message_id)), cx);
+  }
+
+  /// With no `start_at`, a channel that is already open keeps its place, and a new one starts at the bottom.
+  fn open_channel_at(&mut self, channel_id: Snowflake, start_at: Option<StartAt<Snowflake>>, cx: &mut ViewContext<Self>) {
+    let (client, existing) = self.navigation.update(cx, |navigation, cx| {
+      navigation.activate(channel_id);
+      cx.notify();
+
+      (navigation.client.clone(), navigation.view(channel_id))
+    });
+
+    if let Some(existing) = existing {
+      if let Some(start_at) = start_at {
+        existing.update(cx, |view, cx| view.jump_to(start_at, cx));
+      }
+
+      return;
+    }
+
+    let Some(client) = client else {
+      return;
+    };
+
+    // a channel clicked again while it's loading is left to the load already under way
+    if !self.navigation.update(cx, |navigation, _| navigation.start_loading(channel_id, start_at)) {
+      return;
+    }
+
+    let mut context = cx.to_async();
+    let async_navigation = self.navigation.clone();
+
+    cx.foreground_executor()
+      .spawn(async move {
+        let channel = match client.channel(channel_id).await {
+          Ok(channel) => channel,
+          Err(e) => {
+            log::error!("Failed to open channel {:?}: {}", channel_id, e);
+
+            async_navigation
+              .update(&mut context, |navigation, cx| {
+                navigation.finish_loading(channel_id);
+
+                if navigation.active_channel == Some(channel_id) {
+                  navigation.error = Some(e);
+                  cx.notify()
+                }
+              })
+              .unwrap();
+
+            return;
+          }
+        };
+
+        // it may have been asked to start somewhere else while it was loading
+        let start_at = async_navigation.update(&mut context, |navigation, _| navigation.finish_loading(channel_id)).unwrap();
+        let view = context.new_view(|cx| ChannelView::<DiscordChannel>::create(cx, channel, start_at.unwrap_or(StartAt::Bottom))).unwrap();
+
+        async_navigation
+          .update(&mut context, |navigation, cx| {
+            navigation.insert_view(channel_id, view);
+            cx.notify()
           })
           .unwrap();
       })
       .detach();
+  }
+}
+
+/// Which Discord features to connect for, from the environment. Each defaults to `DiscordConfig::default`.
+fn discord_config() -> DiscordConfig {
+  let default = DiscordConfig::default();
+  let flag = |name: &str, default: bool| dotenv::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default);
+
+  DiscordConfig {
+    message_content: flag("DISCORD_MESSAGE_CONTENT", default.message_content),
+    members: flag("DISCORD_MEMBERS", default.members),
+    presences: flag("DISCORD_PRESENCES", default.presences),
+    direct_messages: flag("DISCORD_DIRECT_MESSAGES", default.direct_messages),
+  }
+}
+
+/// What the in-memory backend generates, from the environment. `MEMORY_SEED` picks which data is generated.
+fn memory_config() -> MemoryConfig {
+  let seed = dotenv::var("MEMORY_SEED").ok().and_then(|seed| seed.parse().ok()).unwrap_or_default();
+
+  MemoryConfig::default().with_seed(seed)
+}
 
-    App { channel }
+/// Keeps `navigation` up to date with what `receiver` sees, starting with what it has now, until either goes away.
+async fn follow<T: Clone + Send + Sync + 'static>(
+  mut receiver: watch::Receiver<T>,
+  navigation: Model<Navigation>,
+  mut context: AsyncAppContext,
+  apply: impl Fn(&mut Navigation, T),
+) {
+  loop {
+    let value = receiver.borrow_and_update().clone();
+
+    let updated = navigation.update(&mut context, |navigation, cx| {
+      apply(navigation, value);
+      cx.notify()
+    });
+
+    if updated.is_err() {
+      return;
+    }
+
+    let (sender, changes) = catty::oneshot();
+
+    tokio::spawn(async move {
+      let changed = receiver.changed().await;
+
+      let _ = sender.send((changed, receiver));
+    });
+
+    let (changed, next) = changes.await.unwrap();
+
+    // the client was dropped, so nothing will change anymore
+    if changed.is_err() {
+      return;
+    }
+
+    receiver = next;
   }
 }
 
+fn connection_status(connection: &ConnectionState) -> Option<String> {
+  match connection {
+    ConnectionState::Ready => None,
+    ConnectionState::Connecting => Some("Connecting...".into()),
+    ConnectionState::Resuming => Some("Reconnecting...".into()),
+    ConnectionState::Disconnected { .. } => Some(match connection.retry_in() {
+      Some(retry_in) => format!("Disconnected, reconnecting in {}s", retry_in.as_secs_f32().ceil()),
+      None => "Disconnected".into(),
+    }),
+  }
+}
+
+fn open_message_store() -> Result<PersistentStore, ChatError> {
+  let cache_dir = dirs::cache_dir().ok_or_else(|| ChatError::new(ChatErrorKind::Storage, "There is no cache directory"))?;
+
+  PersistentStore::open(cache_dir.join("scope").join("messages.sqlite3"))
+}
+
 impl Render for App {
   fn render(&mut self, cx: &mut gpui::ViewContext<Self>) -> impl gpui::IntoElement {
     let mut content = div().w_full().h_full();
 
-    if let Some(channel) = self.channel.read(cx).as_ref() {
+    let navigation = self.navigation.read(cx);
+
+    if let Some(channel) = &self.memory_view {
       content = content.child(channel.clone());
+    } else if let Some(channel) = navigation.active_view() {
+      content = content.child(channel);
+    } else if let Some(error) = &navigation.error {
+      content = content.flex().items_center().justify_center().text_color(rgb(0xF38BA8)).child(error.to_string());
+    } else if navigation.active_channel.is_some() {
+      content = content.flex().items_center().justify_center().text_color(rgb(0xAFBAC7)).child("Loading...");
+    }
+
+    let body =
+      div().flex().flex_row().w_full().h_full().min_h_0().child(guild_rail(navigation, cx)).child(channel_sidebar(navigation, cx)).child(content);
+
+    let mut title = div().flex().flex_row().text_color(rgb(0xFFFFFF)).gap_2().child(img("brand/scope-round-200.png").w_6().h_6()).child("Scope");
+
+    if let Some(connection) = &navigation.connection {
+      let color = match connection {
+        ConnectionState::Ready => rgb(0xA6E3A1),
+        ConnectionState::Connecting | ConnectionState::Resuming => rgb(0xF9E2AF),
+        ConnectionState::Disconnected { .. } => rgb(0xF38BA8),
+      };
+
+      let mut status = div().flex().flex_row().items_center().gap_1().child(div().w_2().h_2().rounded_full().bg(color));
+
+      if let Some(text) = connection_status(connection) {
+        status = status.child(div().text_color(color).child(text));
+      }
+
+      title = title.child(status);
+    }
+
+    // only when it was asked for and Discord refused it, not when it was turned off
+    if navigation.client.as_ref().is_some_and(|client| client.requested_config().message_content && !client.config().message_content) {
+      title = title.child(div().text_color(rgb(0xF9E2AF)).child("Message content unavailable"));
+    }
+
+    if let Some(rate_limit) = &navigation.rate_limit {
+      title =
+        title.child(div().text_color(rgb(0xF9E2AF)).child(format!("Rate limited, retrying in {}s", rate_limit.retry_in().as_secs_f32().ceil())));
     }
 
-    let title_bar = components::TitleBar::new()
-      .child(div().flex().flex_row().text_color(rgb(0xFFFFFF)).gap_2().child(img("brand/scope-round-200.png").w_6().h_6()).child("Scope"));
+    let title_bar = components::TitleBar::new().child(title);
 
-    div().bg(cx.theme().background).w_full().h_full().flex().flex_col().child(title_bar).child(content)
+    div().bg(cx.theme().background).w_full().h_full().flex().flex_col().child(title_bar).child(body)
   }
 }
@@ -1,6 +1,10 @@
+use std::rc::Rc;
+
 use chrono::Local;
-use gpui::{div, prelude::FluentBuilder, rgb, IntoElement, ParentElement, Styled, WindowContext};
-use scope_chat::message::{IconRenderConfig, Message, MessageAuthor};
+use gpui::{
+  div, prelude::FluentBuilder, rgb, ElementId, InteractiveElement, IntoElement, ParentElement, StatefulInteractiveElement, Styled, WindowContext,
+};
+use scope_chat::message::{IconRenderConfig, Message, MessageAuthor, SendStatus};
 
 #[derive(Clone)]
 pub struct MessageGroup<M: Message> {
@@ -42,7 +46,60 @@ impl<M: Message> MessageGroup<M> {
   }
 }
 
-pub fn message_group<M: Message>(group: MessageGroup<M>, cx: &mut WindowContext) -> impl IntoElement {
+/// What the user can do with a message that failed to send.
+pub struct FailedMessageActions<M> {
+  pub retry: Rc<dyn Fn(&M, &mut WindowContext)>,
+  pub discard: Rc<dyn Fn(&M, &mut WindowContext)>,
+}
+
+impl<M> Clone for FailedMessageActions<M> {
+  fn clone(&self) -> Self {
+    FailedMessageActions {
+      retry: self.retry.clone(),
+      discard: self.discard.clone(),
+    }
+  }
+}
+
+fn message_content<M: Message + 'static>(message: &M, index: usize, actions: &FailedMessageActions<M>, cx: &mut WindowContext) -> impl IntoElement {
+  let content = div().child(message.get_content(cx));
+
+  let SendStatus::Failed(error) = message.get_send_status() else {
+    return content;
+  };
+
+  let retry = actions.retry.clone();
+  let retry_message = message.clone();
+  let discard = actions.discard.clone();
+  let discard_message = message.clone();
+
+  content.child(
+    div()
+      .flex()
+      .gap_2()
+      .text_sm()
+      .text_color(rgb(0xF38BA8))
+      .child(format!("Failed to send: {}", error.message()))
+      .child(
+        div()
+          .id(ElementId::NamedInteger("retry-send".into(), index))
+          .text_color(rgb(0xFFFFFF))
+          .cursor_pointer()
+          .child("Retry")
+          .on_click(move |_, cx| retry(&retry_message, cx)),
+      )
+      .child(
+        div()
+          .id(ElementId::NamedInteger("discard-send".into(), index))
+          .text_color(rgb(0xFFFFFF))
+          .cursor_pointer()
+          .child("Discard")
+          .on_click(move |_, cx| discard(&discard_message, cx)),
+      ),
+  )
+}
+
+pub fn message_group<M: Message + 'static>(group: MessageGroup<M>, actions: FailedMessageActions<M>, cx: &mut WindowContext) -> impl IntoElement {
   div()
     .flex()
     .flex_row()
@@ -63,6 +120,6 @@ pub fn message_group<M: Message>(group: MessageGroup<M>, cx: &mut WindowContext)
             d.child(div().min_w_0().text_color(rgb(0xAFBAC7)).text_sm().child(ts.with_timezone(&Local).format("%I:%M %p").to_string()))
           }),
         )
-        .children(group.contents.iter().map(|v| v.get_content(cx).clone())),
+        .children(group.contents.iter().enumerate().map(|(index, v)| message_content(v, index, &actions, cx))),
     )
 }
@@ -1,14 +1,24 @@
-use std::sync::Arc;
+use std::{
+  rc::Rc,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+  },
+};
 
-use gpui::{div, list, rgb, Context, IntoElement, ListAlignment, ListState, Model, ParentElement, Pixels, Render, Styled, ViewContext};
+use gpui::{
+  div, list, rgb, Context, InteractiveElement, IntoElement, ListAlignment, ListOffset, ListState, Model, ParentElement, Pixels, Render,
+  StatefulInteractiveElement, Styled, ViewContext, WindowContext,
+};
 use scope_chat::{
-  async_list::{AsyncListIndex, AsyncListItem},
+  async_list::{AsyncList, AsyncListEntry, AsyncListGap, AsyncListIndex, AsyncListItem},
   channel::Channel,
-  message::{Message, MessageAuthor},
+  error::{ChatError, ChatErrorKind},
+  message::{Message, MessageAuthor, SendStatus},
 };
 use tokio::sync::RwLock;
 
-use super::message::{message_group, MessageGroup};
+use super::message::{message_group, FailedMessageActions, MessageGroup};
 
 #[derive(Clone, Copy)]
 struct ListStateDirtyState {
@@ -22,18 +32,40 @@ struct BoundFlags {
   pub after: bool,
 }
 
+/// Identifies a row that's loading, so whatever loads it can find it again however the rows around it have changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RowId(u64);
+
+impl RowId {
+  fn next() -> RowId {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+
+    RowId(NEXT.fetch_add(1, Ordering::Relaxed))
+  }
+}
+
 #[derive(Debug)]
-pub enum Element<T> {
-  Unresolved,
+pub enum Element<T, G> {
+  Unresolved(RowId),
   Resolved(T),
+  /// Loading the row failed. Removing it lets the list try again the next time that end comes into view.
+  Failed(RowId, ChatError),
+  /// Messages between the ones on either side that haven't been loaded yet.
+  Gap(G),
 }
 
+type Gap<M> = AsyncListGap<<M as AsyncListItem>::Identifier>;
+type MessageCache<M> = Vec<Element<Option<M>, Gap<M>>>;
+
+// how many messages a click on a gap loads into it
+const GAP_FILL_BATCH_SIZE: usize = 50;
+
 pub struct MessageListComponent<C: Channel>
 where
   C::Content: 'static,
 {
   list: Arc<RwLock<C>>,
-  cache: Model<Vec<Element<Option<C::Content>>>>,
+  cache: Model<MessageCache<C::Content>>,
   overdraw: Pixels,
 
   // top, bottom
@@ -41,25 +73,38 @@ where
 
   list_state: Model<Option<ListState>>,
   list_state_dirty: Model<Option<ListStateDirtyState>>,
+
+  start_at: StartAt<<C::Content as AsyncListItem>::Identifier>,
+  // the message that is kept in view while the list loads around it, until the user scrolls
+  anchor: Model<Option<<C::Content as AsyncListItem>::Identifier>>,
 }
 
-pub enum StartAt {
+/// Where a message list starts loading from.
+#[derive(Clone, Debug)]
+pub enum StartAt<I> {
   Bottom,
   Top,
+  /// Loads outwards from this message, falling back to the bottom if it doesn't exist
+  Message(I),
+  /// Loads outwards from the first message after this identifier, which doesn't have to be one a message has, e.g.
+  /// one from `Channel::identifier_at`. Falls back to the bottom if there are no messages after it.
+  After(I),
 }
 
 impl<T: Channel> MessageListComponent<T>
 where
   T: 'static,
 {
-  pub fn create(cx: &mut ViewContext<Self>, list: T, overdraw: Pixels) -> Self {
+  pub fn create(cx: &mut ViewContext<Self>, list: T, overdraw: Pixels, start_at: StartAt<<T::Content as AsyncListItem>::Identifier>) -> Self {
     let cache = cx.new_model(|_| Default::default());
     let list_state = cx.new_model(|_| None);
     let list_state_dirty = cx.new_model(|_| None);
 
+    Self::observe_cache(cx, &cache, &list_state);
+
     let lsc = list_state.clone();
 
-    cx.observe(&cache, move |c, _, cx| {
+    cx.observe(&list_state_dirty, move |c, _, cx| {
       let ls = c.list_state(cx);
 
       lsc.update(cx, |v, _| *v = Some(ls));
@@ -68,9 +113,22 @@ where
     })
     .detach();
 
+    MessageListComponent {
+      list: Arc::new(RwLock::new(list)),
+      cache,
+      overdraw,
+      bounds_flags: cx.new_model(|_| BoundFlags { before: false, after: false }),
+      list_state,
+      list_state_dirty,
+      anchor: cx.new_model(|_| Self::anchor_of(&start_at)),
+      start_at,
+    }
+  }
+
+  fn observe_cache(cx: &mut ViewContext<Self>, cache: &Model<MessageCache<T::Content>>, list_state: &Model<Option<ListState>>) {
     let lsc = list_state.clone();
 
-    cx.observe(&list_state_dirty, move |c, _, cx| {
+    cx.observe(cache, move |c, _, cx| {
       let ls = c.list_state(cx);
 
       lsc.update(cx, |v, _| *v = Some(ls));
@@ -78,17 +136,49 @@ where
       cx.notify();
     })
     .detach();
+  }
 
-    MessageListComponent {
-      list: Arc::new(RwLock::new(list)),
-      cache,
-      overdraw,
-      bounds_flags: cx.new_model(|_| BoundFlags { before: false, after: false }),
-      list_state,
-      list_state_dirty,
+  fn anchor_of(start_at: &StartAt<<T::Content as AsyncListItem>::Identifier>) -> Option<<T::Content as AsyncListItem>::Identifier> {
+    match start_at {
+      StartAt::Message(identifier) => Some(identifier.clone()),
+      _ => None,
     }
   }
 
+  /// Throws away the loaded messages and starts again from somewhere else.
+  pub fn jump_to(&mut self, cx: &mut ViewContext<Self>, start_at: StartAt<<T::Content as AsyncListItem>::Identifier>) {
+    // a new model, so loads still in flight for the old one can't land in this one
+    self.cache = cx.new_model(|_| Default::default());
+
+    Self::observe_cache(cx, &self.cache, &self.list_state);
+
+    self.anchor.update(cx, |v, _| *v = Self::anchor_of(&start_at));
+    self.start_at = start_at;
+
+    self.bounds_flags.update(cx, |v, _| *v = BoundFlags { before: false, after: false });
+    self.list_state_dirty.update(cx, |v, _| *v = None);
+    self.list_state.update(cx, |v, _| *v = None);
+
+    cx.notify();
+  }
+
+  /// Loads the messages again, e.g. after some of the channel's events were missed. A list showing the newest messages
+  /// starts from the bottom again, and any other from where it started.
+  pub fn reload(&mut self, cx: &mut ViewContext<Self>) {
+    let start_at = if self.reached_bottom(cx) {
+      StartAt::Bottom
+    } else {
+      self.start_at.clone()
+    };
+
+    self.jump_to(cx, start_at);
+  }
+
+  /// Whether the newest messages are loaded, so new ones can be shown right after them.
+  fn reached_bottom(&self, cx: &ViewContext<Self>) -> bool {
+    matches!(self.cache.read(cx).last(), Some(Element::Resolved(None)))
+  }
+
   pub fn append_message(&mut self, cx: &mut ViewContext<Self>, message: T::Message) {
     self.cache.update(cx, |borrow, cx| {
       for item in borrow.iter_mut() {
@@ -102,9 +192,12 @@ where
         }
       }
 
-      if let Some(Element::Resolved(None)) = borrow.last() {
-        borrow.pop();
-      }
+      // until the list has loaded its way down to the bottom, this message will be loaded along with the rest
+      let Some(Element::Resolved(None)) = borrow.last() else {
+        return;
+      };
+
+      borrow.pop();
 
       borrow.push(Element::Resolved(Some(message)));
       borrow.push(Element::Resolved(None));
@@ -115,8 +208,72 @@ where
     });
   }
 
+  /// Shows a pending message and sends it, marking it as sent or failed once the channel answers.
+  pub fn send_message(&mut self, cx: &mut ViewContext<Self>, message: T::Message) {
+    if !self.reached_bottom(cx) && !matches!(self.start_at, StartAt::Bottom) {
+      self.jump_to(cx, StartAt::Bottom);
+    }
+
+    self.append_message(cx, message.clone());
+
+    deliver(self.list.clone(), self.cache.clone(), message, cx);
+  }
+
+  pub fn update_message(&mut self, cx: &mut ViewContext<Self>, message: T::Message) {
+    self.cache.update(cx, |borrow, cx| {
+      for item in borrow.iter_mut() {
+        if let Element::Resolved(Some(haystack)) = item {
+          if haystack.get_identifier().is_some() && haystack.get_identifier() == message.get_identifier() {
+            *item = Element::Resolved(Some(message));
+
+            cx.notify();
+            return;
+          }
+        }
+      }
+    });
+  }
+
+  pub fn remove_messages(&mut self, cx: &mut ViewContext<Self>, identifiers: &[T::Identifier]) {
+    self.cache.update(cx, |borrow, cx| {
+      let len = borrow.len();
+
+      // loads in flight find their rows by `RowId`, so they aren't thrown off by the rows moving
+      borrow.retain(|item| match item {
+        Element::Resolved(Some(haystack)) => haystack.get_identifier().map(|id| !identifiers.contains(&id)).unwrap_or(true),
+        _ => true,
+      });
+
+      if borrow.len() != len {
+        cx.notify();
+      }
+    });
+  }
+
   fn list_state(&self, cx: &mut gpui::ViewContext<Self>) -> ListState {
     let bounds_model = self.bounds_flags.clone();
+    let cache_model = self.cache.clone();
+    let anchor_model = self.anchor.clone();
+    let anchor = self.anchor.read(cx).clone();
+    let mut anchor_group = None;
+
+    let actions = {
+      let list = self.list.clone();
+      let retry_cache = self.cache.clone();
+      let discard_cache = self.cache.clone();
+
+      FailedMessageActions {
+        retry: Rc::new(move |message: &T::Message, cx: &mut WindowContext| retry(list.clone(), retry_cache.clone(), message, cx)),
+        discard: Rc::new(move |message: &T::Message, cx: &mut WindowContext| discard(&discard_cache, message, cx)),
+      }
+    };
+
+    let fill: Rc<dyn Fn(&Gap<T::Content>, &mut WindowContext)> = {
+      let list = self.list.clone();
+      let cache = self.cache.clone();
+
+      Rc::new(move |gap, cx| fill_gap(list.clone(), cache.clone(), gap.clone(), cx))
+    };
 
     let list_state_dirty = *self.list_state_dirty.read(cx);
 
@@ -132,10 +289,16 @@ where
       let mut items_added: usize = 0;
 
       match item {
-        Element::Unresolved => groups.push(Element::Unresolved),
+        Element::Unresolved(row) => groups.push(Element::Unresolved(*row)),
+        Element::Failed(row, e) => groups.push(Element::Failed(*row, e.clone())),
         Element::Resolved(None) => groups.push(Element::Resolved(None)),
+        Element::Gap(gap) => groups.push(Element::Gap(gap.clone())),
         Element::Resolved(Some(m)) => match groups.last_mut() {
-          None | Some(Element::Unresolved) | Some(Element::Resolved(None)) => {
+          None | Some(Element::Unresolved(_)) | Some(Element::Resolved(None)) | Some(Element::Gap(_)) => {
+            items_added += 1;
+            groups.push(Element::Resolved(Some(MessageGroup::new(m.clone()))));
+          }
+          Some(Element::Failed(..)) => {
             items_added += 1;
             groups.push(Element::Resolved(Some(MessageGroup::new(m.clone()))));
           }
@@ -150,6 +313,12 @@ where
         },
       }
 
+      if let Element::Resolved(Some(m)) = item {
+        if anchor.as_ref() == Some(&m.get_list_identifier()) {
+          anchor_group = Some(groups.len() - 1);
+        }
+      }
+
       if index == 0 {
         continue;
       }
@@ -189,9 +358,36 @@ where
           div()
         } else {
           match &groups[idx - 1] {
-            Element::Unresolved => div().text_color(rgb(0xFFFFFF)).child("Loading..."),
+            Element::Unresolved(_) => div().text_color(rgb(0xFFFFFF)).child("Loading..."),
+            Element::Failed(row, e) => {
+              let cache_model = cache_model.clone();
+              let row = *row;
+
+              div().flex().flex_row().gap_2().text_color(rgb(0xF38BA8)).child(format!("Failed to load messages: {}", e.message())).child(
+                div().id(("retry-load", idx)).text_color(rgb(0xFFFFFF)).cursor_pointer().child("Retry").on_click(move |_, cx| {
+                  // only this row, the other end may have failed for reasons of its own
+                  cache_model.update(cx, |borrow, cx| {
+                    borrow.retain(|item| !matches!(item, Element::Failed(failed, _) if *failed == row));
+                    cx.notify();
+                  })
+                }),
+              )
+            }
+            Element::Gap(gap) => {
+              let fill = fill.clone();
+              let gap = gap.clone();
+
+              div().flex().flex_row().gap_2().text_color(rgb(0xA6ADC8)).child("Some messages here haven't been loaded").child(
+                div()
+                  .id(("load-missing", idx))
+                  .text_color(rgb(0xFFFFFF))
+                  .cursor_pointer()
+                  .child("Load missing messages")
+                  .on_click(move |_, cx| fill(&gap, cx)),
+              )
+            }
             Element::Resolved(None) => div(), // we've hit the ends
-            Element::Resolved(Some(group)) => div().child(message_group(group.clone(), cx)),
+            Element::Resolved(Some(group)) => div().child(message_group(group.clone(), actions.clone(), cx)),
           }
         }
         .into_any_element()
@@ -200,7 +396,15 @@ where
 
     let old_list_state = self.list_state.read(cx);
 
-    if let Some(old_list_state) = old_list_state {
+    if let Some(anchor_group) = anchor_group {
+      // the first item is the top spacer
+      new_list_state.scroll_to(ListOffset {
+        item_ix: anchor_group + 1,
+        offset_in_item: Pixels(0.),
+      });
+
+      new_list_state.set_scroll_handler(move |_, cx| anchor_model.update(cx, |v, _| *v = None));
+    } else if let Some(old_list_state) = old_list_state {
       let mut new_scroll_top = old_list_state.logical_scroll_top();
 
       if old_list_state.logical_scroll_top().item_ix == old_list_state.item_count() {
@@ -230,24 +434,27 @@ where
     if flags.after {
       let cache_model = self.cache.clone();
       let list_handle = self.list.clone();
+      let start_at = self.start_at.clone();
+      let anchor_model = self.anchor.clone();
 
       self.cache.update(cx, |borrow, cx| {
-        let last = borrow.last();
-
-        let index = if let Some(last) = last {
-          AsyncListIndex::After(if let Element::Resolved(Some(v)) = last {
-            v.get_list_identifier()
-          } else {
+        let next = match borrow.last() {
+          None => Next::Start(start_at),
+          Some(Element::Resolved(Some(v))) => Next::Index(AsyncListIndex::After(v.get_list_identifier())),
+          Some(Element::Gap(gap)) => Next::Item(gap.below.clone()),
+          Some(_) => {
             flags.after = false;
             return;
-          })
-        } else {
-          AsyncListIndex::RelativeToBottom(0)
+          }
         };
 
-        borrow.push(Element::Unresolved);
+        // which message the list starts at isn't known until it's loaded, but it's kept in view all the same
+        let keep_in_view = matches!(next, Next::Start(StartAt::After(_)));
+
+        let row = RowId::next();
+
+        borrow.push(Element::Unresolved(row));
 
-        let insert_index = borrow.len() - 1;
         let mut async_ctx = cx.to_async();
 
         cx.foreground_executor()
@@ -255,7 +462,7 @@ where
             let (sender, receiver) = catty::oneshot();
 
             tokio::spawn(async move {
-              match sender.send(list_handle.read().await.get(index).await) {
+              match sender.send(load_next(&*list_handle.read().await, next).await) {
                 Ok(_) => {}
                 Err(_e) => log::error!("Failed to send."),
               }
@@ -265,7 +472,15 @@ where
 
             cache_model
               .update(&mut async_ctx, |borrow, cx| {
-                borrow[insert_index] = Element::Resolved(v.map(|v| v.content));
+                let Some(item) = loading_row(borrow, row) else {
+                  return;
+                };
+
+                *item = element_of(row, v);
+
+                if let (true, Element::Resolved(Some(message))) = (keep_in_view, &*item) {
+                  cx.update_model(&anchor_model, |v, _| *v = Some(message.get_list_identifier()));
+                }
 
                 cx.notify();
               })
@@ -283,23 +498,19 @@ where
       let list_handle = self.list.clone();
 
       self.cache.update(cx, |borrow, cx| {
-        let first = borrow.first();
-
-        let index = if let Some(first) = first {
-          AsyncListIndex::Before(if let Element::Resolved(Some(v)) = first {
-            v.get_list_identifier()
-          } else {
+        let next = match borrow.first() {
+          Some(Element::Resolved(Some(v))) => Next::Index(AsyncListIndex::Before(v.get_list_identifier())),
+          Some(Element::Gap(gap)) => Next::Item(gap.above.clone()),
+          _ => {
             flags.before = false;
             return;
-          })
-        } else {
-          flags.before = false;
-          return;
+          }
         };
 
-        borrow.insert(0, Element::Unresolved);
+        let row = RowId::next();
+
+        borrow.insert(0, Element::Unresolved(row));
 
-        let insert_index = 0;
         let mut async_ctx = cx.to_async();
 
         cx.foreground_executor()
@@ -307,7 +518,7 @@ where
             let (sender, receiver) = catty::oneshot();
 
             tokio::spawn(async move {
-              match sender.send(list_handle.read().await.get(index).await) {
+              match sender.send(load_next(&*list_handle.read().await, next).await) {
                 Ok(_) => {}
                 Err(_e) => log::error!("Failed to send."),
               }
@@ -317,7 +528,11 @@ where
 
             cache_model
               .update(&mut async_ctx, |borrow, cx| {
-                borrow[insert_index] = Element::Resolved(v.map(|v| v.content));
+                let Some(item) = loading_row(borrow, row) else {
+                  return;
+                };
+
+                *item = element_of(row, v);
                 cx.notify();
               })
               .unwrap();
@@ -354,6 +569,228 @@ where
   }
 }
 
+/// What to load at one end of the list.
+enum Next<I> {
+  /// The message the list starts at
+  Start(StartAt<I>),
+  Index(AsyncListIndex<I>),
+  /// A message on the far side of a gap, which is already known to be there
+  Item(I),
+}
+
+async fn load_next<L: AsyncList>(
+  list: &L,
+  next: Next<<L::Content as AsyncListItem>::Identifier>,
+) -> Result<Option<AsyncListEntry<L::Content>>, ChatError> {
+  match next {
+    Next::Start(start_at) => Ok(load_start(list, start_at).await?.map(AsyncListEntry::Item)),
+    Next::Index(index) => Ok(list.get_entry(index).await?.map(|v| v.content)),
+    Next::Item(identifier) => Ok(list.find(&identifier).await?.map(AsyncListEntry::Item)),
+  }
+}
+
+/// The row `row` is loading into, which messages being added or removed around it may have moved, or `None` if it has
+/// been thrown away since.
+fn loading_row<M: AsyncListItem>(cache: &mut MessageCache<M>, row: RowId) -> Option<&mut Element<Option<M>, Gap<M>>> {
+  cache.iter_mut().find(|item| matches!(item, Element::Unresolved(haystack) if *haystack == row))
+}
+
+fn element_of<M: AsyncListItem>(row: RowId, result: Result<Option<AsyncListEntry<M>>, ChatError>) -> Element<Option<M>, Gap<M>> {
+  match result {
+    Ok(Some(AsyncListEntry::Item(item))) => Element::Resolved(Some(item)),
+    Ok(Some(AsyncListEntry::Gap(gap))) => Element::Gap(gap),
+    Ok(None) => Element::Resolved(None),
+    Err(e) => Element::Failed(row, e),
+  }
+}
+
+async fn load_start<L: AsyncList>(list: &L, start_at: StartAt<<L::Content as AsyncListItem>::Identifier>) -> Result<Option<L::Content>, ChatError> {
+  let index = match start_at {
+    StartAt::Bottom => AsyncListIndex::RelativeToBottom(0),
+    StartAt::Top => AsyncListIndex::RelativeToTop(0),
+    StartAt::Message(identifier) => match list.find(&identifier).await? {
+      Some(message) => return Ok(Some(message)),
+      None => {
+        log::warn!("Couldn't find message {:?} to start at, starting at the bottom instead", identifier);
+
+        AsyncListIndex::RelativeToBottom(0)
+      }
+    },
+    StartAt::After(identifier) => match list.get(AsyncListIndex::After(identifier.clone())).await? {
+      Some(result) => return Ok(Some(result.content)),
+      None => {
+        log::warn!("There are no messages after {:?} to start at, starting at the bottom instead", identifier);
+
+        AsyncListIndex::RelativeToBottom(0)
+      }
+    },
+  };
+
+  Ok(list.get(index).await?.map(|v| v.content))
+}
+
+/// Replaces the pending message with the same nonce, unless the backend has already reported it.
+fn set_send_status<M: Message>(cache: &mut [Element<Option<M>, Gap<M>>], message: &M, status: SendStatus) -> bool {
+  for item in cache.iter_mut() {
+    if let Element::Resolved(Some(haystack)) = item {
+      if haystack.get_identifier().is_none() && haystack.get_nonce() == message.get_nonce() {
+        let updated = haystack.with_send_status(status);
+        *item = Element::Resolved(Some(updated));
+
+        return true;
+      }
+    }
+  }
+
+  false
+}
+
+fn deliver<C: Channel + 'static>(list: Arc<RwLock<C>>, cache: Model<MessageCache<C::Content>>, message: C::Message, cx: &mut WindowContext) {
+  let mut async_ctx = cx.to_async();
+
+  cx.foreground_executor()
+    .spawn(async move {
+      let (sender, receiver) = catty::oneshot();
+      let sending = message.clone();
+
+      tokio::spawn(async move {
+        match sender.send(list.read().await.send_message(&sending).await) {
+          Ok(_) => {}
+          Err(_e) => log::error!("Failed to send."),
+        }
+      });
+
+      // the task sending it can panic, or be cancelled when the runtime shuts down
+      let result = receiver.await.unwrap_or_else(|_| Err(ChatError::new(ChatErrorKind::Other, "Sending was interrupted")));
+
+      let status = match result {
+        Ok(()) => SendStatus::Sent,
+        Err(e) => {
+          log::error!("Failed to send message: {}", e);
+          SendStatus::Failed(e)
+        }
+      };
+
+      cache
+        .update(&mut async_ctx, |borrow, cx| {
+          if set_send_status(borrow, &message, status) {
+            cx.notify();
+          }
+        })
+        .unwrap();
+    })
+    .detach();
+}
+
+/// Sends a failed message again, with the nonce it was originally sent with.
+fn retry<C: Channel + 'static>(list: Arc<RwLock<C>>, cache: Model<MessageCache<C::Content>>, message: &C::Message, cx: &mut WindowContext) {
+  let message = message.with_send_status(SendStatus::Pending);
+
+  cache.update(cx, |borrow, cx| {
+    if set_send_status(borrow, &message, SendStatus::Pending) {
+      cx.notify();
+    }
+  });
+
+  deliver(list, cache, message, cx);
+}
+
+fn discard<M: Message + 'static>(cache: &Model<MessageCache<M>>, message: &M, cx: &mut WindowContext) {
+  cache.update(cx, |borrow, cx| {
+    borrow.retain(|item| match item {
+      Element::Resolved(Some(haystack)) => haystack.get_identifier().is_some() || haystack.get_nonce() != message.get_nonce(),
+      _ => true,
+    });
+
+    cx.notify();
+  });
+}
+
+/// Loads a batch of the messages in a gap, from its top down. Whatever is still missing after that stays a gap, until
+/// the messages on both sides of it meet.
+fn fill_gap<C: Channel + 'static>(list: Arc<RwLock<C>>, cache: Model<MessageCache<C::Content>>, gap: Gap<C::Content>, cx: &mut WindowContext) {
+  let row = RowId::next();
+
+  // the gap stays where it is, as a loading row, so it can't be filled twice at once
+  let started = cache.update(cx, |borrow, cx| {
+    let Some(item) = borrow.iter_mut().find(|item| matches!(item, Element::Gap(haystack) if *haystack == gap)) else {
+      return false;
+    };
+
+    *item = Element::Unresolved(row);
+    cx.notify();
+
+    true
+  });
+
+  if !started {
+    return;
+  }
+
+  let mut async_ctx = cx.to_async();
+
+  cx.foreground_executor()
+    .spawn(async move {
+      let (sender, receiver) = catty::oneshot();
+      let filling = gap.clone();
+
+      tokio::spawn(async move {
+        let list = list.read().await;
+        let mut loaded = vec![];
+        let mut current = filling.above.clone();
+
+        // where the rest of the gap starts, `None` once it has closed
+        let remaining = loop {
+          if loaded.len() == GAP_FILL_BATCH_SIZE {
+            break Some(current);
+          }
+
+          match list.get(AsyncListIndex::After(current.clone())).await {
+            Ok(Some(result)) if result.content.get_list_identifier() == filling.below => break None,
+            Ok(Some(result)) => {
+              current = result.content.get_list_identifier();
+              loaded.push(result.content);
+            }
+            // the message below the gap must have been deleted
+            Ok(None) => break None,
+            Err(e) => {
+              log::error!("Failed to load missing messages: {}", e);
+              break Some(current);
+            }
+          }
+        };
+
+        match sender.send((loaded, remaining)) {
+          Ok(_) => {}
+          Err(_e) => log::error!("Failed to send."),
+        }
+      });
+
+      let (loaded, remaining) = receiver.await.unwrap();
+
+      cache
+        .update(&mut async_ctx, |borrow, cx| {
+          // other rows may have come and gone while it loaded
+          let Some(position) = borrow.iter().position(|item| matches!(item, Element::Unresolved(haystack) if *haystack == row)) else {
+            return;
+          };
+
+          let mut replacement = loaded.into_iter().map(|message| Element::Resolved(Some(message))).collect::<Vec<_>>();
+
+          if let Some(above) = remaining {
+            replacement.push(Element::Gap(AsyncListGap { above, below: gap.below }));
+          }
+
+          // closing with nothing loaded shrinks the list, which the other loads in flight are fine with
+          borrow.splice(position..position + 1, replacement);
+
+          cx.notify();
+        })
+        .unwrap();
+    })
+    .detach();
+}
+
 impl<T: Channel + 'static> Render for MessageListComponent<T> {
   fn render(&mut self, cx: &mut gpui::ViewContext<Self>) -> impl gpui::IntoElement {
     self.update(cx);
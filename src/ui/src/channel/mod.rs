@@ -3,23 +3,36 @@ pub mod message_list;
 
 use std::sync::Arc;
 
+use chrono::{Local, NaiveDate, Utc};
 use components::input::{InputEvent, TextInput};
-use gpui::{div, ParentElement, Pixels, Render, Styled, View, VisualContext};
-use message_list::MessageListComponent;
-use scope_chat::channel::Channel;
+use gpui::{div, InteractiveElement, ParentElement, Pixels, Render, Styled, View, ViewContext, VisualContext};
+use message_list::{MessageListComponent, StartAt};
+use scope_chat::{
+  async_list::AsyncListItem,
+  channel::{Channel, ChannelEvent},
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::actions::{JumpToBeginning, JumpToPresent};
 
 pub struct ChannelView<C: Channel + 'static> {
   list_view: View<MessageListComponent<Arc<C>>>,
   message_input: View<TextInput>,
+  date_input: View<TextInput>,
 }
 
 impl<C: Channel + 'static> ChannelView<C> {
-  pub fn create(ctx: &mut gpui::ViewContext<'_, ChannelView<C>>, channel: Arc<C>) -> Self {
-    let channel_listener = channel.get_receiver();
+  pub fn create(
+    ctx: &mut gpui::ViewContext<'_, ChannelView<C>>,
+    channel: Arc<C>,
+    start_at: StartAt<<C::Message as AsyncListItem>::Identifier>,
+  ) -> Self {
+    let mut channel_listener = channel.get_receiver();
 
     let c2 = channel.clone();
+    let c3 = channel.clone();
 
-    let list_view = ctx.new_view(|cx| MessageListComponent::create(cx, channel, Pixels(30.)));
+    let list_view = ctx.new_view(|cx| MessageListComponent::create(cx, channel, Pixels(30.), start_at));
 
     let async_model = list_view.clone();
     let mut async_ctx = ctx.to_async();
@@ -30,22 +43,41 @@ impl<C: Channel + 'static> ChannelView<C> {
         loop {
           let (sender, receiver) = catty::oneshot();
 
-          let mut l = channel_listener.resubscribe();
-
+          // the same receiver each time round, so nothing sent in between is missed
           tokio::spawn(async move {
-            match sender.send(l.recv().await) {
-              Ok(_) => {}
-              Err(_e) => log::error!("Failed to send message data!"),
-            };
+            let event = channel_listener.recv().await;
+
+            let _ = sender.send((event, channel_listener));
           });
 
-          let message = receiver.await.unwrap().unwrap();
-          async_model
-            .update(&mut async_ctx, |data, ctx| {
-              data.append_message(ctx, message);
+          let (event, listener) = receiver.await.unwrap();
+
+          channel_listener = listener;
+
+          let updated = match event {
+            Ok(event) => async_model.update(&mut async_ctx, |data, ctx| {
+              match event {
+                ChannelEvent::Created(message) => data.append_message(ctx, message),
+                ChannelEvent::Updated(message) => data.update_message(ctx, message),
+                ChannelEvent::Deleted(identifier) => data.remove_messages(ctx, &[identifier]),
+                ChannelEvent::BulkDeleted(identifiers) => data.remove_messages(ctx, &identifiers),
+              }
               ctx.notify();
-            })
-            .unwrap();
+            }),
+            // the events that were missed could have changed anything that's shown
+            Err(RecvError::Lagged(missed)) => {
+              log::warn!("Missed {} channel events, reloading the channel", missed);
+
+              async_model.update(&mut async_ctx, |data, ctx| data.reload(ctx))
+            }
+            // the channel is gone, so nothing more will be sent
+            Err(RecvError::Closed) => return,
+          };
+
+          // the view was closed
+          if updated.is_err() {
+            return;
+          }
         }
       })
       .detach();
@@ -73,7 +105,7 @@ impl<C: Channel + 'static> ChannelView<C> {
           });
 
           let nonce = random_string::generate(20, random_string::charsets::ALPHANUMERIC);
-          let pending = c2.send_message(content, nonce);
+          let pending = c2.pending_message(content, nonce);
 
           let mut async_ctx = ctx.to_async();
 
@@ -84,7 +116,7 @@ impl<C: Channel + 'static> ChannelView<C> {
             .spawn(async move {
               async_model
                 .update(&mut async_ctx, |data, ctx| {
-                  data.append_message(ctx, pending);
+                  data.send_message(ctx, pending);
                   ctx.notify();
                 })
                 .unwrap();
@@ -96,18 +128,64 @@ impl<C: Channel + 'static> ChannelView<C> {
       })
       .detach();
 
-    ChannelView::<C> { list_view, message_input }
+    let date_input = ctx.new_view(|cx| {
+      let mut input = components::input::TextInput::new(cx);
+
+      input.set_placeholder("Jump to date (YYYY-MM-DD)", cx);
+
+      input
+    });
+
+    ctx
+      .subscribe(&date_input, move |view, text_input, input_event, ctx| {
+        if let InputEvent::PressEnter = input_event {
+          let text = text_input.read(ctx).text().to_string();
+
+          // the start of that day where the user is
+          let Some(time) =
+            NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)?.and_local_timezone(Local).earliest())
+          else {
+            log::warn!("{:?} isn't a date to jump to", text);
+            return;
+          };
+
+          let Some(identifier) = c3.identifier_at(time.with_timezone(&Utc)) else {
+            log::warn!("This channel can't jump to a date");
+            return;
+          };
+
+          text_input.update(ctx, |text_input, cx| {
+            text_input.set_text("", cx);
+          });
+
+          view.jump_to(StartAt::After(identifier), ctx);
+        }
+      })
+      .detach();
+
+    ChannelView::<C> {
+      list_view,
+      message_input,
+      date_input,
+    }
+  }
+
+  pub fn jump_to(&mut self, start_at: StartAt<<C::Message as AsyncListItem>::Identifier>, cx: &mut ViewContext<Self>) {
+    self.list_view.update(cx, |list, cx| list.jump_to(cx, start_at));
   }
 }
 
 impl<C: Channel + 'static> Render for ChannelView<C> {
-  fn render(&mut self, _: &mut gpui::ViewContext<Self>) -> impl gpui::IntoElement {
+  fn render(&mut self, cx: &mut gpui::ViewContext<Self>) -> impl gpui::IntoElement {
     div()
+      .on_action(cx.listener(|view, _: &JumpToBeginning, cx| view.jump_to(StartAt::Top, cx)))
+      .on_action(cx.listener(|view, _: &JumpToPresent, cx| view.jump_to(StartAt::Bottom, cx)))
       .flex()
       .flex_col()
       .w_full()
       .h_full()
       .p_6()
+      .child(div().w_64().pb_2().child(self.date_input.clone()))
       .child(div().w_full().h_full().flex().flex_col().child(self.list_view.clone()))
       .child(self.message_input.clone())
   }
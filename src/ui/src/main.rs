@@ -3,6 +3,8 @@ pub mod app;
 pub mod app_state;
 pub mod channel;
 pub mod menu;
+pub mod navigation;
+pub mod sidebar;
 
 use std::sync::Arc;
 
@@ -36,6 +38,9 @@ fn init(_: Arc<AppState>, cx: &mut AppContext) -> Result<()> {
     cx.bind_keys(vec![KeyBinding::new("ctrl-h", actions::Hide, None)]);
   }
 
+  cx.bind_keys(vec![KeyBinding::new("alt-home", actions::JumpToBeginning, None)]);
+  cx.bind_keys(vec![KeyBinding::new("alt-end", actions::JumpToPresent, None)]);
+
   cx.set_menus(app_menus());
 
   cx.on_action(|_: &actions::Quit, cx| cx.quit());
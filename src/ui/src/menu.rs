@@ -8,6 +8,13 @@ pub fn app_menus() -> Vec<Menu> {
       name: "Scope".into(),
       items: vec![MenuItem::action("Quit", actions::Quit)],
     },
+    Menu {
+      name: "Channel".into(),
+      items: vec![
+        MenuItem::action("Jump to Beginning", actions::JumpToBeginning),
+        MenuItem::action("Jump to Present", actions::JumpToPresent),
+      ],
+    },
     Menu {
       name: "Window".into(),
       items: vec![MenuItem::action("Hide", actions::Hide)],
@@ -0,0 +1,111 @@
+use std::{
+  collections::{hash_map::Entry, HashMap},
+  sync::Arc,
+};
+
+use gpui::View;
+use scope_backend_discord::{
+  channel::DiscordChannel, client::DiscordClient, connection::ConnectionState, guild::DiscordGuild, scheduler::RateLimit, snowflake::Snowflake,
+};
+use scope_chat::{
+  error::ChatError,
+  guild::{ChannelCategory, ChannelListing, Guild},
+};
+
+use crate::channel::{message_list::StartAt, ChannelView};
+
+/// Everything the sidebar needs to know about where the user is.
+///
+/// Channel views are kept around once opened, so switching back to a channel
+/// restores its scroll position and the messages it already loaded.
+#[derive(Default)]
+pub struct Navigation {
+  pub client: Option<Arc<DiscordClient>>,
+  pub guilds: Vec<DiscordGuild>,
+  pub direct_messages: Vec<ChannelListing<Snowflake>>,
+
+  /// `None` means the direct messages list is selected
+  pub selected_guild: Option<Snowflake>,
+  pub active_channel: Option<Snowflake>,
+  /// Why the active channel (or the client itself) couldn't be loaded
+  pub error: Option<ChatError>,
+  /// The rate limit that will be waited out last, if requests are being held back
+  pub rate_limit: Option<RateLimit>,
+  /// The gateway connection's state, once there is a client
+  pub connection: Option<ConnectionState>,
+
+  channel_views: HashMap<Snowflake, View<ChannelView<DiscordChannel>>>,
+  // channels being opened, with where the last request to open each one wants it to start
+  loading: HashMap<Snowflake, Option<StartAt<Snowflake>>>,
+  last_channel_in_guild: HashMap<Option<Snowflake>, Snowflake>,
+}
+
+impl Navigation {
+  pub fn categories(&self) -> Vec<ChannelCategory<Snowflake>> {
+    match self.selected_guild {
+      Some(guild_id) => self.guilds.iter().find(|guild| guild.get_identifier() == guild_id).map(|guild| guild.get_categories()).unwrap_or_default(),
+      None => vec![ChannelCategory {
+        identifier: None,
+        name: Some("Direct Messages".into()),
+        channels: self.direct_messages.clone(),
+      }],
+    }
+  }
+
+  pub fn guild_of(&self, channel: Snowflake) -> Option<Snowflake> {
+    self
+      .guilds
+      .iter()
+      .find(|guild| guild.get_categories().iter().any(|category| category.channels.iter().any(|listing| listing.identifier == channel)))
+      .map(|guild| guild.get_identifier())
+  }
+
+  /// Selects a guild, returning the channel that was last open in it, if any.
+  pub fn select_guild(&mut self, guild: Option<Snowflake>) -> Option<Snowflake> {
+    self.selected_guild = guild;
+
+    self.last_channel_in_guild.get(&guild).copied()
+  }
+
+  pub fn activate(&mut self, channel: Snowflake) {
+    self.active_channel = Some(channel);
+    self.error = None;
+    self.last_channel_in_guild.insert(self.selected_guild, channel);
+  }
+
+  pub fn view(&self, channel: Snowflake) -> Option<View<ChannelView<DiscordChannel>>> {
+    self.channel_views.get(&channel).cloned()
+  }
+
+  pub fn active_view(&self) -> Option<View<ChannelView<DiscordChannel>>> {
+    self.view(self.active_channel?)
+  }
+
+  /// Records that `channel` is being opened, returning false if it already was, in which case the load under way
+  /// starts at `start_at` instead, if one is given.
+  pub fn start_loading(&mut self, channel: Snowflake, start_at: Option<StartAt<Snowflake>>) -> bool {
+    match self.loading.entry(channel) {
+      Entry::Occupied(mut loading) => {
+        if start_at.is_some() {
+          loading.insert(start_at);
+        }
+
+        false
+      }
+      Entry::Vacant(loading) => {
+        loading.insert(start_at);
+
+        true
+      }
+    }
+  }
+
+  /// Where the channel that was being opened should start, now that it's done loading or has failed to.
+  pub fn finish_loading(&mut self, channel: Snowflake) -> Option<StartAt<Snowflake>> {
+    self.loading.remove(&channel).flatten()
+  }
+
+  pub fn insert_view(&mut self, channel: Snowflake, view: View<ChannelView<DiscordChannel>>) {
+    self.channel_views.insert(channel, view);
+  }
+}
@@ -0,0 +1,113 @@
+use gpui::{
+  div, prelude::FluentBuilder, rgb, ElementId, InteractiveElement, IntoElement, ParentElement, StatefulInteractiveElement, Styled, ViewContext,
+};
+use scope_backend_discord::snowflake::Snowflake;
+use scope_chat::{
+  guild::{ChannelKind, Guild},
+  message::IconRenderConfig,
+};
+
+use crate::{app::App, navigation::Navigation};
+
+pub fn guild_rail(navigation: &Navigation, cx: &ViewContext<App>) -> impl IntoElement {
+  let direct_messages = div()
+    .id("guild-rail-direct-messages")
+    .flex()
+    .flex_shrink_0()
+    .items_center()
+    .justify_center()
+    .w_12()
+    .h_12()
+    .rounded_full()
+    .text_sm()
+    .cursor_pointer()
+    .bg(rgb(0x2B2D31))
+    .hover(|s| s.bg(rgb(0x3F4248)))
+    .when(navigation.selected_guild.is_none(), |d| d.border_2().border_color(rgb(0xFFFFFF)))
+    .child("DMs")
+    .on_click(cx.listener(|app, _, cx| app.select_guild(None, cx)));
+
+  let guilds = navigation.guilds.iter().map(|guild| {
+    let guild_id = guild.get_identifier();
+
+    div()
+      .id(ElementId::NamedInteger("guild-rail-guild".into(), guild_id.0 as usize))
+      .flex_shrink_0()
+      .w_12()
+      .h_12()
+      .rounded_full()
+      .cursor_pointer()
+      .bg(rgb(0x2B2D31))
+      .when(navigation.selected_guild == Some(guild_id), |d| d.border_2().border_color(rgb(0xFFFFFF)))
+      .child(guild.get_icon(IconRenderConfig::small().with_size(64)))
+      .on_click(cx.listener(move |app, _, cx| app.select_guild(Some(guild_id), cx)))
+  });
+
+  div()
+    .id("guild-rail")
+    .flex()
+    .flex_col()
+    .flex_shrink_0()
+    .items_center()
+    .gap_2()
+    .p_3()
+    .h_full()
+    .overflow_y_scroll()
+    .text_color(rgb(0xFFFFFF))
+    .child(direct_messages)
+    .children(guilds)
+}
+
+fn channel_prefix(kind: ChannelKind) -> &'static str {
+  match kind {
+    ChannelKind::DirectMessage => "@ ",
+    ChannelKind::Announcement => "! ",
+    _ => "# ",
+  }
+}
+
+pub fn channel_sidebar(navigation: &Navigation, cx: &ViewContext<App>) -> impl IntoElement {
+  let active_channel = navigation.active_channel;
+
+  let categories = navigation.categories().into_iter().filter_map(|category| {
+    let channels = category.channels.into_iter().filter(|listing| listing.kind.is_text_based()).collect::<Vec<_>>();
+
+    if channels.is_empty() {
+      return None;
+    }
+
+    Some(
+      div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .pb_4()
+        .when_some(category.name, |d, name| {
+          d.child(div().px_2().text_xs().text_color(rgb(0xAFBAC7)).child(name.to_uppercase()))
+        })
+        .children(channels.into_iter().map(|listing| {
+          let channel_id: Snowflake = listing.identifier;
+
+          div()
+            .id(ElementId::NamedInteger("channel-sidebar-channel".into(), channel_id.0 as usize))
+            .px_2()
+            .py_1()
+            .rounded_md()
+            .text_sm()
+            .cursor_pointer()
+            .overflow_x_hidden()
+            .text_color(if active_channel == Some(channel_id) {
+              rgb(0xFFFFFF)
+            } else {
+              rgb(0xAFBAC7)
+            })
+            .when(active_channel == Some(channel_id), |d| d.bg(rgb(0x3F4248)))
+            .hover(|s| s.bg(rgb(0x35373C)))
+            .child(format!("{}{}", channel_prefix(listing.kind), listing.name))
+            .on_click(cx.listener(move |app, _, cx| app.open_channel(channel_id, cx)))
+        })),
+    )
+  });
+
+  div().id("channel-sidebar").flex().flex_col().flex_shrink_0().w_64().h_full().p_3().overflow_y_scroll().bg(rgb(0x1E1F22)).children(categories)
+}